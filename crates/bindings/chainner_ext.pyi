@@ -1,40 +1,109 @@
 from __future__ import annotations
 
 from enum import Enum
-from typing import Dict, List, Literal
+from typing import Callable, Dict, List, Literal
 
 import numpy as np
 
 # pylint: disable=unused-argument,missing-class-docstring,missing-function-docstring
 
+# Plain arrays are always read as interleaved (HWC) images. Planar (CHW) arrays
+# must be wrapped in a PlanarImage. Functions always return interleaved (HWC)
+# arrays, even for planar inputs.
+class PlanarImage:
+    def __init__(self, array: np.ndarray) -> None: ...
+    @property
+    def array(self) -> np.ndarray: ...
+
 def fill_alpha_fragment_blur(
-    img: np.ndarray, threshold: float, iterations: int, fragment_count: int
+    img: np.ndarray | PlanarImage,
+    threshold: float,
+    iterations: int,
+    fragment_count: int,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
 ) -> np.ndarray: ...
 def fill_alpha_extend_color(
-    img: np.ndarray, threshold: float, iterations: int
+    img: np.ndarray | PlanarImage,
+    threshold: float,
+    iterations: int,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
 ) -> np.ndarray: ...
 def fill_alpha_nearest_color(
-    img: np.ndarray, threshold: float, min_radius: int, anti_aliasing: bool
+    img: np.ndarray | PlanarImage,
+    threshold: float,
+    min_radius: int,
+    anti_aliasing: bool,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
 ) -> np.ndarray: ...
 def binary_threshold(
-    img: np.ndarray, threshold: float, anti_aliasing: bool, extra_smoothness: float = 0.0
+    img: np.ndarray | PlanarImage, threshold: float, anti_aliasing: bool, extra_smoothness: float = 0.0
 ) -> np.ndarray: ...
 def esdf(
-    img: np.ndarray, radius: float, cutoff: float, pre_process: bool, post_process: bool
+    img: np.ndarray | PlanarImage,
+    radius: float,
+    cutoff: float,
+    pre_process: bool,
+    post_process: bool,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def msdf(
+    img: np.ndarray | PlanarImage,
+    radius: float,
+    cutoff: float,
+    angle_threshold: float = 1.0,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def mtsdf(
+    img: np.ndarray | PlanarImage,
+    radius: float,
+    cutoff: float,
+    angle_threshold: float = 1.0,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
 ) -> np.ndarray: ...
-def pixel_art_upscale(img: np.ndarray, algorithm: str, scale: int) -> np.ndarray: ...
-def fast_gamma(img: np.ndarray, gamma: float) -> np.ndarray: ...
+def pixel_art_upscale(img: np.ndarray | PlanarImage, algorithm: str, scale: int) -> np.ndarray: ...
+def binary_threshold_inplace(
+    img: np.ndarray, threshold: float, anti_aliasing: bool, extra_smoothness: float = 0.0
+) -> None: ...
+def fast_gamma(img: np.ndarray | PlanarImage, gamma: float) -> np.ndarray: ...
+def fast_gamma_inplace(img: np.ndarray, gamma: float) -> None: ...
+
+# Channels
+
+def split_channels(img: np.ndarray | PlanarImage) -> List[np.ndarray]: ...
+def merge_channels(images: List[np.ndarray]) -> np.ndarray: ...
+def swizzle_channels(img: np.ndarray | PlanarImage, order: List[int]) -> np.ndarray: ...
+def add_alpha(img: np.ndarray | PlanarImage, alpha: float = 1.0) -> np.ndarray: ...
+def drop_alpha(img: np.ndarray | PlanarImage) -> np.ndarray: ...
 
 class UniformQuantization:
     @property
     def colors_per_channel(self) -> int: ...
     def __init__(self, colors_per_channel: int) -> None: ...
 
+class ColorDistance(Enum):
+    Rgb = 0
+    Oklab = 1
+    CieLab = 2
+    CieDe2000 = 3
+    Redmean = 4
+
 class PaletteQuantization:
     @property
     def channels(self) -> int: ...
     def colors(self) -> int: ...
-    def __init__(self, palette: np.ndarray) -> None: ...
+    @property
+    def distance(self) -> ColorDistance: ...
+    def __init__(
+        self,
+        palette: np.ndarray,
+        distance: ColorDistance = ColorDistance.Rgb,
+    ) -> None: ...
 
 class DiffusionAlgorithm(Enum):
     FloydSteinberg = 0
@@ -47,26 +116,73 @@ class DiffusionAlgorithm(Enum):
     SierraLite = 7
 
 def quantize(
-    img: np.ndarray,
+    img: np.ndarray | PlanarImage,
     quant: UniformQuantization | PaletteQuantization,
 ) -> np.ndarray: ...
+def quantize_inplace(img: np.ndarray, quant: UniformQuantization) -> None: ...
+class ThresholdMap(Enum):
+    Bayer = 0
+    ClusteredDot = 1
+    BlueNoise = 2
+
+def threshold_map(map: ThresholdMap, map_size: int) -> np.ndarray: ...
 def ordered_dither(
+    img: np.ndarray | PlanarImage,
+    quant: UniformQuantization | PaletteQuantization,
+    map_size: int,
+    map: ThresholdMap = ThresholdMap.Bayer,
+) -> np.ndarray: ...
+def ordered_dither_with_map(
+    img: np.ndarray | PlanarImage,
+    quant: UniformQuantization | PaletteQuantization,
+    threshold_map: np.ndarray,
+) -> np.ndarray: ...
+def ordered_dither_inplace(
     img: np.ndarray,
     quant: UniformQuantization,
     map_size: int,
-) -> np.ndarray: ...
+    map: ThresholdMap = ThresholdMap.Bayer,
+) -> None: ...
 def error_diffusion_dither(
-    img: np.ndarray,
+    img: np.ndarray | PlanarImage,
     quant: UniformQuantization | PaletteQuantization,
     algorithm: DiffusionAlgorithm,
+    border: BorderMode = BorderMode.Transparent,
+    serpentine: bool = False,
+    error_strength: float = 1.0,
+    error_clamp: float | None = None,
 ) -> np.ndarray: ...
 def riemersma_dither(
-    img: np.ndarray,
+    img: np.ndarray | PlanarImage,
     quant: UniformQuantization | PaletteQuantization,
     history_length: int,
     decay_ratio: float,
 ) -> np.ndarray: ...
 
+# Palette
+
+class PaletteAlgorithm(Enum):
+    MedianCut = 0
+    Octree = 1
+    Wu = 2
+
+def generate_palette(
+    img: np.ndarray | PlanarImage,
+    max_colors: int,
+    algorithm: PaletteAlgorithm,
+    kmeans_iterations: int = 0,
+) -> np.ndarray: ...
+
+class PaletteFormat(Enum):
+    Gpl = 0
+    Act = 1
+    Ase = 2
+    Pal = 3
+    Hex = 4
+
+def read_palette(data: bytes, format: PaletteFormat) -> np.ndarray: ...
+def write_palette(palette: np.ndarray, format: PaletteFormat) -> bytes: ...
+
 class ResizeFilter(Enum):
     Nearest = 0
     Box = 8
@@ -81,11 +197,181 @@ class ResizeFilter(Enum):
     Lagrange = 7
     Gauss = 5
 
+class ResizeKernel:
+    @property
+    def support(self) -> float: ...
+    @staticmethod
+    def cubic_bc(b: float, c: float) -> ResizeKernel: ...
+    @staticmethod
+    def lanczos(lobes: int) -> ResizeKernel: ...
+    @staticmethod
+    def gauss(sigma: float) -> ResizeKernel: ...
+    @staticmethod
+    def kaiser(lobes: int, beta: float) -> ResizeKernel: ...
+    @staticmethod
+    def blackman(lobes: int) -> ResizeKernel: ...
+    @staticmethod
+    def jinc(lobes: int) -> ResizeKernel: ...
+    @staticmethod
+    def custom(kernel: Callable[[float], float], support: float) -> ResizeKernel: ...
+
+class BorderMode(Enum):
+    Clamp = 0
+    Reflect = 1
+    Wrap = 2
+    Constant = 3
+    Transparent = 4
+
+class TransferFunction(Enum):
+    Srgb = 0
+    Rec709 = 1
+    Pq = 2
+    Hlg = 3
+
 def resize(
-    img: np.ndarray,
+    img: np.ndarray | PlanarImage,
+    new_size: tuple[int, int],
+    filter: ResizeFilter | ResizeKernel,
+    gamma_correction: bool | float | TransferFunction,
+    premultiply_alpha: bool = False,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+def warp(
+    img: np.ndarray | PlanarImage,
+    matrix: List[List[float]] | np.ndarray,
     new_size: tuple[int, int],
-    filter: ResizeFilter,
-    gamma_correction: bool,
+    filter: ResizeFilter | ResizeKernel,
+    border: BorderMode = BorderMode.Clamp,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+# Blur
+
+def gaussian_blur(
+    img: np.ndarray | PlanarImage,
+    sigma_x: float,
+    sigma_y: float | None = None,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def box_blur(
+    img: np.ndarray | PlanarImage,
+    radius_x: int,
+    radius_y: int | None = None,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def stack_blur(
+    img: np.ndarray | PlanarImage,
+    radius_x: int,
+    radius_y: int | None = None,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def bilateral_blur(
+    img: np.ndarray | PlanarImage,
+    sigma_space: float,
+    sigma_color: float,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+# Convolution
+
+def convolve(
+    img: np.ndarray | PlanarImage,
+    kernel: np.ndarray,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+# Sharpen
+
+def unsharp_mask(
+    img: np.ndarray | PlanarImage,
+    radius: float,
+    amount: float,
+    threshold: float = 0.0,
+    luminance_only: bool = False,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+def high_pass(
+    img: np.ndarray | PlanarImage,
+    radius: float,
+    luminance_only: bool = False,
+    border: BorderMode = BorderMode.Reflect,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+# Morphology
+
+class MorphOp(Enum):
+    Erode = 0
+    Dilate = 1
+    Open = 2
+    Close = 3
+    Gradient = 4
+    TopHat = 5
+    BlackHat = 6
+
+class MorphShape(Enum):
+    Square = 0
+    Disk = 1
+    Cross = 2
+
+def morphology(
+    img: np.ndarray | PlanarImage,
+    op: MorphOp,
+    shape: MorphShape,
+    radius: int,
+    threshold: float | None = None,
+    border: BorderMode = BorderMode.Transparent,
+    border_value: float | List[float] = 0.0,
+) -> np.ndarray: ...
+
+# Connected components
+
+class Region:
+    @property
+    def label(self) -> int: ...
+    @property
+    def area(self) -> int: ...
+    @property
+    def bbox(self) -> tuple[int, int, int, int]: ...
+    @property
+    def centroid(self) -> tuple[float, float]: ...
+
+def connected_components(
+    img: np.ndarray | PlanarImage,
+    threshold: float = 0.5,
+    connectivity: Literal[4, 8] = 8,
+) -> tuple[np.ndarray, List[Region]]: ...
+def remove_small_components(
+    img: np.ndarray | PlanarImage,
+    min_area: int,
+    threshold: float = 0.5,
+    connectivity: Literal[4, 8] = 8,
+) -> np.ndarray: ...
+
+# Flood fill
+
+def flood_fill(
+    img: np.ndarray | PlanarImage,
+    seed: tuple[int, int],
+    tolerance: float,
+    connectivity: Literal[4, 8] = 8,
+    distance: ColorDistance = ColorDistance.Rgb,
+) -> np.ndarray: ...
+def flood_fill_inplace(
+    img: np.ndarray,
+    seed: tuple[int, int],
+    tolerance: float,
+    color: List[float],
+    connectivity: Literal[4, 8] = 8,
+    distance: ColorDistance = ColorDistance.Rgb,
 ) -> np.ndarray: ...
 
 # Regex
@@ -126,7 +412,7 @@ class MatchGroup:
 class Clipboard:
     def write_text(self, text: str) -> None: ...
     def write_image(
-        self, image: np.ndarray, pixel_format: Literal["RGB", "BGR"]
+        self, image: np.ndarray | PlanarImage, pixel_format: Literal["RGB", "BGR"]
     ) -> None: ...
     @staticmethod
     def create_instance() -> Clipboard: ...
@@ -0,0 +1,140 @@
+use glam::{Vec2, Vec3A, Vec4};
+use image_core::{Flatten, FromFlat, Image};
+use image_ops::blur::BlurPixel;
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{LoadImage, PyImage},
+    IntoNumpy,
+};
+
+#[derive(Clone, Copy)]
+enum Blur {
+    Gaussian { sigma_x: f32, sigma_y: f32 },
+    Box { radius_x: usize, radius_y: usize },
+    Stack { radius_x: usize, radius_y: usize },
+    Bilateral { sigma_space: f32, sigma_color: f32 },
+}
+
+impl Blur {
+    fn apply<P: BlurPixel>(
+        self,
+        img: &Image<P>,
+        border: image_ops::util::BorderMode<P>,
+    ) -> Image<P> {
+        use image_ops::blur::*;
+
+        match self {
+            Blur::Gaussian { sigma_x, sigma_y } => {
+                gaussian_blur(img.view(), sigma_x, sigma_y, border)
+            }
+            Blur::Box { radius_x, radius_y } => box_blur(img.view(), radius_x, radius_y, border),
+            Blur::Stack { radius_x, radius_y } => {
+                stack_blur(img.view(), radius_x, radius_y, border)
+            }
+            Blur::Bilateral {
+                sigma_space,
+                sigma_color,
+            } => bilateral_blur(img.view(), sigma_space, sigma_color, border),
+        }
+    }
+
+    fn run<'py>(
+        self,
+        py: Python<'py>,
+        img: PyImage<'py>,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
+    ) -> PyResult<&'py PyArray3<f32>> {
+        let c = img.channels();
+        let border = Border::new(border, BorderMode::Reflect, border_value, c)?;
+
+        return match c {
+            1 => with_pixel_format::<f32>(py, img, self, border),
+            2 => with_pixel_format::<Vec2>(py, img, self, border),
+            3 => with_pixel_format::<Vec3A>(py, img, self, border),
+            4 => with_pixel_format::<Vec4>(py, img, self, border),
+            _ => Err(PyValueError::new_err(format!(
+                "Argument '{}' does not have the right shape. Expected 1, 2, 3, or 4 channels but found {}.",
+                stringify!(img),
+                c
+            ))),
+        };
+
+        fn with_pixel_format<'py, P>(
+            py: Python<'py>,
+            img: PyImage<'py>,
+            blur: Blur,
+            border: Border,
+        ) -> PyResult<&'py PyArray3<f32>>
+        where
+            P: BlurPixel + Flatten<Sample = f32> + FromFlat + 'static,
+        {
+            let img: Image<P> = img.load_image()?;
+            let border = border.for_pixel::<P>();
+            let result = py.allow_threads(|| blur.apply(&img, border).into_numpy());
+            Ok(result.into_pyarray(py))
+        }
+    }
+}
+
+/// Blurs the given image with a Gaussian kernel.
+#[pyfunction]
+pub fn gaussian_blur<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    sigma_x: f32,
+    sigma_y: Option<f32>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let sigma_y = sigma_y.unwrap_or(sigma_x);
+    Blur::Gaussian { sigma_x, sigma_y }.run(py, img, border, border_value)
+}
+
+/// Blurs the given image with a box kernel.
+#[pyfunction]
+pub fn box_blur<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    radius_x: usize,
+    radius_y: Option<usize>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let radius_y = radius_y.unwrap_or(radius_x);
+    Blur::Box { radius_x, radius_y }.run(py, img, border, border_value)
+}
+
+/// Blurs the given image with a stack blur, a fast approximation of a Gaussian blur.
+#[pyfunction]
+pub fn stack_blur<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    radius_x: usize,
+    radius_y: Option<usize>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let radius_y = radius_y.unwrap_or(radius_x);
+    Blur::Stack { radius_x, radius_y }.run(py, img, border, border_value)
+}
+
+/// Blurs the given image while preserving edges.
+#[pyfunction]
+pub fn bilateral_blur<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    sigma_space: f32,
+    sigma_color: f32,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    Blur::Bilateral {
+        sigma_space,
+        sigma_color,
+    }
+    .run(py, img, border, border_value)
+}
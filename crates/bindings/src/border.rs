@@ -0,0 +1,104 @@
+use image_core::FromFlat;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BorderMode {
+    Clamp = 0,
+    Reflect = 1,
+    Wrap = 2,
+    Constant = 3,
+    Transparent = 4,
+}
+
+#[derive(FromPyObject)]
+pub enum BorderValue {
+    Scalar(f32),
+    Color(Vec<f32>),
+}
+
+/// A border mode together with the border color of an image with a known
+/// number of channels.
+#[derive(Clone, Debug)]
+pub struct Border {
+    mode: BorderMode,
+    value: Vec<f32>,
+}
+
+impl Border {
+    pub fn new(
+        mode: Option<BorderMode>,
+        default: BorderMode,
+        value: Option<BorderValue>,
+        channels: usize,
+    ) -> PyResult<Self> {
+        let value = match value.unwrap_or(BorderValue::Scalar(0.0)) {
+            BorderValue::Scalar(v) => vec![v; channels],
+            BorderValue::Color(v) if v.len() == channels => v,
+            BorderValue::Color(v) => {
+                return Err(PyValueError::new_err(format!(
+                "Argument '{}' must have {} values for an image with {} channel(s), but has {}.",
+                stringify!(border_value),
+                channels,
+                channels,
+                v.len()
+            )))
+            }
+        };
+
+        Ok(Self {
+            mode: mode.unwrap_or(default),
+            value,
+        })
+    }
+
+    /// Applies the given function to the color channels of the border color.
+    ///
+    /// Same as the gamma functions of `image_ops`, the alpha channel of RGBA
+    /// colors is left unchanged.
+    pub fn map_color(mut self, f: impl Fn(f32) -> f32) -> Self {
+        let color_channels = if self.value.len() == 4 {
+            3
+        } else {
+            self.value.len()
+        };
+        for v in &mut self.value[..color_channels] {
+            *v = f(*v);
+        }
+        self
+    }
+
+    /// Returns the border mode for an image with pixels of type `P`.
+    ///
+    /// `P` must have as many channels as the image this border was created for.
+    pub fn for_pixel<P: FromFlat<Sample = f32> + Copy>(&self) -> image_ops::util::BorderMode<P> {
+        use image_ops::util::BorderMode as B;
+
+        match self.mode {
+            BorderMode::Clamp => B::Clamp,
+            BorderMode::Reflect => B::Reflect,
+            BorderMode::Wrap => B::Wrap,
+            BorderMode::Transparent => B::Transparent,
+            BorderMode::Constant => {
+                let value = match P::from_flat_slice(&self.value, self.value.len()) {
+                    Ok(value) => value[0],
+                    Err(_) => unreachable!("the border value has as many channels as the image"),
+                };
+                B::Constant(value)
+            }
+        }
+    }
+
+    /// Returns the border mode for an image with any number of channels.
+    pub fn for_ndim(&self) -> image_ops::util::BorderMode<&[f32]> {
+        use image_ops::util::BorderMode as B;
+
+        match self.mode {
+            BorderMode::Clamp => B::Clamp,
+            BorderMode::Reflect => B::Reflect,
+            BorderMode::Wrap => B::Wrap,
+            BorderMode::Transparent => B::Transparent,
+            BorderMode::Constant => B::Constant(&self.value),
+        }
+    }
+}
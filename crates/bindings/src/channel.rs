@@ -0,0 +1,89 @@
+use image_core::{NDimCow, NDimView};
+use image_ops::channel::ChannelError;
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::convert::{IntoNumpy, LoadImage, PyImage};
+
+fn to_py_err(err: ChannelError) -> PyErr {
+    match err {
+        ChannelError::Empty => PyValueError::new_err("Expected at least one image."),
+        ChannelError::SizeMismatch { expected, actual } => PyValueError::new_err(format!(
+            "All images must have the same size. Expected {}x{}, got {}x{}.",
+            expected.width, expected.height, actual.width, actual.height
+        )),
+        ChannelError::ChannelOutOfBounds { channel, channels } => PyValueError::new_err(format!(
+            "Channel {} does not exist in an image with {} channel(s).",
+            channel, channels
+        )),
+        ChannelError::NoAlpha { channels } => PyValueError::new_err(format!(
+            "An image with {} channel(s) does not have an alpha channel.",
+            channels
+        )),
+    }
+}
+
+/// Splits the given image into single-channel images.
+#[pyfunction]
+pub fn split_channels<'py>(py: Python<'py>, img: PyImage) -> PyResult<Vec<&'py PyArray3<f32>>> {
+    let img: NDimCow = img.load_image()?;
+    let result = py.allow_threads(|| {
+        image_ops::channel::split_ndim(img.view())
+            .into_iter()
+            .map(|c| c.into_numpy())
+            .collect::<Vec<_>>()
+    });
+    Ok(result.into_iter().map(|c| c.into_pyarray(py)).collect())
+}
+
+/// Merges the channels of all given images into one image.
+#[pyfunction]
+pub fn merge_channels<'py>(py: Python<'py>, images: Vec<PyImage>) -> PyResult<&'py PyArray3<f32>> {
+    let images = images
+        .iter()
+        .map(|img| img.load_image())
+        .collect::<PyResult<Vec<NDimCow>>>()?;
+    let result = py.allow_threads(|| {
+        let views: Vec<NDimView> = images.iter().map(|img| img.view()).collect();
+        image_ops::channel::merge_ndim(&views).map(|r| r.into_numpy())
+    });
+    Ok(result.map_err(to_py_err)?.into_pyarray(py))
+}
+
+/// Reorders the channels of the given image. Channel `i` of the result is
+/// channel `order[i]` of the given image.
+#[pyfunction]
+pub fn swizzle_channels<'py>(
+    py: Python<'py>,
+    img: PyImage,
+    order: Vec<usize>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let img: NDimCow = img.load_image()?;
+    let result = py.allow_threads(|| {
+        image_ops::channel::swizzle_ndim(img.view(), &order).map(|r| r.into_numpy())
+    });
+    Ok(result.map_err(to_py_err)?.into_pyarray(py))
+}
+
+/// Adds an alpha channel to the given grayscale or RGB image.
+#[pyfunction]
+pub fn add_alpha<'py>(
+    py: Python<'py>,
+    img: PyImage,
+    alpha: Option<f32>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let img: NDimCow = img.load_image()?;
+    let result = py.allow_threads(|| {
+        image_ops::channel::add_alpha_ndim(img.view(), alpha.unwrap_or(1.0)).into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
+
+/// Removes the alpha channel of the given gray + alpha or RGBA image.
+#[pyfunction]
+pub fn drop_alpha<'py>(py: Python<'py>, img: PyImage) -> PyResult<&'py PyArray3<f32>> {
+    let img: NDimCow = img.load_image()?;
+    let result = py
+        .allow_threads(|| image_ops::channel::drop_alpha_ndim(img.view()).map(|r| r.into_numpy()));
+    Ok(result.map_err(to_py_err)?.into_pyarray(py))
+}
@@ -1,7 +1,5 @@
-use image_core::{
-    util::{slice_as_chunks, vec_into_flattened},
-    NDimCow, NDimView,
-};
+use image_core::{NDimCow, NDimView};
+use image_ops::channel::{add_alpha_ndim, swizzle_ndim};
 use pyo3::{exceptions::PyValueError, prelude::*};
 use std::{
     borrow::Cow,
@@ -75,49 +73,13 @@ fn to_image_data(
     let height = image.height();
     let channels = image.channels();
 
-    fn to_u8(v: f32) -> u8 {
-        (v * 255. + 0.5).floor().clamp(0.0, 255.0) as u8
-    }
-
-    let bytes: Vec<[u8; 4]> = match channels {
-        1 => image
-            .data()
-            .iter()
-            .map(|v| {
-                let v = to_u8(*v);
-                [v, v, v, 255]
-            })
-            .collect(),
-        3 => {
-            let (data, rest) = slice_as_chunks::<f32, 3>(image.data());
-            assert!(rest.is_empty());
-
-            match pixel_format {
-                PixelFormat::Rgb => data
-                    .iter()
-                    .map(|[r, g, b]| [to_u8(*r), to_u8(*g), to_u8(*b), 255])
-                    .collect(),
-                PixelFormat::Bgr => data
-                    .iter()
-                    .map(|[b, g, r]| [to_u8(*r), to_u8(*g), to_u8(*b), 255])
-                    .collect(),
-            }
-        }
-        4 => {
-            let (data, rest) = slice_as_chunks::<f32, 4>(image.data());
-            assert!(rest.is_empty());
-
-            match pixel_format {
-                PixelFormat::Rgb => data
-                    .iter()
-                    .map(|[r, g, b, a]| [to_u8(*r), to_u8(*g), to_u8(*b), to_u8(*a)])
-                    .collect(),
-                PixelFormat::Bgr => data
-                    .iter()
-                    .map(|[b, g, r, a]| [to_u8(*r), to_u8(*g), to_u8(*b), to_u8(*a)])
-                    .collect(),
-            }
-        }
+    // arboard expects 8-bit RGBA
+    let order: &[usize] = match (channels, pixel_format) {
+        (1, _) => &[0, 0, 0],
+        (3, PixelFormat::Rgb) => &[0, 1, 2],
+        (3, PixelFormat::Bgr) => &[2, 1, 0],
+        (4, PixelFormat::Rgb) => &[0, 1, 2, 3],
+        (4, PixelFormat::Bgr) => &[2, 1, 0, 3],
         _ => {
             return Err(PyValueError::new_err(format!(
                 "Invalid number of channels: {}",
@@ -126,10 +88,17 @@ fn to_image_data(
         }
     };
 
+    let image = image.convert::<u8>();
+    let rgb = swizzle_ndim(image.view(), order).expect("Expected valid channel order");
+    let rgba = match rgb.channels() {
+        4 => rgb,
+        _ => add_alpha_ndim(rgb.view(), u8::MAX),
+    };
+
     let image = arboard::ImageData {
         width,
         height,
-        bytes: Cow::Owned(vec_into_flattened(bytes)),
+        bytes: Cow::Owned(rgba.take()),
     };
 
     Ok(image)
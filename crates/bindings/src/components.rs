@@ -0,0 +1,106 @@
+use image_core::Image;
+use image_ops::components::{self as c, Connectivity};
+use numpy::{ndarray::Array2, IntoPyArray, PyArray2, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::convert::{IntoNumpy, LoadImage, PyImage};
+
+/// The statistics of a connected component.
+#[pyclass(frozen)]
+#[derive(Clone)]
+pub struct Region {
+    inner: c::Region,
+}
+
+#[pymethods]
+impl Region {
+    #[getter]
+    pub fn label(&self) -> u32 {
+        self.inner.label
+    }
+    #[getter]
+    pub fn area(&self) -> usize {
+        self.inner.area
+    }
+    /// The bounding box as `(x, y, width, height)`.
+    #[getter]
+    pub fn bbox(&self) -> (usize, usize, usize, usize) {
+        let r = &self.inner;
+        (r.x, r.y, r.width, r.height)
+    }
+    /// The average `(x, y)` coordinate of all pixels of the component.
+    #[getter]
+    pub fn centroid(&self) -> (f32, f32) {
+        self.inner.centroid
+    }
+}
+
+pub fn to_connectivity(connectivity: Option<u32>) -> PyResult<Connectivity> {
+    match connectivity.unwrap_or(8) {
+        4 => Ok(Connectivity::Four),
+        8 => Ok(Connectivity::Eight),
+        other => Err(PyValueError::new_err(format!(
+            "Argument '{}' must be 4 or 8, but is {}.",
+            stringify!(connectivity),
+            other
+        ))),
+    }
+}
+
+fn load_mask(img: PyImage) -> PyResult<Image<f32>> {
+    if img.channels() != 1 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must have 1 channel, but has {}.",
+            stringify!(img),
+            img.channels()
+        )));
+    }
+    img.load_image()
+}
+
+/// Labels the connected components of all pixels above the threshold.
+///
+/// Returns the label of each pixel (0 for the background) and the statistics
+/// of each component.
+#[pyfunction]
+pub fn connected_components<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    threshold: Option<f32>,
+    connectivity: Option<u32>,
+) -> PyResult<(&'py PyArray2<u32>, Vec<Region>)> {
+    let connectivity = to_connectivity(connectivity)?;
+    let threshold = threshold.unwrap_or(0.5);
+    let img = load_mask(img)?;
+
+    let (labels, regions) = py.allow_threads(|| {
+        let components = c::label_components(img.view(), threshold, connectivity);
+        let size = components.labels.size();
+        let labels = Array2::from_shape_vec((size.height, size.width), components.labels.take())
+            .expect("the label image has the size of the image");
+        (labels, components.regions)
+    });
+
+    let regions = regions.into_iter().map(|inner| Region { inner }).collect();
+    Ok((labels.into_pyarray(py), regions))
+}
+
+/// Sets all pixels of components smaller than `min_area` pixels to 0.
+#[pyfunction]
+pub fn remove_small_components<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    min_area: usize,
+    threshold: Option<f32>,
+    connectivity: Option<u32>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let connectivity = to_connectivity(connectivity)?;
+    let threshold = threshold.unwrap_or(0.5);
+    let mut img = load_mask(img)?;
+
+    let result = py.allow_threads(|| {
+        c::remove_small_components(&mut img, threshold, connectivity, min_area);
+        img.into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
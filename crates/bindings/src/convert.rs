@@ -1,17 +1,51 @@
 use image_core::{
     util::slice_as_chunks, FromFlat, Image, ImageView, IntoPixels, NDimCow, NDimImage, NDimView,
-    Shape, ShapeMismatch, Size,
+    NDimViewMut, PlanarView, Shape, ShapeMismatch, Size,
 };
 use numpy::{
     ndarray::{Array3, Dimension},
-    Ix3, PyReadonlyArray, PyReadonlyArray2, PyReadonlyArray3,
+    Ix3, PyArray3, PyReadonlyArray, PyReadonlyArray2, PyReadonlyArray3, PyReadwriteArray2,
+    PyReadwriteArray3,
 };
-use pyo3::{exceptions::PyValueError, FromPyObject, PyResult};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// A planar (CHW) image.
+///
+/// Numpy arrays are always interpreted as interleaved (HWC) images, so planar
+/// arrays have to be wrapped in this class to be passed to functions that
+/// take images. Functions always return interleaved (HWC) arrays, even for
+/// planar inputs.
+#[pyclass]
+pub struct PlanarImage {
+    array: Py<PyArray3<f32>>,
+}
+
+#[pymethods]
+impl PlanarImage {
+    #[new]
+    fn new(array: Py<PyArray3<f32>>) -> Self {
+        Self { array }
+    }
+
+    /// The wrapped CHW array.
+    #[getter]
+    fn array(&self, py: Python<'_>) -> Py<PyArray3<f32>> {
+        self.array.clone_ref(py)
+    }
+}
+
+fn extract_planar(ob: &PyAny) -> PyResult<PyReadonlyArray3<'_, f32>> {
+    let planar: PyRef<PlanarImage> = ob.extract()?;
+    let array = planar.array.clone_ref(ob.py()).into_ref(ob.py());
+    Ok(array.try_readonly()?)
+}
 
 #[derive(FromPyObject)]
 pub enum PyImage<'py> {
     D2(PyReadonlyArray2<'py, f32>),
     D3(PyReadonlyArray3<'py, f32>),
+    /// A CHW array wrapped in a [`PlanarImage`].
+    Planar(#[pyo3(from_py_with = "extract_planar")] PyReadonlyArray3<'py, f32>),
 }
 
 impl PyImage<'_> {
@@ -25,6 +59,10 @@ impl PyImage<'_> {
                 let shape = img.shape();
                 Shape::new(shape[1], shape[0], shape[2])
             }
+            PyImage::Planar(img) => {
+                let shape = img.shape();
+                Shape::new(shape[2], shape[1], shape[0])
+            }
         }
     }
     pub fn channels(&self) -> usize {
@@ -53,11 +91,27 @@ impl PyImage<'_> {
                     }
                 }
             }
+            PyImage::Planar(_) => {}
         };
 
         None
     }
 
+    /// Tries to create a view of a planar (CHW) image.
+    ///
+    /// This is only possible if the image is planar and C-contiguous.
+    pub fn try_planar_view(&'_ self) -> Option<PlanarView<'_>> {
+        if let PyImage::Planar(img) = self {
+            if img.is_c_contiguous() {
+                if let Ok(data) = img.as_slice() {
+                    return Some(PlanarView::new(self.shape(), data));
+                }
+            }
+        }
+
+        None
+    }
+
     /// Creates a view of the image of possible. If not possible, it will copy
     /// the image into a Vec.
     pub fn as_contiguous(&'_ self) -> NDimCow<'_> {
@@ -65,8 +119,23 @@ impl PyImage<'_> {
             return view.into();
         }
 
+        if let Some(planar) = self.try_planar_view() {
+            return planar.to_interleaved().into();
+        }
+
         let shape = self.shape();
 
+        if let PyImage::D3(img) = self {
+            // Planar (CHW) arrays that were transposed to HWC (e.g. via
+            // `np.transpose(img, (1, 2, 0))` or `tensor.permute(1, 2, 0)`) are
+            // not C-contiguous, but they can still be interleaved a lot faster
+            // than iterating over the array element by element.
+            let planar = img.as_array().permuted_axes([2, 0, 1]);
+            if let Some(data) = planar.as_slice() {
+                return PlanarView::new(shape, data).to_interleaved().into();
+            }
+        }
+
         fn to_vec<D: Dimension>(img: &PyReadonlyArray<f32, D>) -> Vec<f32> {
             img.as_array().iter().copied().collect()
         }
@@ -74,6 +143,57 @@ impl PyImage<'_> {
         match self {
             PyImage::D2(img) => NDimImage::new(shape, to_vec(img)).into(),
             PyImage::D3(img) => NDimImage::new(shape, to_vec(img)).into(),
+            PyImage::Planar(img) => {
+                let hwc = img.as_array().permuted_axes([1, 2, 0]);
+                NDimImage::new(shape, hwc.iter().copied().collect()).into()
+            }
+        }
+    }
+}
+
+/// A numpy array that will be modified in place.
+#[derive(FromPyObject)]
+pub enum PyImageMut<'py> {
+    D2(PyReadwriteArray2<'py, f32>),
+    D3(PyReadwriteArray3<'py, f32>),
+}
+
+impl PyImageMut<'_> {
+    pub fn shape(&self) -> Shape {
+        match self {
+            PyImageMut::D2(img) => {
+                let shape = img.shape();
+                Shape::new(shape[1], shape[0], 1)
+            }
+            PyImageMut::D3(img) => {
+                let shape = img.shape();
+                Shape::new(shape[1], shape[0], shape[2])
+            }
+        }
+    }
+
+    /// Creates a mutable view of the image.
+    ///
+    /// Since the image is modified in place, this is only possible if the
+    /// image is C-contiguous.
+    pub fn view_mut(&mut self) -> PyResult<NDimViewMut<'_>> {
+        let shape = self.shape();
+        let data = match self {
+            PyImageMut::D2(img) => match img.is_c_contiguous() {
+                true => img.as_slice_mut().ok(),
+                false => None,
+            },
+            PyImageMut::D3(img) => match img.is_c_contiguous() {
+                true => img.as_slice_mut().ok(),
+                false => None,
+            },
+        };
+
+        match data {
+            Some(data) => Ok(NDimViewMut::new(shape, data)),
+            None => Err(PyValueError::new_err(
+                "Images can only be modified in place if they are C-contiguous.",
+            )),
         }
     }
 }
@@ -109,7 +229,7 @@ impl<'py> LoadImage<NDimImage> for &'py PyImage<'py> {
 }
 impl<'py, T> LoadImage<Image<T>> for &'py PyImage<'py>
 where
-    T: FromFlat,
+    T: FromFlat<Sample = f32>,
 {
     fn load_image(self) -> PyResult<Image<T>> {
         let cow = self.as_contiguous();
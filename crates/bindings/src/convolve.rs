@@ -0,0 +1,45 @@
+use image_core::{Image, NDimCow};
+use image_ops::convolve::{Kernel, KernelError};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{LoadImage, PyImage},
+    IntoNumpy,
+};
+
+/// Convolves each channel of the given image with the given 2D kernel.
+#[pyfunction]
+pub fn convolve<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    kernel: PyImage<'py>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    if kernel.channels() != 1 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be a 2D array, but has {} channels.",
+            stringify!(kernel),
+            kernel.channels()
+        )));
+    }
+    let kernel: Image<f32> = kernel.load_image()?;
+    let kernel = Kernel::new(kernel).map_err(|e| match e {
+        KernelError::Empty => {
+            PyValueError::new_err(format!("Argument '{}' is empty.", stringify!(kernel)))
+        }
+        KernelError::NonFinite => PyValueError::new_err(format!(
+            "Argument '{}' must only contain finite values.",
+            stringify!(kernel)
+        )),
+    })?;
+
+    let img: NDimCow = img.load_image()?;
+    let border = Border::new(border, BorderMode::Reflect, border_value, img.channels())?;
+    let result = py.allow_threads(|| {
+        image_ops::convolve::convolve(img.view(), &kernel, border.for_ndim()).into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
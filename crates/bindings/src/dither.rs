@@ -9,7 +9,10 @@ use image_ops::{
 use numpy::{IntoPyArray, PyArray3};
 use pyo3::{exceptions::PyValueError, prelude::*};
 
-use crate::convert::{IntoNumpy, LoadImage, PyImage};
+use crate::{
+    border::BorderMode,
+    convert::{IntoNumpy, LoadImage, PyImage, PyImageMut},
+};
 
 #[pyclass(frozen)]
 #[derive(Clone, PartialEq, Debug)]
@@ -39,16 +42,65 @@ impl UniformQuantization {
     }
 }
 
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorDistance {
+    Rgb = 0,
+    Oklab = 1,
+    CieLab = 2,
+    CieDe2000 = 3,
+    Redmean = 4,
+}
+
+impl<P> ColorSpace<P> for ColorDistance
+where
+    P: ColorAlpha,
+    RGB: ColorSpace<P, Coord = P::Coord>,
+{
+    type Coord = P::Coord;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord {
+        match self {
+            ColorDistance::Rgb => RGB.get_coordinate(color),
+            ColorDistance::Oklab => Oklab.get_coordinate(color),
+            ColorDistance::CieLab => CieLab.get_coordinate(color),
+            ColorDistance::CieDe2000 => CieDe2000.get_coordinate(color),
+            ColorDistance::Redmean => Redmean.get_coordinate(color),
+        }
+    }
+
+    fn distance_2(&self, a: &Self::Coord, b: &Self::Coord) -> f32 {
+        match self {
+            ColorDistance::Rgb => ColorSpace::<P>::distance_2(&RGB, a, b),
+            ColorDistance::Oklab => ColorSpace::<P>::distance_2(&Oklab, a, b),
+            ColorDistance::CieLab => ColorSpace::<P>::distance_2(&CieLab, a, b),
+            ColorDistance::CieDe2000 => ColorSpace::<P>::distance_2(&CieDe2000, a, b),
+            ColorDistance::Redmean => ColorSpace::<P>::distance_2(&Redmean, a, b),
+        }
+    }
+
+    fn is_euclidean(&self) -> bool {
+        match self {
+            ColorDistance::Rgb => ColorSpace::<P>::is_euclidean(&RGB),
+            ColorDistance::Oklab => ColorSpace::<P>::is_euclidean(&Oklab),
+            ColorDistance::CieLab => ColorSpace::<P>::is_euclidean(&CieLab),
+            ColorDistance::CieDe2000 => ColorSpace::<P>::is_euclidean(&CieDe2000),
+            ColorDistance::Redmean => ColorSpace::<P>::is_euclidean(&Redmean),
+        }
+    }
+}
+
 #[pyclass(frozen)]
 #[derive(Clone)]
 pub struct PaletteQuantization {
     palette: Arc<NDimImage>,
+    distance: ColorDistance,
 }
 
 #[pymethods]
 impl PaletteQuantization {
     #[new]
-    pub fn new(palette: PyImage) -> PyResult<Self> {
+    pub fn new(palette: PyImage, distance: Option<ColorDistance>) -> PyResult<Self> {
         let palette: NDimImage = palette.load_image()?;
         if palette.height() != 1 {
             return Err(PyValueError::new_err(format!(
@@ -83,6 +135,7 @@ impl PaletteQuantization {
 
         Ok(Self {
             palette: Arc::new(palette),
+            distance: distance.unwrap_or(ColorDistance::Rgb),
         })
     }
 
@@ -95,13 +148,18 @@ impl PaletteQuantization {
     pub fn colors(&self) -> u32 {
         self.palette.width() as u32
     }
+
+    #[getter]
+    pub fn distance(&self) -> ColorDistance {
+        self.distance
+    }
 }
 
 impl PaletteQuantization {
     fn into_quantizer<P>(self) -> impl Quantizer<P, P>
     where
-        P: Pixel + std::ops::Sub<Output = P> + FromFlat,
-        RGB: ColorSpace<P>,
+        P: Pixel + std::ops::Sub<Output = P> + FromFlat<Sample = f32> + ColorAlpha,
+        RGB: ColorSpace<P, Coord = P::Coord>,
         BoundError: ErrorCombinator<P>,
     {
         let ndim = NDimImage::new(self.palette.shape(), self.palette.data().to_vec());
@@ -109,7 +167,7 @@ impl PaletteQuantization {
             .into_pixels()
             .expect("Expected shape of palette to match.");
 
-        ColorPalette::new(RGB, img.take(), BoundError)
+        ColorPalette::new(self.distance, img.take(), BoundError)
     }
 }
 
@@ -142,7 +200,7 @@ pub fn quantize<'py>(
         Quant::Uniform(quant) => {
             let mut img: NDimImage = img.load_image()?;
             let result = py.allow_threads(|| {
-                image_ops::dither::quantize_ndim(&mut img, quant.inner);
+                image_ops::dither::quantize_ndim(img.view_mut(), quant.inner);
                 img.into_numpy()
             });
             Ok(result.into_pyarray(py))
@@ -154,12 +212,12 @@ pub fn quantize<'py>(
                 quant: impl Quantizer<P, P> + Sync,
             ) -> PyResult<&'py PyArray3<f32>>
             where
-                P: Pixel + Send + FromFlat,
+                P: Pixel + Send + FromFlat<Sample = f32>,
                 Image<P>: IntoNumpy,
             {
                 let mut img: Image<P> = img.load_image()?;
                 let result = py.allow_threads(|| {
-                    image_ops::dither::quantize(&mut img, &quant);
+                    image_ops::dither::quantize(img.view_mut(), &quant);
                     img.into_numpy()
                 });
                 Ok(result.into_pyarray(py))
@@ -180,26 +238,199 @@ pub fn quantize<'py>(
     }
 }
 
+/// Same as `quantize` with a uniform quantization, but modifies the given image in place.
+#[pyfunction]
+pub fn quantize_inplace(
+    py: Python<'_>,
+    mut img: PyImageMut,
+    quant: UniformQuantization,
+) -> PyResult<()> {
+    let img = img.view_mut()?;
+    py.allow_threads(|| {
+        image_ops::dither::quantize_ndim(img, quant.inner);
+    });
+    Ok(())
+}
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ThresholdMap {
+    Bayer = 0,
+    ClusteredDot = 1,
+    BlueNoise = 2,
+}
+
+/// The largest supported size of generated Bayer and clustered dot maps.
+/// Larger maps would take up a lot of memory and have more levels than an f32
+/// can tell apart.
+const MAX_THRESHOLD_MAP_SIZE: u32 = 1024;
+/// The largest supported size of generated blue noise maps, since their
+/// generation time grows with the 4th power of the size.
+const MAX_BLUE_NOISE_SIZE: u32 = 128;
+
+fn create_threshold_map(map: ThresholdMap, map_size: u32) -> PyResult<Image<f32>> {
+    if map_size < 1 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be at least 1.",
+            stringify!(map_size)
+        )));
+    }
+    let (max_size, name) = match map {
+        ThresholdMap::Bayer => (MAX_THRESHOLD_MAP_SIZE, "Bayer matrices"),
+        ThresholdMap::ClusteredDot => (MAX_THRESHOLD_MAP_SIZE, "clustered dots"),
+        ThresholdMap::BlueNoise => (MAX_BLUE_NOISE_SIZE, "blue noise"),
+    };
+    if map_size > max_size {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be at most {} for {}.",
+            stringify!(map_size),
+            max_size,
+            name
+        )));
+    }
+
+    let n = map_size as usize;
+    match map {
+        ThresholdMap::Bayer => {
+            if !is_bayer_size(n) {
+                return Err(PyValueError::new_err(format!(
+                    "Argument '{}' must be a product of 2s and 3s for Bayer matrices.",
+                    stringify!(map_size)
+                )));
+            }
+            Ok(bayer_threshold_map(n))
+        }
+        ThresholdMap::ClusteredDot => Ok(clustered_dot_threshold_map(n)),
+        ThresholdMap::BlueNoise => Ok(blue_noise_threshold_map(n)),
+    }
+}
+
+/// Returns the threshold map of the given kind and size as a single-channel
+/// image.
+#[pyfunction]
+pub fn threshold_map<'py>(
+    py: Python<'py>,
+    map: ThresholdMap,
+    map_size: u32,
+) -> PyResult<&'py PyArray3<f32>> {
+    let result = py.allow_threads(|| create_threshold_map(map, map_size))?;
+    Ok(result.into_numpy().into_pyarray(py))
+}
+
+fn ordered_dither_impl<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    quant: Quant,
+    map: Image<f32>,
+) -> PyResult<&'py PyArray3<f32>> {
+    match quant {
+        Quant::Uniform(quant) => {
+            let mut img: NDimImage = img.load_image()?;
+            let result = py.allow_threads(|| {
+                image_ops::dither::ordered_dither_with_map(img.view_mut(), &map, quant.inner);
+                img.into_numpy()
+            });
+            Ok(result.into_pyarray(py))
+        }
+        Quant::Palette(quant) => {
+            fn with_pixel_format<'py, P>(
+                py: Python<'py>,
+                img: PyImage<'py>,
+                quant: impl Quantizer<P, P> + Sync,
+                map: Image<f32>,
+            ) -> PyResult<&'py PyArray3<f32>>
+            where
+                P: Pixel + ColorAlpha + Send + FromFlat<Sample = f32>,
+                Image<P>: IntoNumpy,
+            {
+                // more candidates than that rarely make a difference
+                let candidates = map.len().min(64);
+
+                let mut img: Image<P> = img.load_image()?;
+                let result = py.allow_threads(|| {
+                    image_ops::dither::ordered_dither_palette(
+                        img.view_mut(),
+                        &map,
+                        &quant,
+                        candidates,
+                    );
+                    img.into_numpy()
+                });
+                Ok(result.into_pyarray(py))
+            }
+
+            let c = img.channels();
+            match c {
+                1 => with_pixel_format::<f32>(py, img, quant.into_quantizer(), map),
+                3 => with_pixel_format::<Vec3A>(py, img, quant.into_quantizer(), map),
+                4 => with_pixel_format::<Vec4>(py, img, quant.into_quantizer(), map),
+                _ => Err(PyValueError::new_err(format!(
+                        "Argument '{}' does not have the right shape. Expected 1, 3, or 4 channels but found {}.",
+                        stringify!(img),
+                        c
+                    ))),
+            }
+        }
+    }
+}
+
 #[pyfunction]
 pub fn ordered_dither<'py>(
     py: Python<'py>,
-    img: PyImage,
-    quant: UniformQuantization,
+    img: PyImage<'py>,
+    quant: Quant,
     map_size: u32,
+    map: Option<ThresholdMap>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let map = create_threshold_map(map.unwrap_or(ThresholdMap::Bayer), map_size)?;
+    ordered_dither_impl(py, img, quant, map)
+}
+
+/// Same as `ordered_dither`, but uses the given single-channel image as the
+/// threshold map. Only the order of its values matters, since they are ranked
+/// to be uniformly distributed.
+#[pyfunction]
+pub fn ordered_dither_with_map<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    quant: Quant,
+    threshold_map: PyImage<'py>,
 ) -> PyResult<&'py PyArray3<f32>> {
-    if !map_size.is_power_of_two() {
+    if threshold_map.channels() != 1 {
         return Err(PyValueError::new_err(format!(
-            "Argument '{}' must be a power of 2.",
-            stringify!(map_size)
+            "Argument '{}' must have 1 channel, but has {}.",
+            stringify!(threshold_map),
+            threshold_map.channels()
+        )));
+    }
+    let map: Image<f32> = threshold_map.load_image()?;
+    if map.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must not be empty.",
+            stringify!(threshold_map)
         )));
     }
 
-    let mut img = img.load_image()?;
-    let result = py.allow_threads(|| {
-        image_ops::dither::ordered_dither(&mut img, map_size as usize, quant.inner);
-        img.into_numpy()
+    let map = py.allow_threads(|| threshold_map_from_image(&map));
+    ordered_dither_impl(py, img, quant, map)
+}
+
+/// Same as `ordered_dither` with a uniform quantization, but modifies the given image in place.
+#[pyfunction]
+pub fn ordered_dither_inplace(
+    py: Python<'_>,
+    mut img: PyImageMut,
+    quant: UniformQuantization,
+    map_size: u32,
+    map: Option<ThresholdMap>,
+) -> PyResult<()> {
+    let map = create_threshold_map(map.unwrap_or(ThresholdMap::Bayer), map_size)?;
+
+    let img = img.view_mut()?;
+    py.allow_threads(|| {
+        image_ops::dither::ordered_dither_with_map(img, &map, quant.inner);
     });
-    Ok(result.into_pyarray(py))
+    Ok(())
 }
 
 mod diffusion {
@@ -207,27 +438,27 @@ mod diffusion {
 
     use super::*;
 
-    pub struct Config<'py>(pub Python<'py>, pub PyImage<'py>);
+    pub struct Config<'py>(pub Python<'py>, pub PyImage<'py>, pub DiffusionOptions);
 
     fn with_pixel_format<P>(
-        Config(py, img): Config<'_>,
+        Config(py, img, options): Config<'_>,
         quant: impl Quantizer<P, P> + Sync,
         algorithm: impl image_ops::dither::DiffusionAlgorithm + Send,
     ) -> PyResult<&PyArray3<f32>>
     where
-        P: Pixel + Send + FromFlat,
+        P: Pixel + Send + FromFlat<Sample = f32>,
         Image<P>: IntoNumpy,
     {
         let mut img: Image<P> = img.load_image()?;
         let result = py.allow_threads(|| {
-            image_ops::dither::error_diffusion_dither(&mut img, algorithm, &quant);
+            image_ops::dither::error_diffusion_dither(img.view_mut(), algorithm, &quant, options);
             img.into_numpy()
         });
         Ok(result.into_pyarray(py))
     }
 
     pub fn with_algorithm(
-        config: Config,
+        config: Config<'_>,
         quant: Quant,
         algorithm: impl image_ops::dither::DiffusionAlgorithm + Send,
     ) -> PyResult<&PyArray3<f32>> {
@@ -256,15 +487,54 @@ mod diffusion {
 }
 
 #[pyfunction]
+#[allow(clippy::too_many_arguments)]
 pub fn error_diffusion_dither<'py>(
     py: Python<'py>,
     img: PyImage<'py>,
     quant: Quant,
     algorithm: DiffusionAlgorithm,
+    border: Option<BorderMode>,
+    serpentine: Option<bool>,
+    error_strength: Option<f32>,
+    error_clamp: Option<f32>,
 ) -> PyResult<&'py PyArray3<f32>> {
     use diffusion::*;
 
-    let config: Config<'py> = Config(py, img);
+    // errors can only wrap around or leave the image
+    let wrap = match border.unwrap_or(BorderMode::Transparent) {
+        BorderMode::Wrap => true,
+        BorderMode::Transparent => false,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must be Wrap or Transparent.",
+                stringify!(border)
+            )))
+        }
+    };
+
+    let error_strength = error_strength.unwrap_or(1.0);
+    if error_strength.is_nan() || error_strength < 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be at least 0.",
+            stringify!(error_strength)
+        )));
+    }
+    if let Some(error_clamp) = error_clamp {
+        if error_clamp.is_nan() || error_clamp < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must be at least 0.",
+                stringify!(error_clamp)
+            )));
+        }
+    }
+
+    let options = DiffusionOptions {
+        wrap,
+        serpentine: serpentine.unwrap_or(false),
+        error_strength,
+        error_clamp,
+    };
+    let config: Config<'py> = Config(py, img, options);
     match algorithm {
         DiffusionAlgorithm::FloydSteinberg => with_algorithm(config, quant, FloydSteinberg),
         DiffusionAlgorithm::JarvisJudiceNinke => with_algorithm(config, quant, JarvisJudiceNinke),
@@ -291,12 +561,17 @@ mod riemersma {
         quant: impl Quantizer<P, P> + Sync,
     ) -> PyResult<&PyArray3<f32>>
     where
-        P: Pixel + Send + FromFlat,
+        P: Pixel + Send + FromFlat<Sample = f32>,
         Image<P>: IntoNumpy,
     {
         let mut img: Image<P> = img.load_image()?;
         let result = py.allow_threads(|| {
-            image_ops::dither::riemersma_dither(&mut img, history_length, decay_ratio, &quant);
+            image_ops::dither::riemersma_dither(
+                img.view_mut(),
+                history_length,
+                decay_ratio,
+                &quant,
+            );
             img.into_numpy()
         });
         Ok(result.into_pyarray(py))
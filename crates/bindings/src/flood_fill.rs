@@ -0,0 +1,138 @@
+use glam::{Vec2, Vec3A, Vec4};
+use image_core::{FromFlat, Image, IntoPixels, NDimImage, NDimView, NDimViewMut};
+use image_ops::{
+    components::Connectivity,
+    dither::{ColorAlpha, ColorSpace, RGB},
+};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    components::to_connectivity,
+    convert::{IntoNumpy, LoadImage, PyImage, PyImageMut},
+    dither::ColorDistance,
+};
+
+/// Fills the pixels selected by the flood fill with the given color. Without
+/// a color, only the mask is computed.
+fn fill(
+    mut img: NDimViewMut,
+    seed: (usize, usize),
+    tolerance: f32,
+    connectivity: Connectivity,
+    distance: ColorDistance,
+    color: Option<&[f32]>,
+) -> Image<f32> {
+    let mask = match img.channels() {
+        1 => with_pixel_format::<f32>(img.view(), seed, tolerance, connectivity, distance),
+        2 => with_pixel_format::<Vec2>(img.view(), seed, tolerance, connectivity, distance),
+        3 => with_pixel_format::<Vec3A>(img.view(), seed, tolerance, connectivity, distance),
+        4 => with_pixel_format::<Vec4>(img.view(), seed, tolerance, connectivity, distance),
+        _ => unreachable!("the number of channels was checked before"),
+    };
+
+    if let Some(color) = color {
+        let pixels = img.data_mut().chunks_exact_mut(color.len());
+        for (pixel, &m) in pixels.zip(mask.data()) {
+            if m != 0.0 {
+                pixel.copy_from_slice(color);
+            }
+        }
+    }
+    return mask;
+
+    fn with_pixel_format<P>(
+        img: NDimView,
+        seed: (usize, usize),
+        tolerance: f32,
+        connectivity: Connectivity,
+        distance: ColorDistance,
+    ) -> Image<f32>
+    where
+        P: ColorAlpha + FromFlat<Sample = f32>,
+        RGB: ColorSpace<P, Coord = P::Coord>,
+    {
+        let img: Image<P> = img
+            .into_pixels()
+            .expect("the number of channels was checked before");
+        image_ops::flood_fill::flood_fill_mask(img.view(), seed, tolerance, connectivity, &distance)
+    }
+}
+
+fn check_args(channels: usize, color: Option<&[f32]>) -> PyResult<()> {
+    if !(1..=4).contains(&channels) {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' does not have the right shape. Expected 1, 2, 3, or 4 channels but found {}.",
+            stringify!(img),
+            channels
+        )));
+    }
+    if let Some(color) = color {
+        if color.len() != channels {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must have {} values for an image with {} channel(s), but has {}.",
+                stringify!(color),
+                channels,
+                channels,
+                color.len()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Selects all pixels connected to the seed pixel `(x, y)` whose color is
+/// within the tolerance of the seed color. Returns the selection as a mask.
+///
+/// Colors are compared with the given distance metric, which defaults to
+/// `ColorDistance.Rgb`.
+#[pyfunction]
+pub fn flood_fill<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    seed: (usize, usize),
+    tolerance: f32,
+    connectivity: Option<u32>,
+    distance: Option<ColorDistance>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let connectivity = to_connectivity(connectivity)?;
+    let distance = distance.unwrap_or(ColorDistance::Rgb);
+    check_args(img.channels(), None)?;
+
+    let mut img: NDimImage = img.load_image()?;
+    let result = py.allow_threads(|| {
+        fill(
+            img.view_mut(),
+            seed,
+            tolerance,
+            connectivity,
+            distance,
+            None,
+        )
+        .into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
+
+/// Same as `flood_fill`, but also fills the selected pixels of the given
+/// image with the given color in place.
+#[pyfunction]
+pub fn flood_fill_inplace<'py>(
+    py: Python<'py>,
+    mut img: PyImageMut<'py>,
+    seed: (usize, usize),
+    tolerance: f32,
+    color: Vec<f32>,
+    connectivity: Option<u32>,
+    distance: Option<ColorDistance>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let connectivity = to_connectivity(connectivity)?;
+    let distance = distance.unwrap_or(ColorDistance::Rgb);
+    let img = img.view_mut()?;
+    check_args(img.channels(), Some(&color))?;
+
+    let result = py.allow_threads(|| {
+        fill(img, seed, tolerance, connectivity, distance, Some(&color)).into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
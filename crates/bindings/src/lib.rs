@@ -1,39 +1,102 @@
+// pyo3 0.20's macros generate impls inside of functions
+#![allow(non_local_definitions)]
+
+mod blur;
+mod border;
+mod channel;
 mod clipboard;
+mod components;
 mod convert;
+mod convolve;
 mod dither;
+mod flood_fill;
+mod morphology;
+mod palette;
 mod pixel_art;
 mod regex;
 mod resize;
+mod sharpen;
+mod warp;
 
+use glam::Vec4;
 use image_core::{Image, NDimImage};
 use image_ops::fill_alpha::{fill_alpha, FillMode};
 use numpy::{IntoPyArray, PyArray3};
 use pyo3::prelude::*;
 
-use crate::convert::{IntoNumpy, LoadImage, PyImage};
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{IntoNumpy, LoadImage, PyImage, PyImageMut},
+};
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<convert::PlanarImage>()?;
+
     m.add_class::<regex::RustRegex>()?;
     m.add_class::<regex::MatchGroup>()?;
     m.add_class::<regex::RegexMatch>()?;
 
     m.add_class::<clipboard::Clipboard>()?;
 
+    m.add_wrapped(wrap_pyfunction!(channel::split_channels))?;
+    m.add_wrapped(wrap_pyfunction!(channel::merge_channels))?;
+    m.add_wrapped(wrap_pyfunction!(channel::swizzle_channels))?;
+    m.add_wrapped(wrap_pyfunction!(channel::add_alpha))?;
+    m.add_wrapped(wrap_pyfunction!(channel::drop_alpha))?;
+
     m.add_class::<dither::DiffusionAlgorithm>()?;
     m.add_class::<dither::UniformQuantization>()?;
+    m.add_class::<dither::ColorDistance>()?;
     m.add_class::<dither::PaletteQuantization>()?;
     m.add_wrapped(wrap_pyfunction!(dither::quantize))?;
+    m.add_wrapped(wrap_pyfunction!(dither::quantize_inplace))?;
     m.add_wrapped(wrap_pyfunction!(dither::error_diffusion_dither))?;
+    m.add_class::<dither::ThresholdMap>()?;
+    m.add_wrapped(wrap_pyfunction!(dither::threshold_map))?;
     m.add_wrapped(wrap_pyfunction!(dither::ordered_dither))?;
+    m.add_wrapped(wrap_pyfunction!(dither::ordered_dither_with_map))?;
+    m.add_wrapped(wrap_pyfunction!(dither::ordered_dither_inplace))?;
     m.add_wrapped(wrap_pyfunction!(dither::riemersma_dither))?;
 
+    m.add_class::<palette::PaletteAlgorithm>()?;
+    m.add_wrapped(wrap_pyfunction!(palette::generate_palette))?;
+    m.add_class::<palette::PaletteFormat>()?;
+    m.add_wrapped(wrap_pyfunction!(palette::read_palette))?;
+    m.add_wrapped(wrap_pyfunction!(palette::write_palette))?;
+
     m.add_wrapped(wrap_pyfunction!(pixel_art::pixel_art_upscale))?;
 
     m.add_class::<resize::ResizeFilter>()?;
+    m.add_class::<resize::ResizeKernel>()?;
+    m.add_class::<resize::TransferFunction>()?;
     m.add_wrapped(wrap_pyfunction!(resize::resize))?;
 
+    m.add_class::<border::BorderMode>()?;
+    m.add_wrapped(wrap_pyfunction!(warp::warp))?;
+
+    m.add_wrapped(wrap_pyfunction!(blur::gaussian_blur))?;
+    m.add_wrapped(wrap_pyfunction!(blur::box_blur))?;
+    m.add_wrapped(wrap_pyfunction!(blur::stack_blur))?;
+    m.add_wrapped(wrap_pyfunction!(blur::bilateral_blur))?;
+
+    m.add_wrapped(wrap_pyfunction!(convolve::convolve))?;
+
+    m.add_wrapped(wrap_pyfunction!(sharpen::unsharp_mask))?;
+    m.add_wrapped(wrap_pyfunction!(sharpen::high_pass))?;
+
+    m.add_class::<morphology::MorphOp>()?;
+    m.add_class::<morphology::MorphShape>()?;
+    m.add_wrapped(wrap_pyfunction!(morphology::morphology))?;
+
+    m.add_class::<components::Region>()?;
+    m.add_wrapped(wrap_pyfunction!(components::connected_components))?;
+    m.add_wrapped(wrap_pyfunction!(components::remove_small_components))?;
+
+    m.add_wrapped(wrap_pyfunction!(flood_fill::flood_fill))?;
+    m.add_wrapped(wrap_pyfunction!(flood_fill::flood_fill_inplace))?;
+
     /// Fill the transparent pixels in the given image with nearby colors.
     #[pyfn(m)]
     fn fill_alpha_fragment_blur<'py>(
@@ -42,8 +105,12 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
         threshold: f32,
         iterations: u32,
         fragment_count: u32,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
     ) -> PyResult<&'py PyArray3<f32>> {
         let mut img = img.load_image()?;
+        let border = Border::new(border, BorderMode::Transparent, border_value, 4)?;
+        let border = border.for_pixel::<Vec4>();
         let result = py.allow_threads(|| {
             fill_alpha(
                 &mut img,
@@ -52,6 +119,7 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
                     iterations,
                     fragment_count,
                 },
+                border,
                 None,
             );
             img.into_numpy()
@@ -66,13 +134,18 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
         img: PyImage,
         threshold: f32,
         iterations: u32,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
     ) -> PyResult<&'py PyArray3<f32>> {
         let mut img = img.load_image()?;
+        let border = Border::new(border, BorderMode::Transparent, border_value, 4)?;
+        let border = border.for_pixel::<Vec4>();
         let result = py.allow_threads(|| {
             fill_alpha(
                 &mut img,
                 threshold,
                 FillMode::ExtendColor { iterations },
+                border,
                 None,
             );
             img.into_numpy()
@@ -88,8 +161,12 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
         threshold: f32,
         min_radius: u32,
         anti_aliasing: bool,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
     ) -> PyResult<&'py PyArray3<f32>> {
         let mut img = img.load_image()?;
+        let border = Border::new(border, BorderMode::Transparent, border_value, 4)?;
+        let border = border.for_pixel::<Vec4>();
         let result = py.allow_threads(|| {
             fill_alpha(
                 &mut img,
@@ -98,6 +175,7 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
                     min_radius,
                     anti_aliasing,
                 },
+                border,
                 None,
             );
             img.into_numpy()
@@ -124,14 +202,39 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
                 None
             };
 
-            image_ops::threshold::binary_threshold(&mut img, threshold, aa);
+            image_ops::threshold::binary_threshold(img.view_mut(), threshold, aa);
             img.into_numpy()
         });
         Ok(result.into_pyarray(py))
     }
 
+    /// Same as `binary_threshold`, but modifies the given image in place.
+    #[pyfn(m)]
+    fn binary_threshold_inplace(
+        py: Python<'_>,
+        mut img: PyImageMut,
+        threshold: f32,
+        anti_aliasing: bool,
+        extra_smoothness: Option<f32>,
+    ) -> PyResult<()> {
+        let img = img.view_mut()?;
+        py.allow_threads(|| {
+            let aa = if anti_aliasing {
+                Some(image_ops::threshold::AntiAliasing {
+                    extra_smoothness: extra_smoothness.unwrap_or(0.0),
+                })
+            } else {
+                None
+            };
+
+            image_ops::threshold::binary_threshold(img, threshold, aa);
+        });
+        Ok(())
+    }
+
     /// Fill the transparent pixels in the given image with nearby colors.
     #[pyfn(m)]
+    #[allow(clippy::too_many_arguments)]
     fn esdf<'py>(
         py: Python<'py>,
         img: PyImage,
@@ -139,23 +242,78 @@ fn chainner_ext(_py: Python, m: &PyModule) -> PyResult<()> {
         cutoff: f32,
         pre_process: bool,
         post_process: bool,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
     ) -> PyResult<&'py PyArray3<f32>> {
         let img: Image<f32> = img.load_image()?;
+        let border = Border::new(border, BorderMode::Transparent, border_value, 1)?;
+        let border = border.for_pixel::<f32>();
         let result = py.allow_threads(|| {
-            image_ops::esdt::esdf(&img, radius, cutoff, pre_process, post_process).into_numpy()
+            image_ops::esdt::esdf(&img, radius, cutoff, pre_process, post_process, border)
+                .into_numpy()
+        });
+        Ok(result.into_pyarray(py))
+    }
+
+    /// Computes the multi-channel signed distance field of the given image.
+    #[pyfn(m)]
+    fn msdf<'py>(
+        py: Python<'py>,
+        img: PyImage,
+        radius: f32,
+        cutoff: f32,
+        angle_threshold: Option<f32>,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
+    ) -> PyResult<&'py PyArray3<f32>> {
+        let img: Image<f32> = img.load_image()?;
+        let angle_threshold = angle_threshold.unwrap_or(1.0);
+        let border = Border::new(border, BorderMode::Transparent, border_value, 1)?;
+        let border = border.for_pixel::<f32>();
+        let result = py.allow_threads(|| {
+            image_ops::esdt::msdf(&img, radius, cutoff, angle_threshold, border).into_numpy()
+        });
+        Ok(result.into_pyarray(py))
+    }
+
+    /// Same as `msdf`, but with the true signed distance field in the alpha channel.
+    #[pyfn(m)]
+    fn mtsdf<'py>(
+        py: Python<'py>,
+        img: PyImage,
+        radius: f32,
+        cutoff: f32,
+        angle_threshold: Option<f32>,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
+    ) -> PyResult<&'py PyArray3<f32>> {
+        let img: Image<f32> = img.load_image()?;
+        let angle_threshold = angle_threshold.unwrap_or(1.0);
+        let border = Border::new(border, BorderMode::Transparent, border_value, 1)?;
+        let border = border.for_pixel::<f32>();
+        let result = py.allow_threads(|| {
+            image_ops::esdt::mtsdf(&img, radius, cutoff, angle_threshold, border).into_numpy()
         });
         Ok(result.into_pyarray(py))
     }
 
     #[pyfn(m)]
     fn fast_gamma<'py>(py: Python<'py>, img: PyImage, gamma: f32) -> PyResult<&'py PyArray3<f32>> {
-        let mut img = img.load_image()?;
+        let mut img: NDimImage = img.load_image()?;
         let result = py.allow_threads(|| {
-            image_ops::gamma::gamma_ndim(&mut img, gamma);
+            image_ops::gamma::gamma_ndim(img.view_mut(), gamma);
             img.into_numpy()
         });
         Ok(result.into_pyarray(py))
     }
 
+    /// Same as `fast_gamma`, but modifies the given image in place.
+    #[pyfn(m)]
+    fn fast_gamma_inplace(py: Python<'_>, mut img: PyImageMut, gamma: f32) -> PyResult<()> {
+        let img = img.view_mut()?;
+        py.allow_threads(|| image_ops::gamma::gamma_ndim(img, gamma));
+        Ok(())
+    }
+
     Ok(())
 }
@@ -0,0 +1,96 @@
+use image_core::{Image, NDimCow};
+use image_ops::morphology::{self as m, StructuringElement};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{IntoNumpy, LoadImage, PyImage},
+};
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MorphOp {
+    Erode = 0,
+    Dilate = 1,
+    Open = 2,
+    Close = 3,
+    Gradient = 4,
+    TopHat = 5,
+    BlackHat = 6,
+}
+
+impl From<MorphOp> for m::MorphOp {
+    fn from(op: MorphOp) -> Self {
+        match op {
+            MorphOp::Erode => m::MorphOp::Erode,
+            MorphOp::Dilate => m::MorphOp::Dilate,
+            MorphOp::Open => m::MorphOp::Open,
+            MorphOp::Close => m::MorphOp::Close,
+            MorphOp::Gradient => m::MorphOp::Gradient,
+            MorphOp::TopHat => m::MorphOp::TopHat,
+            MorphOp::BlackHat => m::MorphOp::BlackHat,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MorphShape {
+    Square = 0,
+    Disk = 1,
+    Cross = 2,
+}
+
+/// Applies a morphological operation to each channel of the given image.
+///
+/// If a threshold is given, the operation is applied to the binary mask of
+/// all pixels above the threshold instead.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub fn morphology<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    op: MorphOp,
+    shape: MorphShape,
+    radius: usize,
+    threshold: Option<f32>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let op = op.into();
+    let element = match shape {
+        MorphShape::Square => StructuringElement::Square(radius),
+        MorphShape::Disk => StructuringElement::Disk(radius),
+        MorphShape::Cross => StructuringElement::Cross(radius),
+    };
+
+    let border = Border::new(
+        border,
+        BorderMode::Transparent,
+        border_value,
+        img.channels(),
+    )?;
+
+    if let Some(threshold) = threshold {
+        if img.channels() != 1 {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must have 1 channel when a threshold is given, but has {}.",
+                stringify!(img),
+                img.channels()
+            )));
+        }
+        let img: Image<f32> = img.load_image()?;
+        let result = py.allow_threads(|| {
+            m::binary_morphology(img.view(), threshold, op, element, border.for_pixel())
+                .into_numpy()
+        });
+        return Ok(result.into_pyarray(py));
+    }
+
+    let img: NDimCow = img.load_image()?;
+    let result = py.allow_threads(|| {
+        m::morphology_ndim(img.view(), op, element, border.for_ndim()).into_numpy()
+    });
+    Ok(result.into_pyarray(py))
+}
@@ -0,0 +1,148 @@
+use image_core::NDimCow;
+use image_ops::palette::{self as p, generate_palette_ndim, ExtractionError, PaletteFileError};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::convert::{IntoNumpy, LoadImage, PyImage};
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PaletteAlgorithm {
+    MedianCut = 0,
+    Octree = 1,
+    Wu = 2,
+}
+
+impl From<PaletteAlgorithm> for p::PaletteAlgorithm {
+    fn from(algorithm: PaletteAlgorithm) -> Self {
+        match algorithm {
+            PaletteAlgorithm::MedianCut => p::PaletteAlgorithm::MedianCut,
+            PaletteAlgorithm::Octree => p::PaletteAlgorithm::Octree,
+            PaletteAlgorithm::Wu => p::PaletteAlgorithm::Wu,
+        }
+    }
+}
+
+/// Generates a palette of at most `max_colors` colors for the given image.
+///
+/// The palette is returned as an image with a height of 1, so it can be used
+/// with `PaletteQuantization`.
+#[pyfunction]
+pub fn generate_palette<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    max_colors: u32,
+    algorithm: PaletteAlgorithm,
+    kmeans_iterations: Option<u32>,
+) -> PyResult<&'py PyArray3<f32>> {
+    if max_colors < 1 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be at least 1.",
+            stringify!(max_colors)
+        )));
+    }
+    let kmeans_iterations = kmeans_iterations.unwrap_or(0) as usize;
+
+    let img: NDimCow = img.load_image()?;
+    let result = py.allow_threads(|| {
+        generate_palette_ndim(
+            img.view(),
+            max_colors as usize,
+            algorithm.into(),
+            kmeans_iterations,
+        )
+        .map(|palette| palette.into_numpy())
+    });
+
+    match result {
+        Ok(palette) => Ok(palette.into_pyarray(py)),
+        Err(ExtractionError::UnsupportedChannels { channels }) => {
+            Err(PyValueError::new_err(format!(
+                "Argument '{}' has an unsupported number of channels. Images with {} channels are not supported.",
+                stringify!(img),
+                channels
+            )))
+        }
+        Err(ExtractionError::TooManyColors { .. }) => {
+            unreachable!("palette generation never has too many colors")
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PaletteFormat {
+    Gpl = 0,
+    Act = 1,
+    Ase = 2,
+    Pal = 3,
+    Hex = 4,
+}
+
+impl From<PaletteFormat> for p::PaletteFormat {
+    fn from(format: PaletteFormat) -> Self {
+        match format {
+            PaletteFormat::Gpl => p::PaletteFormat::Gpl,
+            PaletteFormat::Act => p::PaletteFormat::Act,
+            PaletteFormat::Ase => p::PaletteFormat::Ase,
+            PaletteFormat::Pal => p::PaletteFormat::Pal,
+            PaletteFormat::Hex => p::PaletteFormat::Hex,
+        }
+    }
+}
+
+fn to_py_error(err: PaletteFileError, arg: &str) -> PyErr {
+    let message = match err {
+        PaletteFileError::InvalidHeader => {
+            "is not a valid palette file of the given format.".into()
+        }
+        PaletteFileError::UnexpectedEnd => "ends unexpectedly.".into(),
+        PaletteFileError::InvalidLine { line } => format!("has an invalid color in line {}.", line),
+        PaletteFileError::UnsupportedColorModel { model } => format!(
+            "uses the unsupported color model '{}'.",
+            String::from_utf8_lossy(&model).trim()
+        ),
+        PaletteFileError::NoColors => "does not contain any colors.".into(),
+        PaletteFileError::TooManyColors {
+            max_colors,
+            actual_colors,
+        } => format!(
+            "has too many colors for the given format. Expected at most {}, got {}.",
+            max_colors, actual_colors
+        ),
+        PaletteFileError::UnsupportedChannels { channels } => format!(
+            "has an unsupported number of channels. Expected 1, 3, or 4 channels but found {}.",
+            channels
+        ),
+    };
+    PyValueError::new_err(format!("Argument '{}' {}", arg, message))
+}
+
+/// Reads the contents of a palette file.
+///
+/// The palette is returned as an RGB image with a height of 1, so it can be
+/// used with `PaletteQuantization`.
+#[pyfunction]
+pub fn read_palette<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: PaletteFormat,
+) -> PyResult<&'py PyArray3<f32>> {
+    let palette =
+        p::read_palette(data, format.into()).map_err(|err| to_py_error(err, stringify!(data)))?;
+    Ok(palette.into_numpy().into_pyarray(py))
+}
+
+/// Writes the pixels of the given image as a palette file and returns the
+/// contents of the file.
+#[pyfunction]
+pub fn write_palette<'py>(
+    py: Python<'py>,
+    palette: PyImage<'py>,
+    format: PaletteFormat,
+) -> PyResult<&'py PyBytes> {
+    let img: NDimCow = palette.load_image()?;
+    let data = p::write_palette(img.view(), format.into())
+        .map_err(|err| to_py_error(err, stringify!(palette)))?;
+    Ok(PyBytes::new(py, &data))
+}
@@ -22,7 +22,7 @@ pub fn pixel_art_upscale<'py>(
         scale: u32,
     ) -> PyResult<&'py PyArray3<f32>>
     where
-        P: FromFlat
+        P: FromFlat<Sample = f32>
             + Default
             + Copy
             + PartialEq
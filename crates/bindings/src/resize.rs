@@ -1,10 +1,11 @@
 use glam::{Vec2, Vec3A, Vec4};
 use image_core::{ClipFloat, Flatten, FromFlat, Image, ImageView, IntoPixels, NDimImage, Size};
-use image_ops::scale::{Filter, FloatPixelFormat, PixelFormat};
+use image_ops::scale::{CustomFilter, Filter, FloatPixelFormat, PixelFormat, ScaleError};
 use numpy::{IntoPyArray, PyArray3};
 use pyo3::{exceptions::PyValueError, prelude::*};
 
 use crate::{
+    border::{Border, BorderMode, BorderValue},
     convert::{LoadImage, PyImage, ViewImage},
     IntoNumpy,
 };
@@ -40,28 +41,224 @@ impl From<ResizeFilter> for Filter {
             ResizeFilter::Hann => Filter::Hann,
             ResizeFilter::Lanczos => Filter::Lanczos3,
             ResizeFilter::Lagrange => Filter::Lagrange,
-            ResizeFilter::Gauss => Filter::Gauss,
+            ResizeFilter::Gauss => Filter::Gauss { sigma: 0.5 },
         }
     }
 }
 
+/// A resize filter with custom parameters.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct ResizeKernel {
+    filter: Filter,
+}
+
+#[pymethods]
+impl ResizeKernel {
+    #[staticmethod]
+    pub fn cubic_bc(b: f32, c: f32) -> Self {
+        Self {
+            filter: Filter::CubicBC { b, c },
+        }
+    }
+
+    #[staticmethod]
+    pub fn lanczos(lobes: u32) -> PyResult<Self> {
+        check_lobes(lobes)?;
+        Ok(Self {
+            filter: Filter::Lanczos { lobes },
+        })
+    }
+
+    #[staticmethod]
+    pub fn gauss(sigma: f32) -> PyResult<Self> {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must be positive.",
+                stringify!(sigma)
+            )));
+        }
+        Ok(Self {
+            filter: Filter::Gauss { sigma },
+        })
+    }
+
+    #[staticmethod]
+    pub fn kaiser(lobes: u32, beta: f32) -> PyResult<Self> {
+        check_lobes(lobes)?;
+        Ok(Self {
+            filter: Filter::Kaiser { lobes, beta },
+        })
+    }
+
+    #[staticmethod]
+    pub fn blackman(lobes: u32) -> PyResult<Self> {
+        check_lobes(lobes)?;
+        Ok(Self {
+            filter: Filter::Blackman { lobes },
+        })
+    }
+
+    /// A jinc-windowed jinc filter. `resize` applies it separably as an
+    /// approximation, only `warp` evaluates it radially.
+    #[staticmethod]
+    pub fn jinc(lobes: u32) -> PyResult<Self> {
+        check_lobes(lobes)?;
+        Ok(Self {
+            filter: Filter::Jinc { lobes },
+        })
+    }
+
+    /// Creates a filter from the given Python function.
+    ///
+    /// The function is sampled in the range `-support..=support` once, so
+    /// that resizing doesn't need the GIL.
+    #[staticmethod]
+    pub fn custom(kernel: &PyAny, support: f32) -> PyResult<Self> {
+        const SAMPLES_PER_PIXEL: f32 = 256.0;
+
+        if !(0.0..=64.0).contains(&support) || support == 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Argument '{}' must be in the range (0, 64].",
+                stringify!(support)
+            )));
+        }
+
+        let steps = (support * SAMPLES_PER_PIXEL).ceil() as usize * 2;
+        let step = 2.0 * support / steps as f32;
+        let samples = (0..=steps)
+            .map(|i| kernel.call1((i as f32 * step - support,))?.extract::<f32>())
+            .collect::<PyResult<Vec<f32>>>()?;
+
+        // linearly interpolate between samples
+        let custom = CustomFilter::new(support, move |x| {
+            let t = ((x + support) / step).clamp(0.0, steps as f32);
+            let i = (t as usize).min(steps - 1);
+            let f = t - i as f32;
+            samples[i] * (1.0 - f) + samples[i + 1] * f
+        });
+        Ok(Self {
+            filter: Filter::Custom(custom),
+        })
+    }
+
+    #[getter]
+    pub fn support(&self) -> f32 {
+        self.filter.support()
+    }
+}
+
+fn check_lobes(lobes: u32) -> PyResult<()> {
+    if lobes == 0 {
+        return Err(PyValueError::new_err(format!(
+            "Argument '{}' must be at least 1.",
+            stringify!(lobes)
+        )));
+    }
+    Ok(())
+}
+
+#[derive(FromPyObject)]
+pub enum ResizeFilterArg {
+    Preset(ResizeFilter),
+    Kernel(ResizeKernel),
+}
+
+impl From<ResizeFilterArg> for Filter {
+    fn from(f: ResizeFilterArg) -> Self {
+        match f {
+            ResizeFilterArg::Preset(f) => f.into(),
+            ResizeFilterArg::Kernel(k) => k.filter,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    Srgb = 0,
+    Rec709 = 1,
+    Pq = 2,
+    Hlg = 3,
+}
+
+impl From<TransferFunction> for image_ops::gamma::TransferFunction {
+    fn from(f: TransferFunction) -> Self {
+        match f {
+            TransferFunction::Srgb => image_ops::gamma::TransferFunction::Srgb,
+            TransferFunction::Rec709 => image_ops::gamma::TransferFunction::Rec709,
+            TransferFunction::Pq => image_ops::gamma::TransferFunction::Pq,
+            TransferFunction::Hlg => image_ops::gamma::TransferFunction::Hlg,
+        }
+    }
+}
+
+/// How the image is converted to linear light before resizing.
+///
+/// `True` uses a pure power function with gamma 2.2, a number uses a pure
+/// power function with that gamma.
+#[derive(FromPyObject)]
+pub enum GammaCorrection {
+    Enabled(bool),
+    Power(f32),
+    Transfer(TransferFunction),
+}
+
+impl GammaCorrection {
+    fn transfer_function(self) -> Option<image_ops::gamma::TransferFunction> {
+        match self {
+            GammaCorrection::Enabled(false) => None,
+            GammaCorrection::Enabled(true) => Some(image_ops::gamma::TransferFunction::Power(2.2)),
+            GammaCorrection::Power(gamma) => Some(image_ops::gamma::TransferFunction::Power(gamma)),
+            GammaCorrection::Transfer(f) => Some(f.into()),
+        }
+    }
+}
+
+fn to_py_err(err: ScaleError, new_size: Size) -> PyErr {
+    match err {
+        ScaleError::EmptySource => PyValueError::new_err(format!(
+            "Cannot resize an empty image to {}x{}.",
+            new_size.width, new_size.height,
+        )),
+        ScaleError::OutOfMemory => PyValueError::new_err(format!(
+            "Not enough memory to allocate a {}x{} image.",
+            new_size.width, new_size.height,
+        )),
+    }
+}
+
 #[pyfunction]
+#[allow(clippy::too_many_arguments)]
 pub fn resize<'py>(
     py: Python<'py>,
     img: PyImage<'py>,
     new_size: (u32, u32),
-    filter: ResizeFilter,
-    mut gamma_correction: bool,
+    filter: ResizeFilterArg,
+    gamma_correction: GammaCorrection,
+    premultiply_alpha: Option<bool>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
 ) -> PyResult<&'py PyArray3<f32>> {
     let new_size: Size = new_size.into();
     let filter: Filter = filter.into();
 
+    let mut transfer = gamma_correction.transfer_function();
     if filter == Filter::Nearest {
         // no point in paying for gamma correction if we're not interpolating
-        gamma_correction = false;
+        transfer = None;
     }
 
+    // the filters may overshoot, so we have to clip the result
+    let clip = filter != Filter::Nearest && filter != Filter::Linear;
+
     let c = img.channels();
+    let border = Border::new(border, BorderMode::Transparent, border_value, c)?;
+
+    // only images with an alpha channel can be premultiplied, and NN doesn't
+    // interpolate pixels
+    let premultiply_alpha =
+        premultiply_alpha.unwrap_or(false) && filter != Filter::Nearest && (c == 2 || c == 4);
 
     let new_error = || {
         PyValueError::new_err(format!(
@@ -71,32 +268,46 @@ pub fn resize<'py>(
         ))
     };
 
-    if gamma_correction {
+    if transfer.is_some() || premultiply_alpha {
+        let border = match transfer {
+            Some(transfer) => border.map_color(|v| transfer.to_linear(v)),
+            None => border,
+        };
         let mut img: NDimImage = img.load_image()?;
         let result: PyResult<_> = py.allow_threads(|| {
             // convert to linear
-            image_ops::gamma::gamma_ndim(&mut img, 2.2);
+            if let Some(transfer) = transfer {
+                image_ops::gamma::to_linear_ndim(img.view_mut(), transfer);
+            }
 
             // the actual resizing
-            let mut result = match c {
-                1 => with_pixel_format::<f32>(img, new_size, filter)?,
-                2 => with_pixel_format::<Vec2>(img, new_size, filter)?,
-                3 => with_pixel_format::<Vec3A>(img, new_size, filter)?,
-                4 => with_pixel_format::<Vec4>(img, new_size, filter)?,
+            use image_ops::scale::{scale, scale_alpha};
+            let mut result = match (c, premultiply_alpha) {
+                (1, _) => with_pixel_format::<f32>(img, new_size, filter, &border, scale)?,
+                (2, false) => with_pixel_format::<Vec2>(img, new_size, filter, &border, scale)?,
+                (2, true) => {
+                    with_pixel_format::<Vec2>(img, new_size, filter, &border, scale_alpha)?
+                }
+                (3, _) => with_pixel_format::<Vec3A>(img, new_size, filter, &border, scale)?,
+                (4, false) => with_pixel_format::<Vec4>(img, new_size, filter, &border, scale)?,
+                (4, true) => {
+                    with_pixel_format::<Vec4>(img, new_size, filter, &border, scale_alpha)?
+                }
                 _ => return Err(new_error()),
             };
 
             // fix up overshooting
-            if filter != Filter::Nearest && filter != Filter::Linear {
-                // the filters may overshoot, so we have to clip the result
+            if clip {
                 result
                     .data_mut()
                     .iter_mut()
                     .for_each(|x| *x = x.clip(0.0, 1.0));
             }
 
-            // convert back to sRGB
-            image_ops::gamma::gamma_ndim(&mut result, 1.0 / 2.2);
+            // convert back
+            if let Some(transfer) = transfer {
+                image_ops::gamma::from_linear_ndim(result.view_mut(), transfer);
+            }
 
             return Ok(result.into_numpy());
 
@@ -104,23 +315,26 @@ pub fn resize<'py>(
                 img: NDimImage,
                 new_size: Size,
                 filter: Filter,
+                border: &Border,
+                scale: impl FnOnce(
+                    ImageView<P>,
+                    Size,
+                    Filter,
+                    image_ops::util::BorderMode<P>,
+                ) -> Result<Image<P>, ScaleError>,
             ) -> PyResult<NDimImage>
             where
-                P: Flatten + FromFlat + Default + Clone + 'static,
-                FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
+                P: Flatten<Sample = f32> + FromFlat + Default + Copy + 'static,
             {
                 let img: Image<P> = img.into_pixels().expect("");
-                let r = image_ops::scale::scale(img.view(), new_size, filter);
+                let r = scale(img.view(), new_size, filter, border.for_pixel());
 
                 // drop image now to free up memory asap
                 std::mem::drop(img);
 
                 match r {
                     Ok(r) => Ok(r.into()),
-                    Err(_) => Err(PyValueError::new_err(format!(
-                        "Not enough memory to allocate a {}x{} image.",
-                        new_size.width, new_size.height,
-                    ))),
+                    Err(e) => Err(to_py_err(e, new_size)),
                 }
             }
         });
@@ -132,13 +346,13 @@ pub fn resize<'py>(
         // read the image directly if we can to avoid copying
 
         if let Some(view) = img.view_image() {
-            return with_pixel_format::<f32>(py, view, new_size, filter);
+            return with_pixel_format::<f32>(py, view, new_size, filter, &border);
         }
         if let Some(view) = img.view_image() {
-            return with_pixel_format::<[f32; 3]>(py, view, new_size, filter);
+            return with_pixel_format::<[f32; 3]>(py, view, new_size, filter, &border);
         }
         if let Some(view) = img.view_image() {
-            return with_pixel_format::<[f32; 4]>(py, view, new_size, filter);
+            return with_pixel_format::<[f32; 4]>(py, view, new_size, filter, &border);
         }
 
         fn with_pixel_format<'py, P>(
@@ -146,25 +360,31 @@ pub fn resize<'py>(
             img: ImageView<'_, P>,
             new_size: Size,
             filter: Filter,
+            border: &Border,
         ) -> PyResult<&'py PyArray3<f32>>
         where
-            P: Flatten + ClipFloat + Default + Copy + Sync + Send + 'static,
+            P: Flatten<Sample = f32>
+                + FromFlat
+                + ClipFloat
+                + Default
+                + Copy
+                + Sync
+                + Send
+                + 'static,
             FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
         {
-            let r = image_ops::scale::scale(img, new_size, filter);
+            let clip = filter != Filter::Nearest && filter != Filter::Linear;
+            let r = image_ops::scale::scale(img, new_size, filter, border.for_pixel());
             match r {
                 Ok(mut r) => {
-                    if filter != Filter::Nearest && filter != Filter::Linear {
+                    if clip {
                         // the filters may overshoot, so we have to clip the result
                         r.data_mut().iter_mut().for_each(|x| *x = x.clip(0.0, 1.0));
                     }
                     Ok(r.into_numpy().into_pyarray(py))
                 }
 
-                Err(_) => Err(PyValueError::new_err(format!(
-                    "Not enough memory to allocate a {}x{} image.",
-                    new_size.width, new_size.height,
-                ))),
+                Err(e) => Err(to_py_err(e, new_size)),
             }
         }
     }
@@ -197,54 +417,54 @@ pub fn resize<'py>(
     return match c {
         1 => {
             let img: Image<f32> = img.load_image()?;
-            with_pixel_format(py, img, new_size, filter)
+            with_pixel_format(py, img, new_size, filter, border)
         }
         2 => {
             let img: Image<[f32; 2]> = img.load_image()?;
-            with_pixel_format(py, img, new_size, filter)
+            with_pixel_format(py, img, new_size, filter, border)
         }
         3 => {
             let img: Image<[f32; 3]> = img.load_image()?;
-            with_pixel_format(py, img, new_size, filter)
+            with_pixel_format(py, img, new_size, filter, border)
         }
         4 => {
             if vec_worth {
                 let img: Image<Vec4> = img.load_image()?;
-                with_pixel_format(py, img, new_size, filter)
+                with_pixel_format(py, img, new_size, filter, border)
             } else {
                 let img: Image<[f32; 4]> = img.load_image()?;
-                with_pixel_format(py, img, new_size, filter)
+                with_pixel_format(py, img, new_size, filter, border)
             }
         }
         _ => Err(new_error()),
     };
 
     fn with_pixel_format<P>(
-        py: Python,
+        py: Python<'_>,
         img: Image<P>,
         new_size: Size,
         filter: Filter,
+        border: Border,
     ) -> PyResult<&PyArray3<f32>>
     where
-        P: Flatten + ClipFloat + Default + Copy + Send + 'static,
+        P: Flatten<Sample = f32> + FromFlat + ClipFloat + Default + Copy + Send + 'static,
         FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
     {
         let result = py.allow_threads(|| {
-            let r = image_ops::scale::scale(img.view(), new_size, filter);
+            let border = border.for_pixel::<P>();
+            let clip = filter != Filter::Nearest && filter != Filter::Linear;
+            let r = image_ops::scale::scale(img.view(), new_size, filter, border);
             std::mem::drop(img);
             match r {
                 Ok(mut r) => {
-                    if filter != Filter::Nearest && filter != Filter::Linear {
+                    if clip {
                         // the filters may overshoot, so we have to clip the result
                         r.data_mut().iter_mut().for_each(|x| *x = x.clip(0.0, 1.0));
                     }
                     Ok(r.into_numpy())
                 }
 
-                Err(_) => Err(PyValueError::new_err(format!(
-                    "Not enough memory to allocate a {}x{} image.",
-                    new_size.width, new_size.height,
-                ))),
+                Err(e) => Err(to_py_err(e, new_size)),
             }
         })?;
 
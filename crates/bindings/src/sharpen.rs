@@ -0,0 +1,117 @@
+use glam::{Vec3A, Vec4};
+use image_core::{Flatten, FromFlat, Image};
+use image_ops::sharpen::{SharpenMode, SharpenPixel};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{LoadImage, PyImage},
+    IntoNumpy,
+};
+
+#[derive(Clone, Copy)]
+enum Sharpen {
+    UnsharpMask { amount: f32, threshold: f32 },
+    HighPass,
+}
+
+impl Sharpen {
+    fn run<'py>(
+        self,
+        py: Python<'py>,
+        img: PyImage<'py>,
+        radius: f32,
+        luminance_only: Option<bool>,
+        border: Option<BorderMode>,
+        border_value: Option<BorderValue>,
+    ) -> PyResult<&'py PyArray3<f32>> {
+        let mode = if luminance_only.unwrap_or(false) {
+            SharpenMode::Luminance
+        } else {
+            SharpenMode::Rgb
+        };
+
+        let c = img.channels();
+        let border = Border::new(border, BorderMode::Reflect, border_value, c)?;
+
+        return match c {
+            3 => with_pixel_format::<Vec3A>(py, img, self, radius, mode, border),
+            4 => with_pixel_format::<Vec4>(py, img, self, radius, mode, border),
+            _ => Err(PyValueError::new_err(format!(
+                "Argument '{}' does not have the right shape. Expected 3 or 4 channels but found {}.",
+                stringify!(img),
+                c
+            ))),
+        };
+
+        fn with_pixel_format<'py, P>(
+            py: Python<'py>,
+            img: PyImage<'py>,
+            sharpen: Sharpen,
+            radius: f32,
+            mode: SharpenMode,
+            border: Border,
+        ) -> PyResult<&'py PyArray3<f32>>
+        where
+            P: SharpenPixel + Flatten<Sample = f32> + FromFlat + 'static,
+        {
+            let img: Image<P> = img.load_image()?;
+            let border = border.for_pixel::<P>();
+            let result = py.allow_threads(|| {
+                match sharpen {
+                    Sharpen::UnsharpMask { amount, threshold } => image_ops::sharpen::unsharp_mask(
+                        img.view(),
+                        radius,
+                        amount,
+                        threshold,
+                        mode,
+                        border,
+                    ),
+                    Sharpen::HighPass => {
+                        image_ops::sharpen::high_pass(img.view(), radius, mode, border)
+                    }
+                }
+                .into_numpy()
+            });
+            Ok(result.into_pyarray(py))
+        }
+    }
+}
+
+/// Sharpens the given image with an unsharp mask.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub fn unsharp_mask<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    radius: f32,
+    amount: f32,
+    threshold: Option<f32>,
+    luminance_only: Option<bool>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let threshold = threshold.unwrap_or(0.0);
+    Sharpen::UnsharpMask { amount, threshold }.run(
+        py,
+        img,
+        radius,
+        luminance_only,
+        border,
+        border_value,
+    )
+}
+
+/// Returns the high frequencies of the given image offset by 0.5.
+#[pyfunction]
+pub fn high_pass<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    radius: f32,
+    luminance_only: Option<bool>,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    Sharpen::HighPass.run(py, img, radius, luminance_only, border, border_value)
+}
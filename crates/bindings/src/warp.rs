@@ -0,0 +1,97 @@
+use glam::{Vec2, Vec3A, Vec4};
+use image_core::{Flatten, FromFlat, Image, Size};
+use image_ops::{
+    scale::{Filter, FloatPixelFormat, PixelFormat},
+    warp::{Transform, WarpError},
+};
+use numpy::{IntoPyArray, PyArray3};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    border::{Border, BorderMode, BorderValue},
+    convert::{LoadImage, PyImage},
+    resize::ResizeFilterArg,
+    IntoNumpy,
+};
+
+/// A row-major 2x3 affine or 3x3 perspective matrix, like the matrices of
+/// OpenCV's `warpAffine` and `warpPerspective`.
+#[derive(FromPyObject)]
+pub enum WarpMatrix {
+    Affine([[f32; 3]; 2]),
+    Perspective([[f32; 3]; 3]),
+}
+
+impl From<WarpMatrix> for Transform {
+    fn from(m: WarpMatrix) -> Self {
+        match m {
+            WarpMatrix::Affine(rows) => Transform::from_affine_rows(rows),
+            WarpMatrix::Perspective(rows) => Transform::from_rows(rows),
+        }
+    }
+}
+
+/// Transforms the given image with the given matrix, which maps source
+/// coordinates to destination coordinates.
+#[pyfunction]
+pub fn warp<'py>(
+    py: Python<'py>,
+    img: PyImage<'py>,
+    matrix: WarpMatrix,
+    new_size: (u32, u32),
+    filter: ResizeFilterArg,
+    border: Option<BorderMode>,
+    border_value: Option<BorderValue>,
+) -> PyResult<&'py PyArray3<f32>> {
+    let new_size: Size = new_size.into();
+    let transform: Transform = matrix.into();
+    let filter: Filter = filter.into();
+
+    let c = img.channels();
+    let border = Border::new(border, BorderMode::Clamp, border_value, c)?;
+
+    return match c {
+        1 => with_pixel_format::<f32>(py, img, new_size, transform, filter, border),
+        2 => with_pixel_format::<Vec2>(py, img, new_size, transform, filter, border),
+        3 => with_pixel_format::<Vec3A>(py, img, new_size, transform, filter, border),
+        4 => with_pixel_format::<Vec4>(py, img, new_size, transform, filter, border),
+        _ => Err(PyValueError::new_err(format!(
+            "Argument '{}' does not have the right shape. Expected 1, 2, 3, or 4 channels but found {}.",
+            stringify!(img),
+            c
+        ))),
+    };
+
+    fn with_pixel_format<'py, P>(
+        py: Python<'py>,
+        img: PyImage<'py>,
+        new_size: Size,
+        transform: Transform,
+        filter: Filter,
+        border: Border,
+    ) -> PyResult<&'py PyArray3<f32>>
+    where
+        P: Flatten<Sample = f32> + FromFlat + Default + Copy + Send + Sync + 'static,
+        FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
+    {
+        let img: Image<P> = img.load_image()?;
+        let border = border.for_pixel::<P>();
+
+        let result = py.allow_threads(|| {
+            image_ops::warp::warp(img.view(), new_size, &transform, &filter, border)
+                .map(|r| r.into_numpy())
+        });
+
+        match result {
+            Ok(r) => Ok(r.into_pyarray(py)),
+            Err(WarpError::Singular) => Err(PyValueError::new_err(format!(
+                "Argument '{}' is not invertible.",
+                stringify!(matrix)
+            ))),
+            Err(WarpError::EmptySource) => Err(PyValueError::new_err(format!(
+                "Cannot warp an empty image to {}x{}.",
+                new_size.width, new_size.height,
+            ))),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::slice::ChunksExact;
+use std::{iter::FusedIterator, slice::ChunksExact};
 
 /// A non-empty size consisting of width and height in that order.
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
@@ -108,6 +108,46 @@ impl<P> Image<P> {
     pub fn view(&self) -> ImageView<'_, P> {
         ImageView::new(self.size(), &self.data)
     }
+    pub fn view_mut(&mut self) -> ImageViewMut<'_, P> {
+        ImageViewMut::new(self.size(), &mut self.data)
+    }
+
+    /// Returns a view of the `width`x`height` rectangle whose top-left corner is at `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not fully contained in the image.
+    pub fn view_region(&self, x: usize, y: usize, width: usize, height: usize) -> ImageView<'_, P> {
+        self.view().crop(x, y, width, height)
+    }
+    /// Returns a mutable view of the `width`x`height` rectangle whose top-left corner is at `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not fully contained in the image.
+    pub fn view_region_mut(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> ImageViewMut<'_, P> {
+        self.view_mut().crop(x, y, width, height)
+    }
+
+    /// Copies the `width`x`height` rectangle whose top-left corner is at `x`, `y` into a new image.
+    ///
+    /// Use [`Image::view_region`] to get the same region without copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not fully contained in the image.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image<P>
+    where
+        P: Clone,
+    {
+        self.view_region(x, y, width, height).into_owned()
+    }
 
     /// The pixel data of the image.
     ///
@@ -165,22 +205,97 @@ impl<P> Image<P> {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Returns the number of elements a strided image of the given size needs.
+///
+/// The last row does not need to be padded to the full stride.
+fn strided_len(size: Size, stride: usize) -> usize {
+    if size.is_empty() {
+        0
+    } else {
+        (size.height - 1) * stride + size.width
+    }
+}
+
+/// Panics if the given rectangle is not fully contained in an image of the given size.
+fn assert_region(size: Size, x: usize, y: usize, width: usize, height: usize) {
+    assert!(
+        x.checked_add(width).map_or(false, |r| r <= size.width)
+            && y.checked_add(height).map_or(false, |b| b <= size.height),
+        "region {}x{} at ({}, {}) is out of bounds for an image of size {}x{}",
+        width,
+        height,
+        x,
+        y,
+        size.width,
+        size.height
+    );
+}
+
+/// A borrowed view of an image.
+///
+/// The rows of a view do not have to be contiguous in memory. Consecutive rows
+/// are [`ImageView::stride`] pixels apart, which allows views of sub-regions of
+/// a larger image without copying.
+#[derive(Debug)]
 pub struct ImageView<'a, P> {
+    /// Starts at the first pixel of the view and contains exactly
+    /// `strided_len(size, stride)` elements.
     data: &'a [P],
     size: Size,
+    stride: usize,
+}
+
+impl<'a, P> Clone for ImageView<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
+impl<'a, P> Copy for ImageView<'a, P> {}
 
 impl<'a, P> ImageView<'a, P> {
     pub fn empty() -> Self {
         Self {
             data: &[],
             size: Size::empty(),
+            stride: 0,
         }
     }
     pub fn new(size: Size, data: &'a [P]) -> Self {
         assert_eq!(size.len(), data.len());
-        Self { data, size }
+        Self {
+            data,
+            size,
+            stride: size.width,
+        }
+    }
+    /// Creates a view of an image whose first pixel is at `data[offset]` and
+    /// whose rows are `stride` pixels apart.
+    ///
+    /// `data` may contain more pixels than necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride < size.width` or if `data` is too short.
+    pub fn new_strided(size: Size, data: &'a [P], offset: usize, stride: usize) -> Self {
+        if size.is_empty() {
+            return Self {
+                data: &[],
+                size,
+                stride: size.width,
+            };
+        }
+
+        assert!(stride >= size.width, "stride must be at least the width");
+        let len = strided_len(size, stride);
+        assert!(
+            offset + len <= data.len(),
+            "data is too short for the given size, offset, and stride"
+        );
+        Self {
+            data: &data[offset..offset + len],
+            size,
+            stride,
+        }
     }
 
     pub fn size(&self) -> Size {
@@ -200,34 +315,84 @@ impl<'a, P> ImageView<'a, P> {
         self.size().is_empty()
     }
 
+    /// The number of pixels between the start of one row and the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+    /// Whether the rows of this view are laid out back-to-back in memory.
+    pub fn is_contiguous(&self) -> bool {
+        self.stride == self.width() || self.height() <= 1
+    }
+
     pub fn into_owned(&self) -> Image<P>
     where
         P: Clone,
     {
-        Image::new(self.size(), self.data().to_vec())
+        if let Some(data) = self.as_slice() {
+            return Image::new(self.size(), data.to_vec());
+        }
+
+        let mut data = Vec::with_capacity(self.len());
+        for row in self.rows() {
+            data.extend_from_slice(row);
+        }
+        Image::new(self.size(), data)
     }
 
-    /// The pixel data of the image.
+    /// The pixel data of the image if the view is contiguous.
     ///
     /// Pixel data is layed out in row-major order.
-    pub fn data(&self) -> &'a [P] {
+    pub fn as_slice(&self) -> Option<&'a [P]> {
+        if self.is_contiguous() {
+            Some(self.data)
+        } else {
+            None
+        }
+    }
+    /// The underlying pixel data of the view.
+    ///
+    /// The pixel at `x`, `y` is at index `y * stride + x`. If the view isn't
+    /// contiguous, the slice will also contain pixels outside of the view.
+    pub fn raw_data(&self) -> &'a [P] {
         self.data
     }
 
     /// The pixel data of a single row of the image.
     pub fn row(&self, y: usize) -> &'a [P] {
-        &self.data()[y * self.width()..(y + 1) * self.width()]
+        assert!(y < self.height());
+        let start = y * self.stride;
+        &self.data[start..start + self.width()]
     }
 
-    pub fn rows(&self) -> ChunksExact<'_, P> {
-        self.data().chunks_exact(self.width())
+    pub fn rows(&self) -> Rows<'a, P> {
+        Rows {
+            data: self.data,
+            width: self.width(),
+            stride: self.stride,
+            remaining: self.height(),
+        }
+    }
+
+    /// Returns a view of the `width`x`height` rectangle whose top-left corner is at `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not fully contained in this view.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> ImageView<'a, P> {
+        assert_region(self.size(), x, y, width, height);
+        let size = Size::new(width, height);
+        if size.is_empty() {
+            return Self::new_strided(size, &[], 0, 0);
+        }
+        Self::new_strided(size, self.data, y * self.stride + x, self.stride)
     }
 
     pub fn map<T>(&self, f: impl Fn(&P) -> T) -> Image<T> {
-        Image {
-            data: self.data().iter().map(f).collect(),
-            size: self.size(),
+        let mut data = Vec::with_capacity(self.len());
+        for row in self.rows() {
+            data.extend(row.iter().map(&f));
         }
+        Image::new(self.size(), data)
     }
     pub fn map_pos<T>(&self, f: impl Fn(&P, usize, usize) -> T) -> Image<T> {
         let f = &f;
@@ -243,3 +408,280 @@ impl<'a, P> ImageView<'a, P> {
         }
     }
 }
+
+/// A mutable borrowed view of an image.
+///
+/// Like [`ImageView`], the rows of a mutable view are [`ImageViewMut::stride`]
+/// pixels apart.
+#[derive(Debug)]
+pub struct ImageViewMut<'a, P> {
+    /// Starts at the first pixel of the view and contains exactly
+    /// `strided_len(size, stride)` elements.
+    data: &'a mut [P],
+    size: Size,
+    stride: usize,
+}
+
+impl<'a, P> ImageViewMut<'a, P> {
+    pub fn new(size: Size, data: &'a mut [P]) -> Self {
+        assert_eq!(size.len(), data.len());
+        Self {
+            data,
+            size,
+            stride: size.width,
+        }
+    }
+    /// Creates a mutable view of an image whose first pixel is at
+    /// `data[offset]` and whose rows are `stride` pixels apart.
+    ///
+    /// `data` may contain more pixels than necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride < size.width` or if `data` is too short.
+    pub fn new_strided(size: Size, data: &'a mut [P], offset: usize, stride: usize) -> Self {
+        if size.is_empty() {
+            return Self {
+                data: &mut [],
+                size,
+                stride: size.width,
+            };
+        }
+
+        assert!(stride >= size.width, "stride must be at least the width");
+        let len = strided_len(size, stride);
+        assert!(
+            offset + len <= data.len(),
+            "data is too short for the given size, offset, and stride"
+        );
+        Self {
+            data: &mut data[offset..offset + len],
+            size,
+            stride,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn width(&self) -> usize {
+        self.size().width
+    }
+    pub fn height(&self) -> usize {
+        self.size().height
+    }
+    pub fn len(&self) -> usize {
+        self.size().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size().is_empty()
+    }
+
+    /// The number of pixels between the start of one row and the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+    /// Whether the rows of this view are laid out back-to-back in memory.
+    pub fn is_contiguous(&self) -> bool {
+        self.stride == self.width() || self.height() <= 1
+    }
+
+    /// Returns a read-only view of the same pixels.
+    pub fn view(&self) -> ImageView<'_, P> {
+        ImageView {
+            data: self.data,
+            size: self.size,
+            stride: self.stride,
+        }
+    }
+    /// Returns a shorter-lived mutable view of the same pixels.
+    ///
+    /// This is useful for passing the view to a function without giving up ownership of it.
+    pub fn reborrow(&mut self) -> ImageViewMut<'_, P> {
+        ImageViewMut {
+            data: self.data,
+            size: self.size,
+            stride: self.stride,
+        }
+    }
+    pub fn into_view(self) -> ImageView<'a, P> {
+        ImageView {
+            data: self.data,
+            size: self.size,
+            stride: self.stride,
+        }
+    }
+
+    pub fn into_owned(&self) -> Image<P>
+    where
+        P: Clone,
+    {
+        self.view().into_owned()
+    }
+
+    /// The pixel data of the image if the view is contiguous.
+    ///
+    /// Pixel data is layed out in row-major order.
+    pub fn as_slice(&self) -> Option<&[P]> {
+        self.view().as_slice()
+    }
+    /// The pixel data of the image if the view is contiguous.
+    ///
+    /// Pixel data is layed out in row-major order.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [P]> {
+        if self.is_contiguous() {
+            Some(self.data)
+        } else {
+            None
+        }
+    }
+    /// The underlying pixel data of the view.
+    ///
+    /// The pixel at `x`, `y` is at index `y * stride + x`. If the view isn't
+    /// contiguous, the slice will also contain pixels outside of the view.
+    pub fn raw_data(&self) -> &[P] {
+        self.data
+    }
+    /// The underlying pixel data of the view.
+    ///
+    /// The pixel at `x`, `y` is at index `y * stride + x`. If the view isn't
+    /// contiguous, the slice will also contain pixels outside of the view.
+    pub fn raw_data_mut(&mut self) -> &mut [P] {
+        self.data
+    }
+
+    /// The pixel data of a single row of the image.
+    pub fn row(&self, y: usize) -> &[P] {
+        self.view().row(y)
+    }
+    /// The pixel data of a single row of the image.
+    pub fn row_mut(&mut self, y: usize) -> &mut [P] {
+        assert!(y < self.height());
+        let start = y * self.stride;
+        let end = start + self.width();
+        &mut self.data[start..end]
+    }
+
+    pub fn rows(&self) -> Rows<'_, P> {
+        self.view().rows()
+    }
+    pub fn rows_mut(&mut self) -> RowsMut<'_, P> {
+        RowsMut {
+            data: self.data,
+            width: self.size.width,
+            stride: self.stride,
+            remaining: self.size.height,
+        }
+    }
+
+    /// Returns a mutable view of the `width`x`height` rectangle whose top-left corner is at `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle is not fully contained in this view.
+    pub fn crop(self, x: usize, y: usize, width: usize, height: usize) -> ImageViewMut<'a, P> {
+        assert_region(self.size(), x, y, width, height);
+        let size = Size::new(width, height);
+        if size.is_empty() {
+            return Self::new_strided(size, &mut [], 0, 0);
+        }
+        Self::new_strided(size, self.data, y * self.stride + x, self.stride)
+    }
+
+    pub fn fill(&mut self, c: P)
+    where
+        P: Clone,
+    {
+        for row in self.rows_mut() {
+            row.fill(c.clone());
+        }
+    }
+    /// Copies all pixels from the given view into this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes of the two views are different.
+    pub fn copy_from(&mut self, src: ImageView<P>)
+    where
+        P: Copy,
+    {
+        assert_eq!(self.size(), src.size());
+        for (dst, src) in self.rows_mut().zip(src.rows()) {
+            dst.copy_from_slice(src);
+        }
+    }
+}
+
+impl<'a, P> From<ImageViewMut<'a, P>> for ImageView<'a, P> {
+    fn from(value: ImageViewMut<'a, P>) -> Self {
+        value.into_view()
+    }
+}
+
+/// An iterator over the rows of an [`ImageView`].
+#[derive(Debug, Clone)]
+pub struct Rows<'a, P> {
+    data: &'a [P],
+    width: usize,
+    stride: usize,
+    remaining: usize,
+}
+
+impl<'a, P> Iterator for Rows<'a, P> {
+    type Item = &'a [P];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let row = &self.data[..self.width];
+        if self.remaining > 0 {
+            self.data = &self.data[self.stride..];
+        }
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, P> ExactSizeIterator for Rows<'a, P> {}
+impl<'a, P> FusedIterator for Rows<'a, P> {}
+
+/// An iterator over the mutable rows of an [`ImageViewMut`].
+#[derive(Debug)]
+pub struct RowsMut<'a, P> {
+    data: &'a mut [P],
+    width: usize,
+    stride: usize,
+    remaining: usize,
+}
+
+impl<'a, P> Iterator for RowsMut<'a, P> {
+    type Item = &'a mut [P];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let data = std::mem::take(&mut self.data);
+        if self.remaining > 0 {
+            let (row, rest) = data.split_at_mut(self.stride);
+            self.data = rest;
+            Some(&mut row[..self.width])
+        } else {
+            Some(&mut data[..self.width])
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, P> ExactSizeIterator for RowsMut<'a, P> {}
+impl<'a, P> FusedIterator for RowsMut<'a, P> {}
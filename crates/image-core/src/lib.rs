@@ -1,8 +1,12 @@
 mod image;
 mod ndim;
 mod pixel;
+mod planar;
+mod sample;
 pub mod util;
 
 pub use image::*;
 pub use ndim::*;
 pub use pixel::*;
+pub use planar::*;
+pub use sample::*;
@@ -1,4 +1,4 @@
-use crate::{pixel::Flatten, FromFlat, Image, Size};
+use crate::{pixel::Flatten, FromFlat, Image, Sample, Size};
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct Shape {
@@ -36,21 +36,27 @@ impl Shape {
 }
 
 /// A 3D image that is similar to how numpy arrays.
+///
+/// The samples of the image are `f32` by default, but integer and half
+/// precision samples are also supported.
 #[derive(Debug, Clone)]
-pub struct NDimImage {
-    data: Vec<f32>,
+pub struct NDimImage<T = f32> {
+    data: Vec<T>,
     shape: Shape,
 }
 
-impl NDimImage {
-    pub fn new(shape: Shape, data: Vec<f32>) -> Self {
+impl<T> NDimImage<T> {
+    pub fn new(shape: Shape, data: Vec<T>) -> Self {
         assert!(shape.len() == data.len());
         Self { data, shape }
     }
-    pub fn zeros(shape: Shape) -> Self {
-        Self::new(shape, vec![0f32; shape.len()])
+    pub fn zeros(shape: Shape) -> Self
+    where
+        T: Sample,
+    {
+        Self::new(shape, vec![T::default(); shape.len()])
     }
-    pub fn from_fn_c<const C: usize>(size: Size, f: impl Fn(usize, usize) -> [f32; C]) -> Self {
+    pub fn from_fn_c<const C: usize>(size: Size, f: impl Fn(usize, usize) -> [T; C]) -> Self {
         let shape = Shape::from_size(size, C);
         let f = &f;
 
@@ -62,12 +68,15 @@ impl NDimImage {
         )
     }
 
-    pub fn take(self) -> Vec<f32> {
+    pub fn take(self) -> Vec<T> {
         self.data
     }
-    pub fn view(&self) -> NDimView {
+    pub fn view(&self) -> NDimView<'_, T> {
         NDimView::new(self.shape, &self.data)
     }
+    pub fn view_mut(&mut self) -> NDimViewMut<'_, T> {
+        NDimViewMut::new(self.shape, &mut self.data)
+    }
 
     pub fn shape(&self) -> Shape {
         self.shape
@@ -85,22 +94,48 @@ impl NDimImage {
         self.shape().channels
     }
 
-    pub fn data(&self) -> &[f32] {
+    pub fn data(&self) -> &[T] {
         &self.data[..]
     }
-    pub fn data_mut(&mut self) -> &mut [f32] {
+    pub fn data_mut(&mut self) -> &mut [T] {
         &mut self.data[..]
     }
+
+    /// Converts all samples of the image into another sample type.
+    pub fn convert<U: Sample>(&self) -> NDimImage<U>
+    where
+        T: Sample,
+    {
+        self.view().convert()
+    }
+    /// Converts all samples of the image into another sample type if the
+    /// conversion does not lose any information.
+    ///
+    /// E.g. an `f32` image can be converted into a `u8` image losslessly if all
+    /// of its values are multiples of `1/255`.
+    pub fn convert_lossless<U: Sample>(&self) -> Option<NDimImage<U>>
+    where
+        T: Sample,
+    {
+        self.view().convert_lossless()
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct NDimView<'a> {
-    data: &'a [f32],
+#[derive(Debug)]
+pub struct NDimView<'a, T = f32> {
+    data: &'a [T],
     shape: Shape,
 }
 
-impl<'a> NDimView<'a> {
-    pub fn new(shape: Shape, data: &'a [f32]) -> Self {
+impl<'a, T> Clone for NDimView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for NDimView<'a, T> {}
+
+impl<'a, T> NDimView<'a, T> {
+    pub fn new(shape: Shape, data: &'a [T]) -> Self {
         assert!(shape.len() == data.len());
         Self { data, shape }
     }
@@ -121,18 +156,108 @@ impl<'a> NDimView<'a> {
         self.shape().channels
     }
 
-    pub fn data(&self) -> &'a [f32] {
+    pub fn data(&self) -> &'a [T] {
         self.data
     }
+
+    pub fn into_owned(&self) -> NDimImage<T>
+    where
+        T: Clone,
+    {
+        NDimImage::new(self.shape, self.data.to_vec())
+    }
+
+    /// Converts all samples of the image into another sample type.
+    pub fn convert<U: Sample>(&self) -> NDimImage<U>
+    where
+        T: Sample,
+    {
+        NDimImage::new(self.shape, self.data.iter().map(|s| s.convert()).collect())
+    }
+    /// Converts all samples of the image into another sample type if the
+    /// conversion does not lose any information.
+    pub fn convert_lossless<U: Sample>(&self) -> Option<NDimImage<U>>
+    where
+        T: Sample,
+    {
+        let data = self
+            .data
+            .iter()
+            .map(|s| s.convert_lossless())
+            .collect::<Option<Vec<U>>>()?;
+        Some(NDimImage::new(self.shape, data))
+    }
+}
+
+#[derive(Debug)]
+pub struct NDimViewMut<'a, T = f32> {
+    data: &'a mut [T],
+    shape: Shape,
+}
+
+impl<'a, T> NDimViewMut<'a, T> {
+    pub fn new(shape: Shape, data: &'a mut [T]) -> Self {
+        assert!(shape.len() == data.len());
+        Self { data, shape }
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+    pub fn size(&self) -> Size {
+        self.shape().size()
+    }
+    pub fn width(&self) -> usize {
+        self.shape().width
+    }
+    pub fn height(&self) -> usize {
+        self.shape().height
+    }
+    pub fn channels(&self) -> usize {
+        self.shape().channels
+    }
+
+    /// Returns a read-only view of the same data.
+    pub fn view(&self) -> NDimView<'_, T> {
+        NDimView::new(self.shape, self.data)
+    }
+    /// Returns a shorter-lived mutable view of the same data.
+    ///
+    /// This is useful for passing the view to a function without giving up ownership of it.
+    pub fn reborrow(&mut self) -> NDimViewMut<'_, T> {
+        NDimViewMut::new(self.shape, self.data)
+    }
+    pub fn into_view(self) -> NDimView<'a, T> {
+        NDimView::new(self.shape, self.data)
+    }
+    pub fn into_owned(&self) -> NDimImage<T>
+    where
+        T: Clone,
+    {
+        NDimImage::new(self.shape, self.data.to_vec())
+    }
+
+    pub fn data(&self) -> &[T] {
+        self.data
+    }
+    pub fn data_mut(&mut self) -> &mut [T] {
+        self.data
+    }
+}
+
+impl<'a, T> From<NDimViewMut<'a, T>> for NDimView<'a, T> {
+    fn from(value: NDimViewMut<'a, T>) -> Self {
+        value.into_view()
+    }
 }
 
 #[derive(Debug)]
-pub enum NDimCow<'a> {
-    Image(NDimImage),
-    View(NDimView<'a>),
+pub enum NDimCow<'a, T = f32> {
+    Image(NDimImage<T>),
+    View(NDimView<'a, T>),
 }
 
-impl<'a> NDimCow<'a> {
+impl<'a, T> NDimCow<'a, T> {
     pub fn shape(&self) -> Shape {
         match self {
             Self::Image(image) => image.shape(),
@@ -152,20 +277,23 @@ impl<'a> NDimCow<'a> {
         self.shape().channels
     }
 
-    pub fn view(&self) -> NDimView {
+    pub fn view(&self) -> NDimView<'_, T> {
         match self {
             Self::Image(image) => image.view(),
             Self::View(view) => *view,
         }
     }
-    pub fn into_owned(self) -> NDimImage {
+    pub fn into_owned(self) -> NDimImage<T>
+    where
+        T: Clone,
+    {
         match self {
             Self::Image(image) => image,
-            Self::View(view) => NDimImage::new(view.shape, view.data.to_vec()),
+            Self::View(view) => view.into_owned(),
         }
     }
 
-    pub fn data(&self) -> &[f32] {
+    pub fn data(&self) -> &[T] {
         match self {
             Self::Image(image) => image.data(),
             Self::View(view) => view.data(),
@@ -173,20 +301,20 @@ impl<'a> NDimCow<'a> {
     }
 }
 
-impl From<NDimImage> for NDimCow<'static> {
-    fn from(value: NDimImage) -> Self {
+impl<T> From<NDimImage<T>> for NDimCow<'static, T> {
+    fn from(value: NDimImage<T>) -> Self {
         Self::Image(value)
     }
 }
-impl<'a> From<NDimView<'a>> for NDimCow<'a> {
-    fn from(value: NDimView<'a>) -> Self {
+impl<'a, T> From<NDimView<'a, T>> for NDimCow<'a, T> {
+    fn from(value: NDimView<'a, T>) -> Self {
         Self::View(value)
     }
 }
 
 // Conversions from Image to NDimImage
 
-impl<P: Flatten> From<Image<P>> for NDimImage {
+impl<P: Flatten> From<Image<P>> for NDimImage<P::Sample> {
     fn from(value: Image<P>) -> Self {
         Self::new(
             Shape::from_size(value.size(), P::COMPONENTS),
@@ -206,7 +334,7 @@ pub struct ShapeMismatch {
 pub trait IntoPixels<P> {
     fn into_pixels(self) -> Result<Image<P>, ShapeMismatch>;
 }
-impl<P> IntoPixels<P> for NDimImage
+impl<P> IntoPixels<P> for NDimImage<P::Sample>
 where
     P: FromFlat,
 {
@@ -222,7 +350,7 @@ where
         }
     }
 }
-impl<P> IntoPixels<P> for NDimView<'_>
+impl<P> IntoPixels<P> for NDimView<'_, P::Sample>
 where
     P: FromFlat,
 {
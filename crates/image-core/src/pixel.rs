@@ -1,42 +1,71 @@
 use std::borrow::Cow;
 
 use glam::{Vec2, Vec3, Vec3A, Vec4};
+use half::f16;
 
-use crate::util::{slice_as_chunks, vec_into_chunks, vec_into_flattened, vec_try_transmute};
+use crate::{
+    util::{slice_as_chunks, vec_into_chunks, vec_into_flattened, vec_try_transmute},
+    Sample,
+};
 
 pub trait Components {
+    /// The type of a single component.
+    type Sample: Sample;
+
     const COMPONENTS: usize;
 }
-impl Components for f32 {
-    const COMPONENTS: usize = 1;
-}
-impl<const N: usize> Components for [f32; N] {
+macro_rules! impl_components_sample {
+    ($t:ty) => {
+        impl Components for $t {
+            type Sample = $t;
+            const COMPONENTS: usize = 1;
+        }
+    };
+}
+impl_components_sample!(u8);
+impl_components_sample!(u16);
+impl_components_sample!(f16);
+impl_components_sample!(f32);
+impl<T: Sample, const N: usize> Components for [T; N] {
+    type Sample = T;
     const COMPONENTS: usize = N;
 }
 impl Components for Vec2 {
+    type Sample = f32;
     const COMPONENTS: usize = 2;
 }
 impl Components for Vec3 {
+    type Sample = f32;
     const COMPONENTS: usize = 3;
 }
 impl Components for Vec3A {
+    type Sample = f32;
     const COMPONENTS: usize = 3;
 }
 impl Components for Vec4 {
+    type Sample = f32;
     const COMPONENTS: usize = 4;
 }
 
 pub trait Flatten: Components + Sized {
-    fn flatten_pixels(vec: Vec<Self>) -> Vec<f32>;
+    fn flatten_pixels(vec: Vec<Self>) -> Vec<Self::Sample>;
 }
 
-impl Flatten for f32 {
-    fn flatten_pixels(vec: Vec<Self>) -> Vec<f32> {
-        vec
-    }
-}
-impl<const N: usize> Flatten for [f32; N] {
-    fn flatten_pixels(vec: Vec<Self>) -> Vec<f32> {
+macro_rules! impl_flatten_sample {
+    ($t:ty) => {
+        impl Flatten for $t {
+            fn flatten_pixels(vec: Vec<Self>) -> Vec<Self::Sample> {
+                vec
+            }
+        }
+    };
+}
+impl_flatten_sample!(u8);
+impl_flatten_sample!(u16);
+impl_flatten_sample!(f16);
+impl_flatten_sample!(f32);
+impl<T: Sample, const N: usize> Flatten for [T; N] {
+    fn flatten_pixels(vec: Vec<Self>) -> Vec<T> {
         vec_into_flattened(vec)
     }
 }
@@ -138,34 +167,48 @@ where
     [Self]: std::borrow::ToOwned<Owned = Vec<Self>>,
 {
     fn from_flat_slice(
-        slice: &[f32],
+        slice: &[Self::Sample],
         channels: usize,
     ) -> Result<Cow<'_, [Self]>, UnsupportedChannel>;
-    fn from_flat_vec(vec: Vec<f32>, channels: usize) -> Result<Vec<Self>, UnsupportedChannel> {
+    fn from_flat_vec(
+        vec: Vec<Self::Sample>,
+        channels: usize,
+    ) -> Result<Vec<Self>, UnsupportedChannel> {
         Ok(Self::from_flat_slice(&vec, channels)?.into_owned())
     }
 }
 
-impl FromFlat for f32 {
-    fn from_flat_slice(
-        slice: &[f32],
-        channels: usize,
-    ) -> Result<Cow<'_, [Self]>, UnsupportedChannel> {
-        if channels == 1 {
-            return Ok(Cow::Borrowed(slice));
+macro_rules! impl_from_flat_sample {
+    ($t:ty) => {
+        impl FromFlat for $t {
+            fn from_flat_slice(
+                slice: &[Self],
+                channels: usize,
+            ) -> Result<Cow<'_, [Self]>, UnsupportedChannel> {
+                if channels == 1 {
+                    return Ok(Cow::Borrowed(slice));
+                }
+                Err(UnsupportedChannel { supported: &[1] })
+            }
+            fn from_flat_vec(
+                vec: Vec<Self>,
+                channels: usize,
+            ) -> Result<Vec<Self>, UnsupportedChannel> {
+                if channels == 1 {
+                    return Ok(vec);
+                }
+                Err(UnsupportedChannel { supported: &[1] })
+            }
         }
-        Err(UnsupportedChannel { supported: &[1] })
-    }
-    fn from_flat_vec(vec: Vec<f32>, channels: usize) -> Result<Vec<Self>, UnsupportedChannel> {
-        if channels == 1 {
-            return Ok(vec);
-        }
-        Err(UnsupportedChannel { supported: &[1] })
-    }
+    };
 }
-impl<const N: usize> FromFlat for [f32; N] {
+impl_from_flat_sample!(u8);
+impl_from_flat_sample!(u16);
+impl_from_flat_sample!(f16);
+impl_from_flat_sample!(f32);
+impl<T: Sample, const N: usize> FromFlat for [T; N] {
     fn from_flat_slice(
-        slice: &[f32],
+        slice: &[T],
         channels: usize,
     ) -> Result<Cow<'_, [Self]>, UnsupportedChannel> {
         if channels == N {
@@ -175,7 +218,7 @@ impl<const N: usize> FromFlat for [f32; N] {
         }
         Err(UnsupportedChannel { supported: &[N] })
     }
-    fn from_flat_vec(vec: Vec<f32>, channels: usize) -> Result<Vec<Self>, UnsupportedChannel> {
+    fn from_flat_vec(vec: Vec<T>, channels: usize) -> Result<Vec<Self>, UnsupportedChannel> {
         if channels == N {
             return Ok(vec_into_chunks(vec));
         }
@@ -253,3 +296,75 @@ impl ClipFloat for Vec4 {
         self.clamp(Self::splat(min), Self::splat(max))
     }
 }
+
+/// A pixel whose last component is an alpha channel.
+pub trait PremultiplyAlpha: Copy {
+    /// Multiplies the color channels with the alpha channel.
+    fn premultiply_alpha(self) -> Self;
+    /// Divides the color channels by the alpha channel.
+    ///
+    /// Pixels with an alpha of zero (or less) are returned unchanged.
+    fn unpremultiply_alpha(self) -> Self;
+}
+
+impl PremultiplyAlpha for [f32; 2] {
+    #[inline]
+    fn premultiply_alpha(self) -> Self {
+        let [v, a] = self;
+        [v * a, a]
+    }
+    #[inline]
+    fn unpremultiply_alpha(self) -> Self {
+        let [v, a] = self;
+        if a <= 0.0 {
+            self
+        } else {
+            [v / a, a]
+        }
+    }
+}
+impl PremultiplyAlpha for [f32; 4] {
+    #[inline]
+    fn premultiply_alpha(self) -> Self {
+        let [r, g, b, a] = self;
+        [r * a, g * a, b * a, a]
+    }
+    #[inline]
+    fn unpremultiply_alpha(self) -> Self {
+        let [r, g, b, a] = self;
+        if a <= 0.0 {
+            self
+        } else {
+            [r / a, g / a, b / a, a]
+        }
+    }
+}
+impl PremultiplyAlpha for Vec2 {
+    #[inline]
+    fn premultiply_alpha(self) -> Self {
+        Vec2::new(self.x * self.y, self.y)
+    }
+    #[inline]
+    fn unpremultiply_alpha(self) -> Self {
+        if self.y <= 0.0 {
+            self
+        } else {
+            Vec2::new(self.x / self.y, self.y)
+        }
+    }
+}
+impl PremultiplyAlpha for Vec4 {
+    #[inline]
+    fn premultiply_alpha(self) -> Self {
+        Vec4::new(self.x * self.w, self.y * self.w, self.z * self.w, self.w)
+    }
+    #[inline]
+    fn unpremultiply_alpha(self) -> Self {
+        if self.w <= 0.0 {
+            self
+        } else {
+            let rgb = 1.0 / self.w;
+            self * Vec4::new(rgb, rgb, rgb, 1.0)
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    util::{slice_as_chunks, vec_into_flattened},
+    ImageView, ImageViewMut, NDimImage, NDimView, Sample, Shape, Size,
+};
+
+/// A 3D image with a planar (channel-major) memory layout.
+///
+/// [`NDimImage`] stores the samples of a pixel next to each other (HWC). This
+/// image instead stores all samples of the first channel, followed by all
+/// samples of the second channel, and so on (CHW). This is the layout most
+/// neural networks use for their input and output tensors.
+#[derive(Debug, Clone)]
+pub struct PlanarImage<T = f32> {
+    data: Vec<T>,
+    shape: Shape,
+}
+
+impl<T> PlanarImage<T> {
+    pub fn new(shape: Shape, data: Vec<T>) -> Self {
+        assert!(shape.len() == data.len());
+        Self { data, shape }
+    }
+    pub fn zeros(shape: Shape) -> Self
+    where
+        T: Sample,
+    {
+        Self::new(shape, vec![T::default(); shape.len()])
+    }
+
+    pub fn take(self) -> Vec<T> {
+        self.data
+    }
+    pub fn view(&self) -> PlanarView<'_, T> {
+        PlanarView::new(self.shape, &self.data)
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+    pub fn size(&self) -> Size {
+        self.shape().size()
+    }
+    pub fn width(&self) -> usize {
+        self.shape().width
+    }
+    pub fn height(&self) -> usize {
+        self.shape().height
+    }
+    pub fn channels(&self) -> usize {
+        self.shape().channels
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data[..]
+    }
+    pub fn data_mut(&mut self) -> &mut [T] {
+        &mut self.data[..]
+    }
+
+    /// Returns a view of the given channel.
+    pub fn channel(&self, channel: usize) -> ImageView<'_, T> {
+        self.view().channel(channel)
+    }
+    /// Returns a mutable view of the given channel.
+    pub fn channel_mut(&mut self, channel: usize) -> ImageViewMut<'_, T> {
+        let size = self.size();
+        let range = plane_range(self.shape, channel);
+        ImageViewMut::new(size, &mut self.data[range])
+    }
+    /// Returns views of all channels.
+    pub fn planes(&self) -> impl Iterator<Item = ImageView<'_, T>> {
+        self.view().planes()
+    }
+
+    /// Converts the image into an interleaved (HWC) image.
+    pub fn to_interleaved(&self) -> NDimImage<T>
+    where
+        T: Copy,
+    {
+        self.view().to_interleaved()
+    }
+}
+
+#[derive(Debug)]
+pub struct PlanarView<'a, T = f32> {
+    data: &'a [T],
+    shape: Shape,
+}
+
+impl<'a, T> Clone for PlanarView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for PlanarView<'a, T> {}
+
+impl<'a, T> PlanarView<'a, T> {
+    pub fn new(shape: Shape, data: &'a [T]) -> Self {
+        assert!(shape.len() == data.len());
+        Self { data, shape }
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+    pub fn size(&self) -> Size {
+        self.shape().size()
+    }
+    pub fn width(&self) -> usize {
+        self.shape().width
+    }
+    pub fn height(&self) -> usize {
+        self.shape().height
+    }
+    pub fn channels(&self) -> usize {
+        self.shape().channels
+    }
+
+    pub fn data(&self) -> &'a [T] {
+        self.data
+    }
+
+    pub fn into_owned(&self) -> PlanarImage<T>
+    where
+        T: Clone,
+    {
+        PlanarImage::new(self.shape, self.data.to_vec())
+    }
+
+    /// Returns a view of the given channel.
+    pub fn channel(&self, channel: usize) -> ImageView<'a, T> {
+        ImageView::new(self.size(), &self.data[plane_range(self.shape, channel)])
+    }
+    /// Returns views of all channels.
+    pub fn planes(&self) -> impl Iterator<Item = ImageView<'a, T>> {
+        let this = *self;
+        (0..self.channels()).map(move |c| this.channel(c))
+    }
+
+    /// Converts the image into an interleaved (HWC) image.
+    pub fn to_interleaved(&self) -> NDimImage<T>
+    where
+        T: Copy,
+    {
+        NDimImage::new(self.shape, interleave(self.shape, self.data))
+    }
+}
+
+impl<T> From<PlanarImage<T>> for NDimImage<T>
+where
+    T: Copy,
+{
+    fn from(value: PlanarImage<T>) -> Self {
+        value.to_interleaved()
+    }
+}
+impl<T> From<NDimImage<T>> for PlanarImage<T>
+where
+    T: Copy,
+{
+    fn from(value: NDimImage<T>) -> Self {
+        value.to_planar()
+    }
+}
+
+impl<T> NDimImage<T> {
+    /// Converts the image into a planar (CHW) image.
+    pub fn to_planar(&self) -> PlanarImage<T>
+    where
+        T: Copy,
+    {
+        self.view().to_planar()
+    }
+}
+impl<T> NDimView<'_, T> {
+    /// Converts the image into a planar (CHW) image.
+    pub fn to_planar(&self) -> PlanarImage<T>
+    where
+        T: Copy,
+    {
+        PlanarImage::new(self.shape(), deinterleave(self.shape(), self.data()))
+    }
+}
+
+fn plane_range(shape: Shape, channel: usize) -> std::ops::Range<usize> {
+    assert!(
+        channel < shape.channels,
+        "Channel {} is out of bounds for an image with {} channels",
+        channel,
+        shape.channels
+    );
+    let plane_len = shape.width * shape.height;
+    channel * plane_len..(channel + 1) * plane_len
+}
+
+fn interleave<T: Copy>(shape: Shape, planar: &[T]) -> Vec<T> {
+    fn interleave_const<T: Copy, const N: usize>(planar: &[T]) -> Vec<T> {
+        let plane_len = planar.len() / N;
+        let planes: [&[T]; N] =
+            std::array::from_fn(|c| &planar[c * plane_len..(c + 1) * plane_len]);
+        let pixels: Vec<[T; N]> = (0..plane_len).map(|i| planes.map(|p| p[i])).collect();
+        vec_into_flattened(pixels)
+    }
+
+    match shape.channels {
+        1 => planar.to_vec(),
+        2 => interleave_const::<T, 2>(planar),
+        3 => interleave_const::<T, 3>(planar),
+        4 => interleave_const::<T, 4>(planar),
+        channels => {
+            let plane_len = shape.width * shape.height;
+            let mut data = Vec::with_capacity(planar.len());
+            for i in 0..plane_len {
+                data.extend((0..channels).map(|c| planar[c * plane_len + i]));
+            }
+            data
+        }
+    }
+}
+
+fn deinterleave<T: Copy>(shape: Shape, interleaved: &[T]) -> Vec<T> {
+    fn deinterleave_const<T: Copy, const N: usize>(interleaved: &[T]) -> Vec<T> {
+        let (pixels, rest) = slice_as_chunks::<T, N>(interleaved);
+        assert!(rest.is_empty());
+
+        let mut data = Vec::with_capacity(interleaved.len());
+        for c in 0..N {
+            data.extend(pixels.iter().map(|p| p[c]));
+        }
+        data
+    }
+
+    match shape.channels {
+        1 => interleaved.to_vec(),
+        2 => deinterleave_const::<T, 2>(interleaved),
+        3 => deinterleave_const::<T, 3>(interleaved),
+        4 => deinterleave_const::<T, 4>(interleaved),
+        channels => {
+            let mut data = Vec::with_capacity(interleaved.len());
+            for c in 0..channels {
+                data.extend(interleaved.iter().skip(c).step_by(channels).copied());
+            }
+            data
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{NDimImage, Shape};
+
+    use super::{PlanarImage, PlanarView};
+
+    fn interleaved(shape: Shape) -> NDimImage {
+        NDimImage::new(shape, (0..shape.len()).map(|i| i as f32).collect())
+    }
+
+    #[test]
+    fn round_trip() {
+        for channels in [1, 2, 3, 4, 5] {
+            let img = interleaved(Shape::new(5, 3, channels));
+            let planar = img.to_planar();
+            assert_eq!(planar.shape(), img.shape());
+            assert_eq!(planar.to_interleaved().data(), img.data());
+        }
+    }
+
+    #[test]
+    fn deinterleave() {
+        let img = interleaved(Shape::new(2, 2, 3));
+        let planar = img.to_planar();
+        assert_eq!(
+            planar.data(),
+            &[0.0, 3.0, 6.0, 9.0, 1.0, 4.0, 7.0, 10.0, 2.0, 5.0, 8.0, 11.0]
+        );
+    }
+
+    #[test]
+    fn channel_views() {
+        let shape = Shape::new(3, 2, 4);
+        let data: Vec<f32> = (0..shape.len()).map(|i| i as f32).collect();
+        let mut planar = PlanarImage::new(shape, data);
+
+        for (c, plane) in planar.planes().enumerate() {
+            assert_eq!(plane.size(), shape.size());
+            assert_eq!(plane.raw_data().as_ptr(), planar.data()[c * 6..].as_ptr());
+            assert_eq!(plane.row(1), &[3.0, 4.0, 5.0].map(|v| v + c as f32 * 6.0));
+        }
+
+        planar.channel_mut(2).fill(-1.0);
+        assert!(planar.channel(2).raw_data().iter().all(|&v| v == -1.0));
+        assert_eq!(
+            planar.channel(1).raw_data(),
+            &[6.0, 7.0, 8.0, 9.0, 10.0, 11.0]
+        );
+        assert_eq!(planar.channel(3).raw_data()[0], 18.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_length() {
+        PlanarView::new(Shape::new(2, 2, 3), &[0.0; 11]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel 3 is out of bounds for an image with 3 channels")]
+    fn channel_out_of_bounds() {
+        let planar = PlanarImage::<f32>::zeros(Shape::new(2, 2, 3));
+        planar.channel(3);
+    }
+}
@@ -0,0 +1,130 @@
+pub use half::f16;
+
+/// A single channel value of a pixel.
+///
+/// Integer samples are normalized, so the full range of the integer type maps
+/// to `0..=1`. E.g. the `u8` value 255 corresponds to the `f32` value 1.0.
+pub trait Sample: Copy + Default + PartialEq + Send + Sync + 'static {
+    /// The sample that represents a channel at full intensity.
+    const MAX: Self;
+
+    /// A value that uniquely identifies the sample and can be hashed.
+    type Bits: Copy + Eq + std::hash::Hash;
+
+    fn to_f32(self) -> f32;
+    /// Converts the given normalized value to the closest sample.
+    ///
+    /// Values outside the range of the sample type are clamped.
+    fn from_f32(value: f32) -> Self;
+
+    fn to_bits(self) -> Self::Bits;
+    fn from_bits(bits: Self::Bits) -> Self;
+
+    /// Converts this sample into a sample of another type.
+    #[inline]
+    fn convert<T: Sample>(self) -> T {
+        T::from_f32(self.to_f32())
+    }
+    /// Converts this sample into a sample of another type if the conversion
+    /// does not lose any information.
+    #[inline]
+    fn convert_lossless<T: Sample>(self) -> Option<T> {
+        let converted: T = self.convert();
+        if converted.convert::<Self>().to_bits() == self.to_bits() {
+            Some(converted)
+        } else {
+            None
+        }
+    }
+}
+
+impl Sample for u8 {
+    const MAX: Self = u8::MAX;
+    type Bits = u8;
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32 / 255.0
+    }
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        // NaN will be mapped to 0
+        (value * 255.0 + 0.5).clamp(0.0, 255.0) as u8
+    }
+
+    #[inline]
+    fn to_bits(self) -> Self::Bits {
+        self
+    }
+    #[inline]
+    fn from_bits(bits: Self::Bits) -> Self {
+        bits
+    }
+}
+impl Sample for u16 {
+    const MAX: Self = u16::MAX;
+    type Bits = u16;
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32 / 65535.0
+    }
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        // NaN will be mapped to 0
+        (value * 65535.0 + 0.5).clamp(0.0, 65535.0) as u16
+    }
+
+    #[inline]
+    fn to_bits(self) -> Self::Bits {
+        self
+    }
+    #[inline]
+    fn from_bits(bits: Self::Bits) -> Self {
+        bits
+    }
+}
+impl Sample for f16 {
+    const MAX: Self = f16::ONE;
+    type Bits = u16;
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        f16::to_f32(self)
+    }
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        f16::from_f32(value)
+    }
+
+    #[inline]
+    fn to_bits(self) -> Self::Bits {
+        f16::to_bits(self)
+    }
+    #[inline]
+    fn from_bits(bits: Self::Bits) -> Self {
+        f16::from_bits(bits)
+    }
+}
+impl Sample for f32 {
+    const MAX: Self = 1.0;
+    type Bits = u32;
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self
+    }
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    #[inline]
+    fn to_bits(self) -> Self::Bits {
+        f32::to_bits(self)
+    }
+    #[inline]
+    fn from_bits(bits: Self::Bits) -> Self {
+        f32::from_bits(bits)
+    }
+}
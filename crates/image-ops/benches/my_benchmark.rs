@@ -1,19 +1,60 @@
 #![allow(unused)]
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use image_core::{NDimImage, Shape};
+use glam::Vec4;
+use image_core::{Image, ImageView, NDimImage, Shape, Size};
 use image_ops::{
     dither::*,
     esdt::esdf,
     fill_alpha::{fill_alpha, FillMode},
     fragment_blur::{fragment_blur, fragment_blur_alpha},
     palette::extract_unique_ndim,
+    scale::{scale, Filter},
     threshold::{binary_threshold, AntiAliasing},
+    util::BorderMode,
 };
 use test_util::data::{
     read_at, read_flower, read_flower_palette, read_flower_transparent, read_lion,
 };
 
+/// The `resize` crate path `scale` used before it got its own resampler.
+struct ResizeCrateFormat;
+
+impl resize::PixelFormat for ResizeCrateFormat {
+    type InputPixel = Vec4;
+    type OutputPixel = Vec4;
+    type Accumulator = Vec4;
+
+    fn new() -> Vec4 {
+        Vec4::ZERO
+    }
+    fn add(&self, acc: &mut Vec4, inp: Vec4, coeff: f32) {
+        *acc += inp * coeff;
+    }
+    fn add_acc(acc: &mut Vec4, inp: Vec4, coeff: f32) {
+        *acc += inp * coeff;
+    }
+    fn into_pixel(&self, acc: Vec4) -> Vec4 {
+        acc
+    }
+}
+
+fn scale_resize_crate(img: ImageView<Vec4>, size: Size, filter: resize::Type) -> Image<Vec4> {
+    let mut dest = Image::from_const(size, Vec4::ZERO);
+    resize::Resizer::new(
+        img.width(),
+        img.height(),
+        size.width,
+        size.height,
+        ResizeCrateFormat,
+        filter,
+    )
+    .unwrap()
+    .resize_stride(img.raw_data(), img.stride(), dest.data_mut())
+    .unwrap();
+    dest
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let img = black_box(read_flower());
     let img_t = black_box(read_flower_transparent());
@@ -26,13 +67,13 @@ fn criterion_benchmark(c: &mut Criterion) {
     let img_at = read_at();
 
     c.bench_function("fragment rgb r=20 c=5", |b| {
-        b.iter(|| fragment_blur(&img, 20., 5, 0., None))
+        b.iter(|| fragment_blur(img.view(), 20., 5, 0., BorderMode::Transparent, None))
     });
     c.bench_function("fragment rgb r=20 c=10", |b| {
-        b.iter(|| fragment_blur(&img, 20., 10, 0., None))
+        b.iter(|| fragment_blur(img.view(), 20., 10, 0., BorderMode::Transparent, None))
     });
     c.bench_function("fragment rgba r=20 c=10", |b| {
-        b.iter(|| fragment_blur_alpha(&img_t, 20., 10, 0., None))
+        b.iter(|| fragment_blur_alpha(img_t.view(), 20., 10, 0., BorderMode::Transparent, None))
     });
 
     c.bench_function("fill alpha texture", |b| {
@@ -45,6 +86,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                     iterations: 8,
                     fragment_count: 5,
                 },
+                BorderMode::Transparent,
                 None,
             )
         })
@@ -57,6 +99,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                 &mut i,
                 0.15,
                 FillMode::ExtendColor { iterations: 1000 },
+                BorderMode::Transparent,
                 None,
             )
         })
@@ -72,6 +115,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                     min_radius: u32::MAX,
                     anti_aliasing: false,
                 },
+                BorderMode::Transparent,
                 None,
             )
         })
@@ -85,31 +129,42 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
     c.bench_function("error diffusion dither map", |b| {
         b.iter(|| {
-            error_diffusion_dither_map(&img, FloydSteinberg, &ChannelQuantization::new(4), None);
+            error_diffusion_dither_map(
+                img.view(),
+                FloydSteinberg,
+                &ChannelQuantization::new(4),
+                DiffusionOptions::default(),
+                None,
+            );
         })
     });
     c.bench_function("error diffusion dither", |b| {
         let mut img = img.clone();
         b.iter(|| {
-            error_diffusion_dither(&mut img, FloydSteinberg, &ChannelQuantization::new(4));
+            error_diffusion_dither(
+                img.view_mut(),
+                FloydSteinberg,
+                &ChannelQuantization::new(4),
+                DiffusionOptions::default(),
+            );
         })
     });
     c.bench_function("riemersma dither", |b| {
         let mut img = img.clone();
         b.iter(|| {
-            riemersma_dither(&mut img, 16, 1.0 / 16.0, &ChannelQuantization::new(4));
+            riemersma_dither(img.view_mut(), 16, 1.0 / 16.0, &ChannelQuantization::new(4));
         })
     });
     c.bench_function("ordered dither", |b| {
         let mut flower_nd: NDimImage = img.clone().into();
         b.iter(|| {
-            ordered_dither(&mut flower_nd, 4, ChannelQuantization::new(2));
+            ordered_dither(flower_nd.view_mut(), 4, ChannelQuantization::new(2));
         })
     });
     c.bench_function("quantize", |b| {
         let mut flower_nd: NDimImage = img.clone().into();
         b.iter(|| {
-            quantize_ndim(&mut flower_nd, ChannelQuantization::new(4));
+            quantize_ndim(flower_nd.view_mut(), ChannelQuantization::new(4));
         })
     });
     c.bench_function("error diffusion dither palette", |b| {
@@ -117,29 +172,64 @@ fn criterion_benchmark(c: &mut Criterion) {
         let palette = black_box(read_flower_palette());
         let quant = ColorPalette::new(RGB, palette.row(0).iter().copied(), BoundError);
         b.iter(|| {
-            error_diffusion_dither(&mut img, FloydSteinberg, &quant);
+            error_diffusion_dither(
+                img.view_mut(),
+                FloydSteinberg,
+                &quant,
+                DiffusionOptions::default(),
+            );
         })
     });
 
     c.bench_function("threshold", |b| {
         let mut img = img_lion_ndim.clone();
         b.iter(|| {
-            binary_threshold(&mut img, 0.5, Some(Default::default()));
+            binary_threshold(img.view_mut(), 0.5, Some(Default::default()));
         })
     });
 
     c.bench_function("esdt", |b| {
         b.iter(|| {
-            esdf(&img_at, 200.0, 0.25, false, false);
+            esdf(&img_at, 200.0, 0.25, false, false, BorderMode::Transparent);
         })
     });
 
     c.bench_function("gamma", |b| {
         b.iter(|| {
             let mut img = img_lion_ndim.clone();
-            image_ops::gamma::gamma_ndim(&mut img, 2.2);
+            image_ops::gamma::gamma_ndim(img.view_mut(), 2.2);
         })
     });
+
+    for (name, factor) in [("up 2x", 2.0), ("down 4x", 0.25)] {
+        let size = img_t.size().scale(factor);
+        c.bench_function(&format!("scale catrom {name}"), |b| {
+            b.iter(|| {
+                scale(
+                    img_t.view(),
+                    size,
+                    Filter::CubicCatrom,
+                    BorderMode::Transparent,
+                )
+            })
+        });
+        c.bench_function(&format!("scale catrom {name} (resize crate)"), |b| {
+            b.iter(|| scale_resize_crate(img_t.view(), size, resize::Type::Catrom))
+        });
+        c.bench_function(&format!("scale lanczos3 {name}"), |b| {
+            b.iter(|| {
+                scale(
+                    img_t.view(),
+                    size,
+                    Filter::Lanczos3,
+                    BorderMode::Transparent,
+                )
+            })
+        });
+        c.bench_function(&format!("scale lanczos3 {name} (resize crate)"), |b| {
+            b.iter(|| scale_resize_crate(img_t.view(), size, resize::Type::Lanczos3))
+        });
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
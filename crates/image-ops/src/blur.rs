@@ -0,0 +1,491 @@
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+
+use glam::{Vec2, Vec3A, Vec4};
+use image_core::{Image, ImageView, Size};
+use rayon::prelude::*;
+
+use crate::util::BorderMode;
+
+/// A pixel type that can be blurred.
+pub trait BlurPixel:
+    Copy
+    + Default
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<f32, Output = Self>
+    + AddAssign
+    + SubAssign
+{
+    /// Returns the squared Euclidean distance between the two colors.
+    fn distance_sq(self, other: Self) -> f32;
+}
+
+impl BlurPixel for f32 {
+    #[inline]
+    fn distance_sq(self, other: Self) -> f32 {
+        let d = self - other;
+        d * d
+    }
+}
+impl BlurPixel for Vec2 {
+    #[inline]
+    fn distance_sq(self, other: Self) -> f32 {
+        self.distance_squared(other)
+    }
+}
+impl BlurPixel for Vec3A {
+    #[inline]
+    fn distance_sq(self, other: Self) -> f32 {
+        self.distance_squared(other)
+    }
+}
+impl BlurPixel for Vec4 {
+    #[inline]
+    fn distance_sq(self, other: Self) -> f32 {
+        self.distance_squared(other)
+    }
+}
+
+/// Blurs the given image with a Gaussian kernel.
+///
+/// The kernel is cut off at 3 standard deviations. A standard deviation of 0
+/// (or less) leaves the respective axis unchanged.
+pub fn gaussian_blur<P: BlurPixel>(
+    img: ImageView<P>,
+    sigma_x: f32,
+    sigma_y: f32,
+    border: BorderMode<P>,
+) -> Image<P> {
+    separable(
+        img,
+        &GaussianKernel::new(sigma_x),
+        &GaussianKernel::new(sigma_y),
+        &border,
+    )
+}
+
+/// Blurs the given image with a box kernel, i.e. every pixel becomes the
+/// average of the `(2 * radius_x + 1) x (2 * radius_y + 1)` pixels around it.
+///
+/// The running time per pixel does not depend on the radius.
+pub fn box_blur<P: BlurPixel>(
+    img: ImageView<P>,
+    radius_x: usize,
+    radius_y: usize,
+    border: BorderMode<P>,
+) -> Image<P> {
+    separable(img, &BoxKernel(radius_x), &BoxKernel(radius_y), &border)
+}
+
+/// Blurs the given image with a stack blur, i.e. a triangular kernel.
+///
+/// This is a fast approximation of [`gaussian_blur`]. The running time per
+/// pixel does not depend on the radius.
+pub fn stack_blur<P: BlurPixel>(
+    img: ImageView<P>,
+    radius_x: usize,
+    radius_y: usize,
+    border: BorderMode<P>,
+) -> Image<P> {
+    separable(img, &StackKernel(radius_x), &StackKernel(radius_y), &border)
+}
+
+/// Blurs the given image while preserving edges.
+///
+/// Each pixel is the average of its neighbors, weighted by their distance
+/// (a Gaussian with `sigma_space` in pixels) and by their color difference to
+/// the center pixel (a Gaussian with `sigma_color`). For images with values
+/// in `0..=1`, `sigma_color` is typically around `0.1`.
+pub fn bilateral_blur<P: BlurPixel>(
+    img: ImageView<P>,
+    sigma_space: f32,
+    sigma_color: f32,
+    border: BorderMode<P>,
+) -> Image<P> {
+    if img.size().is_empty() || !(sigma_space > 0.0 && sigma_color > 0.0) {
+        return img.into_owned();
+    }
+
+    let radius = (sigma_space * 2.0).ceil() as isize;
+    let space_coeff = -0.5 / (sigma_space * sigma_space);
+    let color_coeff = -0.5 / (sigma_color * sigma_color);
+
+    let mut window = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let d2 = (dx * dx + dy * dy) as f32;
+            if d2 <= (radius * radius) as f32 {
+                window.push((dx, dy, (d2 * space_coeff).exp()));
+            }
+        }
+    }
+
+    let size = img.size();
+    let mut data = vec![P::default(); size.len()];
+    data.par_chunks_exact_mut(size.width)
+        .enumerate()
+        .for_each(|(y, out)| {
+            let row = img.row(y);
+            for (x, out) in out.iter_mut().enumerate() {
+                let center = row[x];
+                let mut acc = P::default();
+                let mut sum = 0.0;
+                for &(dx, dy, w) in &window {
+                    let p = match border.get(img, x as isize + dx, y as isize + dy) {
+                        Some(p) => p,
+                        None => continue,
+                    };
+                    let w = w * (center.distance_sq(p) * color_coeff).exp();
+                    acc += p * w;
+                    sum += w;
+                }
+                // the center pixel always has a weight of 1, so the sum can't be 0
+                *out = acc * (1.0 / sum);
+            }
+        });
+
+    Image::new(size, data)
+}
+
+/// A 1D filter that is applied to rows and columns.
+trait LineFilter: Sync {
+    /// The number of pixels the filter reaches to either side.
+    fn radius(&self) -> usize;
+    /// Filters a line of `out.len()` pixels. `src` is the line padded by
+    /// [`LineFilter::radius`] pixels on both sides.
+    fn apply<P: BlurPixel>(&self, src: &[P], out: &mut [P]);
+}
+
+struct GaussianKernel {
+    weights: Vec<f32>,
+}
+
+impl GaussianKernel {
+    fn new(sigma: f32) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return Self { weights: vec![1.0] };
+        }
+
+        let radius = (sigma * 3.0).ceil() as isize;
+        let coeff = -0.5 / (sigma * sigma);
+        let mut weights: Vec<f32> = (-radius..=radius)
+            .map(|i| ((i * i) as f32 * coeff).exp())
+            .collect();
+        let sum: f32 = weights.iter().sum();
+        weights.iter_mut().for_each(|w| *w /= sum);
+        Self { weights }
+    }
+}
+
+impl LineFilter for GaussianKernel {
+    fn radius(&self) -> usize {
+        self.weights.len() / 2
+    }
+    fn apply<P: BlurPixel>(&self, src: &[P], out: &mut [P]) {
+        for (x, out) in out.iter_mut().enumerate() {
+            let mut acc = P::default();
+            for (&w, &p) in self.weights.iter().zip(&src[x..]) {
+                acc += p * w;
+            }
+            *out = acc;
+        }
+    }
+}
+
+struct BoxKernel(usize);
+
+impl LineFilter for BoxKernel {
+    fn radius(&self) -> usize {
+        self.0
+    }
+    fn apply<P: BlurPixel>(&self, src: &[P], out: &mut [P]) {
+        let n = self.0 * 2 + 1;
+        let scale = 1.0 / n as f32;
+
+        let mut sum = P::default();
+        for &p in &src[..n] {
+            sum += p;
+        }
+        let len = out.len();
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = sum * scale;
+            if x + 1 < len {
+                sum += src[x + n];
+                sum -= src[x];
+            }
+        }
+    }
+}
+
+struct StackKernel(usize);
+
+impl LineFilter for StackKernel {
+    fn radius(&self) -> usize {
+        self.0
+    }
+    fn apply<P: BlurPixel>(&self, src: &[P], out: &mut [P]) {
+        // The kernel weights are `r + 1 - |k|`. Moving the kernel one pixel to
+        // the right decreases the weights of the left half (including the
+        // center) by 1 and increases the weights of the pixels to the right of
+        // the center by 1.
+        let r = self.0;
+        let scale = 1.0 / ((r + 1) * (r + 1)) as f32;
+        let len = out.len();
+
+        let mut sum = P::default();
+        for (i, &p) in src[..2 * r + 1].iter().enumerate() {
+            sum += p * (r + 1 - i.abs_diff(r)) as f32;
+        }
+        // sum of src[x..=x+r]
+        let mut left = P::default();
+        for &p in &src[..=r] {
+            left += p;
+        }
+        // sum of src[x+r+1..=x+2r+1]
+        let mut right = P::default();
+        if len > 1 {
+            for &p in &src[r + 1..=2 * r + 1] {
+                right += p;
+            }
+        }
+
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = sum * scale;
+            if x + 1 < len {
+                sum += right;
+                sum -= left;
+            }
+            if x + 2 < len {
+                left += src[x + r + 1];
+                left -= src[x];
+                right += src[x + 2 * r + 2];
+                right -= src[x + r + 1];
+            }
+        }
+    }
+}
+
+fn separable<P: BlurPixel>(
+    img: ImageView<P>,
+    filter_x: &impl LineFilter,
+    filter_y: &impl LineFilter,
+    border: &BorderMode<P>,
+) -> Image<P> {
+    if img.size().is_empty() {
+        return img.into_owned();
+    }
+
+    let horizontal = if filter_x.radius() > 0 {
+        filter_rows(img, filter_x, border)
+    } else {
+        img.into_owned()
+    };
+    if filter_y.radius() == 0 {
+        return horizontal;
+    }
+
+    // filter columns as the rows of the transposed image
+    let transposed = transpose(horizontal.view());
+    transpose(filter_rows(transposed.view(), filter_y, border).view())
+}
+
+/// Applies the given filter to all rows of the given image.
+fn filter_rows<P: BlurPixel>(
+    img: ImageView<P>,
+    filter: &impl LineFilter,
+    border: &BorderMode<P>,
+) -> Image<P> {
+    let w = img.width();
+    let r = filter.radius();
+
+    // Pixels outside the image are zero for transparent borders, so we divide
+    // by the sum of the weights of the pixels inside the image instead.
+    let normalize: Option<Vec<f32>> = match border {
+        BorderMode::Transparent => {
+            let mask: Vec<f32> = (0..w + 2 * r)
+                .map(|i| (r..r + w).contains(&i) as u8 as f32)
+                .collect();
+            let mut coverage = vec![0.0; w];
+            filter.apply(&mask, &mut coverage);
+            Some(coverage.into_iter().map(|c| 1.0 / c).collect())
+        }
+        _ => None,
+    };
+    let outside = |row: &[P], x: isize| match border.index(x, w) {
+        Some(x) => row[x],
+        None => match border {
+            BorderMode::Constant(c) => *c,
+            _ => P::default(),
+        },
+    };
+
+    let mut data = vec![P::default(); img.len()];
+    data.par_chunks_exact_mut(w)
+        .enumerate()
+        .for_each_init(Vec::new, |line, (y, out)| {
+            let row = img.row(y);
+            line.clear();
+            line.extend((0..r).map(|i| outside(row, i as isize - r as isize)));
+            line.extend_from_slice(row);
+            line.extend((0..r).map(|i| outside(row, (w + i) as isize)));
+
+            filter.apply(line, out);
+            if let Some(normalize) = &normalize {
+                for (p, &n) in out.iter_mut().zip(normalize) {
+                    *p = *p * n;
+                }
+            }
+        });
+
+    Image::new(img.size(), data)
+}
+
+fn transpose<P: Copy + Default + Send + Sync>(img: ImageView<P>) -> Image<P> {
+    let size = Size::new(img.height(), img.width());
+    let mut data = vec![P::default(); size.len()];
+    data.par_chunks_exact_mut(size.width)
+        .enumerate()
+        .for_each(|(x, out)| {
+            for (y, out) in out.iter_mut().enumerate() {
+                *out = img.row(y)[x];
+            }
+        });
+    Image::new(size, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3A, Vec4};
+    use image_core::{Image, Size};
+    use test_util::{
+        data::{read_abstract_transparent, read_portrait},
+        fixtures::{assert_close, pattern},
+        snap::ImageSnapshot,
+    };
+
+    use crate::util::BorderMode;
+
+    fn borders<P: Copy>(constant: P) -> [BorderMode<P>; 5] {
+        [
+            BorderMode::Clamp,
+            BorderMode::Reflect,
+            BorderMode::Wrap,
+            BorderMode::Constant(constant),
+            BorderMode::Transparent,
+        ]
+    }
+
+    /// Convolves the given image with the given 1D kernel along both axes
+    /// without any tricks.
+    fn naive(img: &Image<f32>, kernel: &[f32], border: BorderMode<f32>) -> Image<f32> {
+        let r = (kernel.len() / 2) as isize;
+        let pass = |img: &Image<f32>, horizontal: bool| {
+            img.map_pos(|_, x, y| {
+                let mut acc = 0.0;
+                let mut sum = 0.0;
+                for (k, &w) in (-r..=r).zip(kernel) {
+                    let (x, y) = if horizontal {
+                        (x as isize + k, y as isize)
+                    } else {
+                        (x as isize, y as isize + k)
+                    };
+                    if let Some(p) = border.get(img.view(), x, y) {
+                        acc += p * w;
+                        sum += w;
+                    }
+                }
+                acc / sum
+            })
+        };
+        pass(&pass(img, true), false)
+    }
+
+    #[test]
+    fn constant() {
+        let img = Image::from_const(Size::new(20, 10), Vec4::new(0.2, 0.4, 0.6, 0.8));
+        let close = |a: &Image<Vec4>| {
+            a.data()
+                .iter()
+                .all(|p| (*p - Vec4::new(0.2, 0.4, 0.6, 0.8)).abs().max_element() < 1e-5)
+        };
+
+        for border in borders(Vec4::new(0.2, 0.4, 0.6, 0.8)) {
+            assert!(close(&super::gaussian_blur(img.view(), 2.5, 1.0, border)));
+            assert!(close(&super::box_blur(img.view(), 3, 12, border)));
+            assert!(close(&super::stack_blur(img.view(), 12, 3, border)));
+            assert!(close(&super::bilateral_blur(img.view(), 2.0, 0.1, border)));
+        }
+    }
+
+    #[test]
+    fn matches_naive() {
+        let img: Image<f32> = pattern(Size::new(13, 7));
+        for border in borders(0.5) {
+            let r = 4;
+
+            let box_kernel = vec![1.0; 2 * r + 1];
+            let result = super::box_blur(img.view(), r, r, border);
+            assert_close(result, naive(&img, &box_kernel, border), 1e-5);
+
+            let stack_kernel: Vec<f32> = (0..2 * r + 1)
+                .map(|i| (r + 1 - i.abs_diff(r)) as f32)
+                .collect();
+            let result = super::stack_blur(img.view(), r, r, border);
+            assert_close(result, naive(&img, &stack_kernel, border), 1e-5);
+
+            let gauss = super::GaussianKernel::new(1.5);
+            let result = super::gaussian_blur(img.view(), 1.5, 1.5, border);
+            assert_close(result, naive(&img, &gauss.weights, border), 1e-5);
+        }
+
+        // a radius of 0 doesn't change the image
+        assert_eq!(
+            super::box_blur(img.view(), 0, 0, BorderMode::Clamp).data(),
+            img.data()
+        );
+        assert_eq!(
+            super::gaussian_blur(img.view(), 0.0, 0.0, BorderMode::Clamp).data(),
+            img.data()
+        );
+    }
+
+    #[test]
+    fn gaussian() {
+        let original = read_portrait();
+        super::gaussian_blur(original.view(), 4.0, 4.0, BorderMode::Reflect)
+            .snapshot("blur_gaussian");
+        super::gaussian_blur(original.view(), 12.0, 0.0, BorderMode::Transparent)
+            .snapshot("blur_gaussian_horizontal");
+    }
+
+    #[test]
+    fn box_and_stack() {
+        let original = read_portrait();
+        super::box_blur(original.view(), 6, 6, BorderMode::Clamp).snapshot("blur_box");
+        super::stack_blur(original.view(), 10, 10, BorderMode::Wrap).snapshot("blur_stack");
+
+        let original = read_abstract_transparent();
+        super::stack_blur(original.view(), 20, 20, BorderMode::Constant(Vec4::ZERO))
+            .snapshot("blur_stack_alpha");
+    }
+
+    #[test]
+    fn bilateral() {
+        let original = read_portrait();
+        let small = original.crop(80, 100, 180, 200);
+        super::bilateral_blur(small.view(), 3.0, 0.1, BorderMode::Reflect)
+            .snapshot("blur_bilateral");
+
+        // a tiny color sigma keeps the image as is
+        let result = super::bilateral_blur(small.view(), 3.0, 1e-6, BorderMode::Reflect);
+        let max_diff = result
+            .data()
+            .iter()
+            .zip(small.data())
+            .map(|(a, b): (&Vec3A, &Vec3A)| (*a - *b).abs().max_element())
+            .fold(0.0, f32::max);
+        assert!(max_diff < 1e-5);
+    }
+}
@@ -0,0 +1,246 @@
+use image_core::{Image, ImageView, NDimImage, NDimView, Shape, Size};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelError {
+    /// No images were given.
+    Empty,
+    /// Not all images have the same size.
+    SizeMismatch { expected: Size, actual: Size },
+    /// A channel index is larger than the number of channels of the image.
+    ChannelOutOfBounds { channel: usize, channels: usize },
+    /// The image does not have an alpha channel.
+    ///
+    /// Only images with 2 (gray + alpha) or 4 (RGBA) channels have an alpha
+    /// channel.
+    NoAlpha { channels: usize },
+}
+
+/// Splits the given image into its channels.
+pub fn split<T: Copy, const N: usize>(img: ImageView<[T; N]>) -> [Image<T>; N] {
+    std::array::from_fn(|c| img.map(|p| p[c]))
+}
+
+/// Splits the given image into its channels.
+pub fn split_ndim<T: Copy>(img: NDimView<T>) -> Vec<Image<T>> {
+    let channels = img.channels();
+    let size = img.size();
+
+    (0..channels)
+        .map(|c| {
+            let data = img.data().iter().skip(c).step_by(channels).copied();
+            Image::new(size, data.collect())
+        })
+        .collect()
+}
+
+/// Merges the given channels into a single image.
+///
+/// All channels must have the same size.
+pub fn merge<T: Copy, const N: usize>(channels: [ImageView<T>; N]) -> Image<[T; N]> {
+    assert_ne!(N, 0);
+    let size = channels[0].size();
+    for c in &channels {
+        assert_eq!(c.size(), size, "All channels must have the same size");
+    }
+
+    let mut data = Vec::with_capacity(size.len());
+    for y in 0..size.height {
+        let rows = channels.map(|c| c.row(y));
+        data.extend((0..size.width).map(|x| rows.map(|r| r[x])));
+    }
+    Image::new(size, data)
+}
+
+/// Merges the channels of the given images into a single image.
+///
+/// The channels of the result are the channels of all images in order. E.g.
+/// merging an RGB image and a grayscale image will result in an RGBA image.
+pub fn merge_ndim<T: Copy>(images: &[NDimView<T>]) -> Result<NDimImage<T>, ChannelError> {
+    let first = images.first().ok_or(ChannelError::Empty)?;
+    let size = first.size();
+    if let Some(img) = images.iter().find(|img| img.size() != size) {
+        return Err(ChannelError::SizeMismatch {
+            expected: size,
+            actual: img.size(),
+        });
+    }
+
+    let shape = Shape::from_size(size, images.iter().map(|img| img.channels()).sum());
+    let mut data = Vec::with_capacity(shape.len());
+    for i in 0..size.len() {
+        for img in images {
+            let channels = img.channels();
+            data.extend_from_slice(&img.data()[i * channels..(i + 1) * channels]);
+        }
+    }
+    Ok(NDimImage::new(shape, data))
+}
+
+/// Reorders the channels of the given image.
+///
+/// Channel `i` of the result is channel `order[i]` of the given image. Channels
+/// may be repeated or left out, so this can also be used to e.g. convert
+/// BGR to RGB (`[2, 1, 0]`) or grayscale to RGB (`[0, 0, 0]`).
+///
+/// # Panics
+///
+/// Panics if any index in `order` is `>= N`.
+pub fn swizzle<T: Copy, const N: usize, const M: usize>(
+    img: ImageView<[T; N]>,
+    order: [usize; M],
+) -> Image<[T; M]> {
+    assert!(
+        order.iter().all(|&c| c < N),
+        "Channel index out of bounds for an image with {} channels",
+        N
+    );
+    img.map(|p| order.map(|c| p[c]))
+}
+
+/// Reorders the channels of the given image.
+///
+/// Channel `i` of the result is channel `order[i]` of the given image. Channels
+/// may be repeated or left out.
+pub fn swizzle_ndim<T: Copy>(
+    img: NDimView<T>,
+    order: &[usize],
+) -> Result<NDimImage<T>, ChannelError> {
+    let channels = img.channels();
+    if let Some(&channel) = order.iter().find(|&&c| c >= channels) {
+        return Err(ChannelError::ChannelOutOfBounds { channel, channels });
+    }
+
+    let shape = Shape::from_size(img.size(), order.len());
+    let mut data = Vec::with_capacity(shape.len());
+    for pixel in img.data().chunks_exact(channels) {
+        data.extend(order.iter().map(|&c| pixel[c]));
+    }
+    Ok(NDimImage::new(shape, data))
+}
+
+/// Adds an alpha channel with the given value to an RGB image.
+pub fn add_alpha<T: Copy>(img: ImageView<[T; 3]>, alpha: T) -> Image<[T; 4]> {
+    img.map(|&[r, g, b]| [r, g, b, alpha])
+}
+
+/// Adds an alpha channel with the given value as the last channel of the image.
+///
+/// Grayscale images will become gray + alpha images and RGB images will become
+/// RGBA images.
+pub fn add_alpha_ndim<T: Copy>(img: NDimView<T>, alpha: T) -> NDimImage<T> {
+    let channels = img.channels();
+    let shape = Shape::from_size(img.size(), channels + 1);
+    let mut data = Vec::with_capacity(shape.len());
+    if channels == 0 {
+        data.resize(shape.len(), alpha);
+    } else {
+        for pixel in img.data().chunks_exact(channels) {
+            data.extend_from_slice(pixel);
+            data.push(alpha);
+        }
+    }
+    NDimImage::new(shape, data)
+}
+
+/// Removes the alpha channel of an RGBA image.
+pub fn drop_alpha<T: Copy>(img: ImageView<[T; 4]>) -> Image<[T; 3]> {
+    img.map(|&[r, g, b, _]| [r, g, b])
+}
+
+/// Removes the alpha channel of a gray + alpha or RGBA image.
+pub fn drop_alpha_ndim<T: Copy>(img: NDimView<T>) -> Result<NDimImage<T>, ChannelError> {
+    match img.channels() {
+        2 => swizzle_ndim(img, &[0]),
+        4 => swizzle_ndim(img, &[0, 1, 2]),
+        channels => Err(ChannelError::NoAlpha { channels }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image_core::{Image, NDimImage};
+    use test_util::{data::read_flower_transparent, snap::ImageSnapshot};
+
+    use super::ChannelError;
+
+    fn read_flower_rgba() -> Image<[f32; 4]> {
+        read_flower_transparent().map(|p| p.to_array())
+    }
+
+    #[test]
+    fn split_merge() {
+        let original = read_flower_rgba();
+
+        let [r, g, b, a] = super::split(original.view());
+        a.snapshot("channel_split_alpha");
+
+        let merged = super::merge([r.view(), g.view(), b.view(), a.view()]);
+        assert_eq!(merged.data(), original.data());
+
+        let ndim: NDimImage = original.clone().into();
+        let channels = super::split_ndim(ndim.view());
+        assert_eq!(channels.len(), 4);
+        assert_eq!(channels[0].data(), r.data());
+        assert_eq!(channels[3].data(), a.data());
+    }
+
+    #[test]
+    fn merge_ndim() {
+        let original: NDimImage = read_flower_rgba().into();
+
+        let rgb = super::drop_alpha_ndim(original.view()).unwrap();
+        let alpha = super::swizzle_ndim(original.view(), &[3]).unwrap();
+        let merged = super::merge_ndim(&[rgb.view(), alpha.view()]).unwrap();
+        assert_eq!(merged.shape(), original.shape());
+        assert_eq!(merged.data(), original.data());
+
+        let small = NDimImage::<f32>::zeros(image_core::Shape::new(1, 1, 1));
+        assert!(matches!(
+            super::merge_ndim(&[rgb.view(), small.view()]),
+            Err(ChannelError::SizeMismatch { .. })
+        ));
+        assert_eq!(
+            super::merge_ndim::<f32>(&[]).unwrap_err(),
+            ChannelError::Empty
+        );
+    }
+
+    #[test]
+    fn swizzle() {
+        let original = read_flower_rgba();
+
+        let bgr = super::swizzle(original.view(), [2, 1, 0]);
+        bgr.snapshot("channel_swizzle_bgr");
+
+        let ndim: NDimImage = original.clone().into();
+        let bgr_ndim = super::swizzle_ndim(ndim.view(), &[2, 1, 0]).unwrap();
+        assert_eq!(bgr_ndim.channels(), 3);
+        assert_eq!(bgr_ndim.data(), NDimImage::from(bgr).data());
+
+        assert_eq!(
+            super::swizzle_ndim(ndim.view(), &[0, 4]).unwrap_err(),
+            ChannelError::ChannelOutOfBounds {
+                channel: 4,
+                channels: 4
+            }
+        );
+    }
+
+    #[test]
+    fn add_drop_alpha() {
+        let original = read_flower_rgba();
+
+        let rgb = super::drop_alpha(original.view());
+        let rgba = super::add_alpha(rgb.view(), 1.0);
+        assert!(rgba.data().iter().all(|p| p[3] == 1.0));
+        assert_eq!(super::drop_alpha(rgba.view()).data(), rgb.data());
+
+        let ndim: NDimImage = rgb.clone().into();
+        let ndim_rgba = super::add_alpha_ndim(ndim.view(), 1.0);
+        assert_eq!(ndim_rgba.data(), NDimImage::from(rgba).data());
+        assert_eq!(
+            super::drop_alpha_ndim(ndim.view()).unwrap_err(),
+            ChannelError::NoAlpha { channels: 3 }
+        );
+    }
+}
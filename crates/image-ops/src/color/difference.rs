@@ -0,0 +1,96 @@
+use glam::Vec3A;
+
+/// The CIE76 color difference of two CIE L*a*b* colors, which is their
+/// Euclidean distance.
+#[inline]
+pub fn delta_e_76(a: Vec3A, b: Vec3A) -> f32 {
+    a.distance(b)
+}
+
+/// The CIEDE2000 color difference of two CIE L*a*b* colors.
+///
+/// This follows "The CIEDE2000 Color-Difference Formula: Implementation Notes,
+/// Supplementary Test Data, and Mathematical Observations" by Sharma et al.
+/// with all weighting factors set to 1.
+pub fn delta_e_2000(a: Vec3A, b: Vec3A) -> f32 {
+    const POW_25_7: f32 = 6103515625.0;
+
+    let (l1, a1, b1) = (a.x, a.y, a.z);
+    let (l2, a2, b2) = (b.x, b.y, b.z);
+
+    let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let c_mean_7 = c_mean.powi(7);
+    let g = 0.5 * (1.0 - (c_mean_7 / (c_mean_7 + POW_25_7)).sqrt());
+
+    let a1 = a1 * (1.0 + g);
+    let a2 = a2 * (1.0 + g);
+    let c1 = a1.hypot(b1);
+    let c2 = a2.hypot(b2);
+    let hue = |a: f32, b: f32| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1 = hue(a1, b1);
+    let h2 = hue(a2, b2);
+    let achromatic = c1 * c2 == 0.0;
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if achromatic {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if achromatic {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let cos = |degrees: f32| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_mean - 30.0) + 0.24 * cos(2.0 * h_mean) + 0.32 * cos(3.0 * h_mean + 6.0)
+            - 0.20 * cos(4.0 * h_mean - 63.0);
+    let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let c_mean_7 = c_mean.powi(7);
+    let r_c = 2.0 * (c_mean_7 / (c_mean_7 + POW_25_7)).sqrt();
+    let l_50 = (l_mean - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_50 / (20.0 + l_50).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let l = delta_l / s_l;
+    let c = delta_c / s_c;
+    let h = delta_h / s_h;
+    (l * l + c * c + h * h + r_t * c * h).max(0.0).sqrt()
+}
+
+/// The squared "redmean" color difference of two sRGB colors. This is a
+/// weighted Euclidean distance where the weights of the red and blue channels
+/// depend on the mean red value of both colors.
+///
+/// The weights are scaled such that they sum to 3, so the result is comparable
+/// to the squared Euclidean distance.
+#[inline]
+pub fn redmean_2(a: Vec3A, b: Vec3A) -> f32 {
+    let r_mean = (a.x + b.x) / 2.0;
+    let d = a - b;
+    let d = d * d;
+    ((2.0 + r_mean) * d.x + 4.0 * d.y + (3.0 - r_mean) * d.z) / 3.0
+}
@@ -0,0 +1,394 @@
+mod difference;
+mod model;
+mod srgb;
+
+pub use difference::*;
+pub use model::*;
+pub use srgb::*;
+
+use glam::{Vec3A, Vec4};
+use image_core::{ImageViewMut, NDimViewMut};
+use rayon::prelude::*;
+
+/// A color model (and encoding) of the color channels of an image.
+///
+/// Alpha channels are never changed by color conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorModel {
+    /// Gamma-encoded RGB using the piecewise sRGB transfer function.
+    Srgb,
+    /// Linear light RGB with sRGB primaries.
+    LinearRgb,
+    /// Hue, saturation, value of sRGB. All components are in 0..=1.
+    Hsv,
+    /// Hue, saturation, lightness of sRGB. All components are in 0..=1.
+    Hsl,
+    /// CIE L*a*b* with a D65 white point. L* is in 0..=100.
+    Lab,
+    /// The cylindrical form of [`ColorModel::Lab`]. The hue is in degrees.
+    Lch,
+    /// The Oklab perceptual color space.
+    Oklab,
+    /// The cylindrical form of [`ColorModel::Oklab`]. The hue is in degrees.
+    Oklch,
+    /// Full-range YCbCr of sRGB. Cb and Cr are offset by 0.5.
+    YCbCr(YCbCrStandard),
+}
+
+/// The model all conversions go through.
+///
+/// Models derived from gamma-encoded RGB use sRGB as the hub to avoid a round
+/// trip through linear light, and vice versa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hub {
+    Srgb,
+    Linear,
+}
+
+impl ColorModel {
+    fn hub(self) -> Hub {
+        match self {
+            ColorModel::Srgb | ColorModel::Hsv | ColorModel::Hsl | ColorModel::YCbCr(_) => {
+                Hub::Srgb
+            }
+            ColorModel::LinearRgb
+            | ColorModel::Lab
+            | ColorModel::Lch
+            | ColorModel::Oklab
+            | ColorModel::Oklch => Hub::Linear,
+        }
+    }
+
+    /// Converts a color of this model into the hub of this model.
+    #[inline]
+    fn decode(self, c: Vec3A) -> Vec3A {
+        match self {
+            ColorModel::Srgb | ColorModel::LinearRgb => c,
+            ColorModel::Hsv => hsv_to_rgb(c),
+            ColorModel::Hsl => hsl_to_rgb(c),
+            ColorModel::Lab => lab_to_linear(c),
+            ColorModel::Lch => lab_to_linear(lch_to_lab(c)),
+            ColorModel::Oklab => oklab_to_linear(c),
+            ColorModel::Oklch => oklab_to_linear(lch_to_lab(c)),
+            ColorModel::YCbCr(standard) => ycbcr_to_rgb(c, standard),
+        }
+    }
+    /// Converts a color in the hub of this model into this model.
+    #[inline]
+    fn encode(self, c: Vec3A) -> Vec3A {
+        match self {
+            ColorModel::Srgb | ColorModel::LinearRgb => c,
+            ColorModel::Hsv => rgb_to_hsv(c),
+            ColorModel::Hsl => rgb_to_hsl(c),
+            ColorModel::Lab => linear_to_lab(c),
+            ColorModel::Lch => lab_to_lch(linear_to_lab(c)),
+            ColorModel::Oklab => linear_to_oklab(c),
+            ColorModel::Oklch => lab_to_lch(linear_to_oklab(c)),
+            ColorModel::YCbCr(standard) => rgb_to_ycbcr(c, standard),
+        }
+    }
+}
+
+/// Converts a single color from one model into another.
+#[inline]
+pub fn convert_color(c: Vec3A, from: ColorModel, to: ColorModel) -> Vec3A {
+    if from == to {
+        return c;
+    }
+
+    let c = from.decode(c);
+    let c = match (from.hub(), to.hub()) {
+        (Hub::Srgb, Hub::Linear) => srgb_to_linear_rgb(c),
+        (Hub::Linear, Hub::Srgb) => linear_to_srgb_rgb(c),
+        _ => c,
+    };
+    to.encode(c)
+}
+
+/// A pixel with 3 color channels and optionally an alpha channel.
+pub trait ColorPixel: Copy + Send + Sync {
+    fn color(self) -> Vec3A;
+    fn with_color(self, color: Vec3A) -> Self;
+}
+impl ColorPixel for Vec3A {
+    #[inline]
+    fn color(self) -> Vec3A {
+        self
+    }
+    #[inline]
+    fn with_color(self, color: Vec3A) -> Self {
+        color
+    }
+}
+impl ColorPixel for Vec4 {
+    #[inline]
+    fn color(self) -> Vec3A {
+        self.into()
+    }
+    #[inline]
+    fn with_color(self, color: Vec3A) -> Self {
+        color.extend(self.w)
+    }
+}
+
+/// Converts the colors of the given image from one model into another.
+pub fn convert<P: ColorPixel>(mut img: ImageViewMut<P>, from: ColorModel, to: ColorModel) {
+    if from == to {
+        return;
+    }
+
+    let rows: Vec<&mut [P]> = img.rows_mut().collect();
+    rows.into_par_iter().for_each(|row| {
+        for p in row.iter_mut() {
+            *p = p.with_color(convert_color(p.color(), from, to));
+        }
+    });
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorError {
+    /// Only images with 3 (RGB) or 4 (RGBA) channels can be converted.
+    UnsupportedChannels { channels: usize },
+}
+
+/// Converts the colors of the given image from one model into another.
+///
+/// The image must have 3 or 4 channels. The alpha channel of RGBA images is
+/// left unchanged.
+pub fn convert_ndim(
+    mut img: NDimViewMut,
+    from: ColorModel,
+    to: ColorModel,
+) -> Result<(), ColorError> {
+    const BLOCK_SIZE: usize = 1024 * 4;
+
+    fn map_pixels<const N: usize>(data: &mut [f32], f: impl Fn(Vec3A) -> Vec3A + Sync) {
+        data.par_chunks_mut(BLOCK_SIZE * N).for_each(|chunk| {
+            let (pixels, rest) = image_core::util::slice_as_chunks_mut::<f32, N>(chunk);
+            assert!(rest.is_empty());
+
+            for p in pixels {
+                let c = f(Vec3A::new(p[0], p[1], p[2]));
+                p[0] = c.x;
+                p[1] = c.y;
+                p[2] = c.z;
+            }
+        });
+    }
+    fn convert_const<const N: usize>(data: &mut [f32], from: ColorModel, to: ColorModel) {
+        let transfer = match (from.hub(), to.hub()) {
+            (Hub::Srgb, Hub::Linear) => Some(Hub::Linear),
+            (Hub::Linear, Hub::Srgb) => Some(Hub::Srgb),
+            _ => None,
+        };
+
+        // RGB images can use the fast SIMD implementation of the transfer
+        // function, so we do the conversion in 3 steps.
+        if N == 3 && transfer.is_some() {
+            if !matches!(from, ColorModel::Srgb | ColorModel::LinearRgb) {
+                map_pixels::<N>(data, |c| from.decode(c));
+            }
+            match transfer {
+                Some(Hub::Linear) => srgb_to_linear_slice(data),
+                _ => linear_to_srgb_slice(data),
+            }
+            if !matches!(to, ColorModel::Srgb | ColorModel::LinearRgb) {
+                map_pixels::<N>(data, |c| to.encode(c));
+            }
+            return;
+        }
+
+        map_pixels::<N>(data, |c| convert_color(c, from, to));
+    }
+
+    if from == to {
+        return Ok(());
+    }
+
+    match img.channels() {
+        3 => convert_const::<3>(img.data_mut(), from, to),
+        4 => convert_const::<4>(img.data_mut(), from, to),
+        channels => return Err(ColorError::UnsupportedChannels { channels }),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use image_core::{Image, NDimImage};
+    use test_util::{
+        data::{read_flower_transparent, read_portrait},
+        snap::ImageSnapshot,
+    };
+
+    use super::{ColorModel, ColorPixel, YCbCrStandard};
+
+    const MODELS: [ColorModel; 11] = [
+        ColorModel::Srgb,
+        ColorModel::LinearRgb,
+        ColorModel::Hsv,
+        ColorModel::Hsl,
+        ColorModel::Lab,
+        ColorModel::Lch,
+        ColorModel::Oklab,
+        ColorModel::Oklch,
+        ColorModel::YCbCr(YCbCrStandard::Bt601),
+        ColorModel::YCbCr(YCbCrStandard::Bt709),
+        ColorModel::YCbCr(YCbCrStandard::Bt2020),
+    ];
+
+    fn assert_close(a: Vec3A, b: Vec3A, epsilon: f32) {
+        assert!(
+            (a - b).abs().max_element() <= epsilon,
+            "{} != {} (epsilon {})",
+            a,
+            b,
+            epsilon
+        );
+    }
+
+    #[test]
+    fn known_values() {
+        let white = Vec3A::ONE;
+        let red = Vec3A::X;
+        let c = |c, to| super::convert_color(c, ColorModel::Srgb, to);
+
+        assert_close(c(white, ColorModel::LinearRgb), white, 1e-6);
+        assert_close(c(white, ColorModel::Lab), Vec3A::new(100.0, 0.0, 0.0), 1e-3);
+        assert_close(c(white, ColorModel::Oklab), Vec3A::new(1.0, 0.0, 0.0), 1e-4);
+        assert_close(c(red, ColorModel::Hsv), Vec3A::new(0.0, 1.0, 1.0), 1e-6);
+        assert_close(c(red, ColorModel::Hsl), Vec3A::new(0.0, 1.0, 0.5), 1e-6);
+        assert_close(
+            c(red, ColorModel::Lab),
+            Vec3A::new(53.24, 80.09, 67.20),
+            1e-2,
+        );
+        assert_close(
+            c(red, ColorModel::Oklab),
+            Vec3A::new(0.628, 0.2249, 0.1258),
+            1e-3,
+        );
+        assert_close(
+            c(
+                Vec3A::new(0.5, 0.5, 0.5),
+                ColorModel::YCbCr(YCbCrStandard::Bt709),
+            ),
+            Vec3A::new(0.5, 0.5, 0.5),
+            1e-6,
+        );
+        assert_close(
+            c(Vec3A::splat(0.5), ColorModel::LinearRgb),
+            Vec3A::splat(0.21404114),
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn delta_e_2000() {
+        // test data from Sharma et al.
+        let pairs = [
+            ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+            ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+            ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+            ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0),
+            (
+                [60.2574, -34.0099, 36.2677],
+                [60.4626, -34.1751, 39.4387],
+                1.2644,
+            ),
+            (
+                [22.7233, 20.0904, -46.694],
+                [23.0331, 14.973, -42.5619],
+                2.0373,
+            ),
+        ];
+        for (a, b, expected) in pairs {
+            let (a, b) = (Vec3A::from(a), Vec3A::from(b));
+            let actual = super::delta_e_2000(a, b);
+            assert!(
+                (actual - expected).abs() < 1e-3,
+                "{} != {}",
+                actual,
+                expected
+            );
+            assert!((super::delta_e_2000(b, a) - expected).abs() < 1e-3);
+        }
+        assert_eq!(super::delta_e_2000(Vec3A::ONE, Vec3A::ONE), 0.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let img = read_portrait();
+        for from in MODELS {
+            for to in MODELS {
+                for &p in img.data().iter().step_by(97) {
+                    let c = super::convert_color(p, ColorModel::Srgb, from);
+                    let back = super::convert_color(super::convert_color(c, from, to), to, from);
+                    // compare in sRGB, because hues wrap around and are
+                    // undefined for grays
+                    assert_close(super::convert_color(back, from, ColorModel::Srgb), p, 1e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ndim_matches_pixels() {
+        fn check<P: ColorPixel>(original: Image<P>)
+        where
+            Image<P>: Into<NDimImage>,
+        {
+            for to in MODELS {
+                let mut img: NDimImage = original.clone().into();
+                super::convert_ndim(img.view_mut(), ColorModel::Srgb, to).unwrap();
+
+                let mut expected = original.clone();
+                super::convert(expected.view_mut(), ColorModel::Srgb, to);
+                let expected: NDimImage = expected.into();
+
+                // The RGB path uses SIMD, so results may differ slightly. We
+                // compare in sRGB, because hues are unstable for grays.
+                let channels = img.channels();
+                let pixels = img.data().chunks(channels);
+                for (a, b) in pixels.zip(expected.data().chunks(channels)) {
+                    let to_srgb = |p: &[f32]| {
+                        super::convert_color(Vec3A::new(p[0], p[1], p[2]), to, ColorModel::Srgb)
+                    };
+                    assert_close(to_srgb(a), to_srgb(b), 1e-4);
+                    assert_eq!(a[3..], b[3..]);
+                }
+            }
+        }
+
+        check(read_flower_transparent());
+        check(read_portrait());
+
+        let mut img: NDimImage = read_portrait().into();
+        super::convert_ndim(img.view_mut(), ColorModel::Srgb, ColorModel::Oklab).unwrap();
+        super::convert_ndim(img.view_mut(), ColorModel::Oklab, ColorModel::Srgb).unwrap();
+        let expected: NDimImage = read_portrait().into();
+        for (a, b) in img.data().iter().zip(expected.data()) {
+            assert!((a - b).abs() <= 1e-4, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn convert_images() {
+        let mut img: NDimImage = read_portrait().into();
+        super::convert_ndim(img.view_mut(), ColorModel::Srgb, ColorModel::LinearRgb).unwrap();
+        img.snapshot("color_linear");
+
+        let mut img = read_portrait();
+        super::convert(img.view_mut(), ColorModel::Srgb, ColorModel::Hsv);
+        img.snapshot("color_hsv");
+
+        let mut img = read_portrait();
+        super::convert(
+            img.view_mut(),
+            ColorModel::Srgb,
+            ColorModel::YCbCr(YCbCrStandard::Bt709),
+        );
+        img.snapshot("color_ycbcr_709");
+    }
+}
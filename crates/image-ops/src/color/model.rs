@@ -0,0 +1,250 @@
+// the constants are copied from their respective specifications
+#![allow(clippy::excessive_precision)]
+
+use glam::Vec3A;
+
+use super::{linear_to_srgb, srgb_to_linear};
+
+#[inline]
+fn mat_mul(m: &[[f32; 3]; 3], v: Vec3A) -> Vec3A {
+    Vec3A::new(
+        Vec3A::from(m[0]).dot(v),
+        Vec3A::from(m[1]).dot(v),
+        Vec3A::from(m[2]).dot(v),
+    )
+}
+
+#[inline]
+pub fn srgb_to_linear_rgb(c: Vec3A) -> Vec3A {
+    Vec3A::new(
+        srgb_to_linear(c.x),
+        srgb_to_linear(c.y),
+        srgb_to_linear(c.z),
+    )
+}
+#[inline]
+pub fn linear_to_srgb_rgb(c: Vec3A) -> Vec3A {
+    Vec3A::new(
+        linear_to_srgb(c.x),
+        linear_to_srgb(c.y),
+        linear_to_srgb(c.z),
+    )
+}
+
+/// Converts RGB to HSV. All components of the result are in the range 0..=1,
+/// including the hue.
+#[inline]
+pub fn rgb_to_hsv(c: Vec3A) -> Vec3A {
+    let max = c.max_element();
+    let min = c.min_element();
+    let delta = max - min;
+
+    let h = hue(c, max, delta);
+    let s = if max > 0.0 { delta / max } else { 0.0 };
+    Vec3A::new(h, s, max)
+}
+/// Converts HSV to RGB.
+#[inline]
+pub fn hsv_to_rgb(c: Vec3A) -> Vec3A {
+    let (h, s, v) = (c.x, c.y, c.z);
+    let chroma = v * s;
+    from_hue_chroma(h, chroma) + (v - chroma)
+}
+
+/// Converts RGB to HSL. All components of the result are in the range 0..=1,
+/// including the hue.
+#[inline]
+pub fn rgb_to_hsl(c: Vec3A) -> Vec3A {
+    let max = c.max_element();
+    let min = c.min_element();
+    let delta = max - min;
+
+    let h = hue(c, max, delta);
+    let l = (max + min) * 0.5;
+    let s = if delta > 0.0 {
+        delta / (1.0 - (2.0 * l - 1.0).abs())
+    } else {
+        0.0
+    };
+    Vec3A::new(h, s, l)
+}
+/// Converts HSL to RGB.
+#[inline]
+pub fn hsl_to_rgb(c: Vec3A) -> Vec3A {
+    let (h, s, l) = (c.x, c.y, c.z);
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue_chroma(h, chroma) + (l - chroma * 0.5)
+}
+
+/// Returns the hue in the range 0..1.
+#[inline]
+fn hue(c: Vec3A, max: f32, delta: f32) -> f32 {
+    if delta <= 0.0 {
+        return 0.0;
+    }
+
+    let h = if max == c.x {
+        (c.y - c.z) / delta
+    } else if max == c.y {
+        (c.z - c.x) / delta + 2.0
+    } else {
+        (c.x - c.y) / delta + 4.0
+    };
+    (h / 6.0).rem_euclid(1.0)
+}
+#[inline]
+fn from_hue_chroma(h: f32, chroma: f32) -> Vec3A {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u8 {
+        0 => Vec3A::new(chroma, x, 0.0),
+        1 => Vec3A::new(x, chroma, 0.0),
+        2 => Vec3A::new(0.0, chroma, x),
+        3 => Vec3A::new(0.0, x, chroma),
+        4 => Vec3A::new(x, 0.0, chroma),
+        _ => Vec3A::new(chroma, 0.0, x),
+    }
+}
+
+/// The D65 white point in XYZ.
+const D65: Vec3A = Vec3A::new(0.95047, 1.0, 1.08883);
+
+const LINEAR_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
+const XYZ_TO_LINEAR: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660, 1.8760108, 0.0415560],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+/// Converts linear RGB to CIE L*a*b* (D65). L* is in the range 0..=100.
+#[inline]
+pub fn linear_to_lab(c: Vec3A) -> Vec3A {
+    const DELTA: f32 = 6.0 / 29.0;
+    fn f(t: f32) -> f32 {
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    }
+
+    let xyz = mat_mul(&LINEAR_TO_XYZ, c) / D65;
+    let (fx, fy, fz) = (f(xyz.x), f(xyz.y), f(xyz.z));
+    Vec3A::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+/// Converts CIE L*a*b* (D65) to linear RGB.
+#[inline]
+pub fn lab_to_linear(c: Vec3A) -> Vec3A {
+    const DELTA: f32 = 6.0 / 29.0;
+    fn f_inv(t: f32) -> f32 {
+        if t > DELTA {
+            t * t * t
+        } else {
+            3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+        }
+    }
+
+    let fy = (c.x + 16.0) / 116.0;
+    let fx = fy + c.y / 500.0;
+    let fz = fy - c.z / 200.0;
+    let xyz = Vec3A::new(f_inv(fx), f_inv(fy), f_inv(fz)) * D65;
+    mat_mul(&XYZ_TO_LINEAR, xyz)
+}
+
+/// Converts a Lab-like color (CIE L*a*b* or Oklab) to its cylindrical
+/// representation. The hue is in degrees in the range 0..360.
+#[inline]
+pub fn lab_to_lch(c: Vec3A) -> Vec3A {
+    let chroma = (c.y * c.y + c.z * c.z).sqrt();
+    let hue = c.z.atan2(c.y).to_degrees().rem_euclid(360.0);
+    Vec3A::new(c.x, chroma, hue)
+}
+/// Converts a cylindrical LCh color to its Lab-like representation.
+#[inline]
+pub fn lch_to_lab(c: Vec3A) -> Vec3A {
+    let (sin, cos) = c.z.to_radians().sin_cos();
+    Vec3A::new(c.x, c.y * cos, c.y * sin)
+}
+
+/// Converts linear RGB to Oklab.
+#[inline]
+pub fn linear_to_oklab(c: Vec3A) -> Vec3A {
+    const M1: [[f32; 3]; 3] = [
+        [0.4122214708, 0.5363325363, 0.0514459929],
+        [0.2119034982, 0.6806995451, 0.1073969566],
+        [0.0883024619, 0.2817188376, 0.6299787005],
+    ];
+    const M2: [[f32; 3]; 3] = [
+        [0.2104542553, 0.7936177850, -0.0040720468],
+        [1.9779984951, -2.4285922050, 0.4505937099],
+        [0.0259040371, 0.7827717662, -0.8086757660],
+    ];
+
+    let lms = mat_mul(&M1, c);
+    let lms = Vec3A::new(lms.x.cbrt(), lms.y.cbrt(), lms.z.cbrt());
+    mat_mul(&M2, lms)
+}
+/// Converts Oklab to linear RGB.
+#[inline]
+pub fn oklab_to_linear(c: Vec3A) -> Vec3A {
+    const M2_INV: [[f32; 3]; 3] = [
+        [1.0, 0.3963377774, 0.2158037573],
+        [1.0, -0.1055613458, -0.0638541728],
+        [1.0, -0.0894841775, -1.2914855480],
+    ];
+    const M1_INV: [[f32; 3]; 3] = [
+        [4.0767416621, -3.3077115913, 0.2309699292],
+        [-1.2684380046, 2.6097574011, -0.3413193965],
+        [-0.0041960863, -0.7034186147, 1.7076147010],
+    ];
+
+    let lms = mat_mul(&M2_INV, c);
+    mat_mul(&M1_INV, lms * lms * lms)
+}
+
+/// The luma coefficients of a YCbCr standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YCbCrStandard {
+    /// ITU-R BT.601 (SD video, JPEG).
+    Bt601,
+    /// ITU-R BT.709 (HD video).
+    Bt709,
+    /// ITU-R BT.2020 (UHD video).
+    Bt2020,
+}
+
+impl YCbCrStandard {
+    /// Returns the red and blue luma coefficients `(Kr, Kb)`.
+    pub fn coefficients(self) -> (f32, f32) {
+        match self {
+            YCbCrStandard::Bt601 => (0.299, 0.114),
+            YCbCrStandard::Bt709 => (0.2126, 0.0722),
+            YCbCrStandard::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Converts RGB to full-range YCbCr. Cb and Cr are offset by 0.5, so all
+/// components are in the range 0..=1.
+#[inline]
+pub fn rgb_to_ycbcr(c: Vec3A, standard: YCbCrStandard) -> Vec3A {
+    let (kr, kb) = standard.coefficients();
+    let y = kr * c.x + (1.0 - kr - kb) * c.y + kb * c.z;
+    let cb = (c.z - y) / (2.0 * (1.0 - kb)) + 0.5;
+    let cr = (c.x - y) / (2.0 * (1.0 - kr)) + 0.5;
+    Vec3A::new(y, cb, cr)
+}
+/// Converts full-range YCbCr to RGB.
+#[inline]
+pub fn ycbcr_to_rgb(c: Vec3A, standard: YCbCrStandard) -> Vec3A {
+    let (kr, kb) = standard.coefficients();
+    let y = c.x;
+    let r = y + 2.0 * (1.0 - kr) * (c.z - 0.5);
+    let b = y + 2.0 * (1.0 - kb) * (c.y - 0.5);
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    Vec3A::new(r, g, b)
+}
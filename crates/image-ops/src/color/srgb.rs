@@ -0,0 +1,66 @@
+use rayon::prelude::*;
+
+/// Converts an sRGB value into linear light using the piecewise sRGB EOTF.
+#[inline]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c > 0.04045 {
+        ((c + 0.055) / 1.055).powf(2.4)
+    } else {
+        c / 12.92
+    }
+}
+
+/// Converts a linear light value into sRGB using the piecewise inverse sRGB EOTF.
+#[inline]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c > 0.0031308 {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    } else {
+        c * 12.92
+    }
+}
+
+// we want to divide the image into chunks
+const BLOCK_SIZE: usize = 1024 * 8;
+
+/// Converts all sRGB values in the given slice into linear light.
+pub fn srgb_to_linear_slice(data: &mut [f32]) {
+    data.par_chunks_mut(BLOCK_SIZE).for_each(|chunk| {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                let (chunks, rest) = image_core::util::slice_as_chunks_mut::<f32, 8>(chunk);
+
+                rest.iter_mut().for_each(|f| *f = srgb_to_linear(*f));
+                chunks
+                    .iter_mut()
+                    .for_each(|f| unsafe { crate::util::avx2::srgb_to_linear(f) });
+                return;
+            }
+        }
+
+        // fallback
+        chunk.iter_mut().for_each(|f| *f = srgb_to_linear(*f));
+    });
+}
+
+/// Converts all linear light values in the given slice into sRGB.
+pub fn linear_to_srgb_slice(data: &mut [f32]) {
+    data.par_chunks_mut(BLOCK_SIZE).for_each(|chunk| {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                let (chunks, rest) = image_core::util::slice_as_chunks_mut::<f32, 8>(chunk);
+
+                rest.iter_mut().for_each(|f| *f = linear_to_srgb(*f));
+                chunks
+                    .iter_mut()
+                    .for_each(|f| unsafe { crate::util::avx2::linear_to_srgb(f) });
+                return;
+            }
+        }
+
+        // fallback
+        chunk.iter_mut().for_each(|f| *f = linear_to_srgb(*f));
+    });
+}
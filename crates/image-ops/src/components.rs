@@ -0,0 +1,294 @@
+use image_core::{Image, ImageView};
+
+/// Which neighbors of a pixel belong to the same component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only pixels that share an edge are connected.
+    Four,
+    /// Pixels that share an edge or a corner are connected.
+    Eight,
+}
+
+/// The statistics of a connected component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    /// The label of the component in [`Components::labels`].
+    pub label: u32,
+    /// The number of pixels of the component.
+    pub area: usize,
+    /// The x coordinate of the left column of the bounding box.
+    pub x: usize,
+    /// The y coordinate of the top row of the bounding box.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// The average of the coordinates of all pixels of the component.
+    pub centroid: (f32, f32),
+}
+
+/// The connected components of a binary mask.
+#[derive(Debug, Clone)]
+pub struct Components {
+    /// The label of each pixel. Background pixels are 0, and components are
+    /// numbered from 1 in the order of their first pixel (row by row).
+    pub labels: Image<u32>,
+    /// The statistics of each component. The region with label `i` is at
+    /// index `i - 1`.
+    pub regions: Vec<Region>,
+}
+
+/// Finds the connected components of all pixels above the given threshold.
+pub fn label_components(
+    img: ImageView<f32>,
+    threshold: f32,
+    connectivity: Connectivity,
+) -> Components {
+    let size = img.size();
+    let w = size.width;
+
+    // first pass: assign provisional labels and record which labels touch
+    let mut sets = DisjointSets::default();
+    let mut labels = vec![0_u32; size.len()];
+    for (y, row) in img.rows().enumerate() {
+        for (x, &p) in row.iter().enumerate() {
+            if p <= threshold {
+                continue;
+            }
+
+            let i = y * w + x;
+            let mut neighbors = [0; 4];
+            if x > 0 {
+                neighbors[0] = labels[i - 1];
+            }
+            if y > 0 {
+                neighbors[1] = labels[i - w];
+                if connectivity == Connectivity::Eight {
+                    if x > 0 {
+                        neighbors[2] = labels[i - w - 1];
+                    }
+                    if x + 1 < w {
+                        neighbors[3] = labels[i - w + 1];
+                    }
+                }
+            }
+
+            let label = match neighbors.iter().copied().filter(|&l| l != 0).min() {
+                Some(label) => {
+                    for &n in neighbors.iter().filter(|&&l| l != 0) {
+                        sets.union(label, n);
+                    }
+                    label
+                }
+                None => sets.add(),
+            };
+            labels[i] = label;
+        }
+    }
+
+    // second pass: number the components in order and gather statistics
+    let mut final_labels = vec![0_u32; sets.len() + 1];
+    let mut regions: Vec<Region> = Vec::new();
+    let mut sums: Vec<(f64, f64)> = Vec::new();
+    for (i, label) in labels.iter_mut().enumerate() {
+        if *label == 0 {
+            continue;
+        }
+
+        let root = sets.find(*label) as usize;
+        if final_labels[root] == 0 {
+            regions.push(Region {
+                label: regions.len() as u32 + 1,
+                area: 0,
+                x: usize::MAX,
+                y: usize::MAX,
+                width: 0,
+                height: 0,
+                centroid: (0.0, 0.0),
+            });
+            sums.push((0.0, 0.0));
+            final_labels[root] = regions.len() as u32;
+        }
+        *label = final_labels[root];
+
+        let (x, y) = (i % w, i / w);
+        let index = *label as usize - 1;
+        let region = &mut regions[index];
+        region.area += 1;
+        // abuse width and height as the max coordinates for now
+        region.x = region.x.min(x);
+        region.y = region.y.min(y);
+        region.width = region.width.max(x);
+        region.height = region.height.max(y);
+        sums[index].0 += x as f64;
+        sums[index].1 += y as f64;
+    }
+    for (region, (sum_x, sum_y)) in regions.iter_mut().zip(sums) {
+        region.width = region.width + 1 - region.x;
+        region.height = region.height + 1 - region.y;
+        let area = region.area as f64;
+        region.centroid = ((sum_x / area) as f32, (sum_y / area) as f32);
+    }
+
+    Components {
+        labels: Image::new(size, labels),
+        regions,
+    }
+}
+
+/// Sets all pixels of components with fewer than `min_area` pixels to 0.
+///
+/// Components are made up of all pixels above the given threshold.
+pub fn remove_small_components(
+    img: &mut Image<f32>,
+    threshold: f32,
+    connectivity: Connectivity,
+    min_area: usize,
+) {
+    let components = label_components(img.view(), threshold, connectivity);
+    let small: Vec<bool> = std::iter::once(false)
+        .chain(components.regions.iter().map(|r| r.area < min_area))
+        .collect();
+
+    for (p, &label) in img.data_mut().iter_mut().zip(components.labels.data()) {
+        if small[label as usize] {
+            *p = 0.0;
+        }
+    }
+}
+
+/// A union-find structure over the labels `1..=len`.
+#[derive(Default)]
+struct DisjointSets {
+    /// The parent of each label. Index 0 is unused.
+    parent: Vec<u32>,
+}
+
+impl DisjointSets {
+    fn len(&self) -> usize {
+        self.parent.len().saturating_sub(1)
+    }
+
+    fn add(&mut self) -> u32 {
+        if self.parent.is_empty() {
+            self.parent.push(0);
+        }
+        let label = self.parent.len() as u32;
+        self.parent.push(label);
+        label
+    }
+
+    fn find(&mut self, mut label: u32) -> u32 {
+        while self.parent[label as usize] != label {
+            // path halving
+            let grandparent = self.parent[self.parent[label as usize] as usize];
+            self.parent[label as usize] = grandparent;
+            label = grandparent;
+        }
+        label
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let a = self.find(a);
+        let b = self.find(b);
+        // the smaller label becomes the root
+        if a < b {
+            self.parent[b as usize] = a;
+        } else {
+            self.parent[a as usize] = b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_util::{data::read_binary_alpha, fixtures::parse_image, snap::ImageSnapshot};
+
+    use super::Connectivity;
+
+    #[test]
+    fn labels() {
+        let img = parse_image(&[
+            "##..#.", //
+            ".#.#..", //
+            "...#.#", //
+            "##...#", //
+            "#..###", //
+        ]);
+
+        let four = super::label_components(img.view(), 0.5, Connectivity::Four);
+        assert_eq!(
+            four.labels.data(),
+            [
+                1, 1, 0, 0, 2, 0, //
+                0, 1, 0, 3, 0, 0, //
+                0, 0, 0, 3, 0, 4, //
+                5, 5, 0, 0, 0, 4, //
+                5, 0, 0, 4, 4, 4, //
+            ]
+        );
+        let areas: Vec<usize> = four.regions.iter().map(|r| r.area).collect();
+        assert_eq!(areas, [3, 1, 2, 5, 3]);
+
+        let eight = super::label_components(img.view(), 0.5, Connectivity::Eight);
+        assert_eq!(
+            eight.labels.data(),
+            [
+                1, 1, 0, 0, 2, 0, //
+                0, 1, 0, 2, 0, 0, //
+                0, 0, 0, 2, 0, 3, //
+                4, 4, 0, 0, 0, 3, //
+                4, 0, 0, 3, 3, 3, //
+            ]
+        );
+
+        let region = &eight.regions[2];
+        assert_eq!(region.label, 3);
+        assert_eq!(region.area, 5);
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (3, 2, 3, 3)
+        );
+        assert_eq!(region.centroid, (4.4, 3.4));
+    }
+
+    #[test]
+    fn merges_labels() {
+        // a U shape only connects at the bottom
+        let img = parse_image(&[
+            "#.#.#", //
+            "#.#.#", //
+            "#####", //
+        ]);
+        let result = super::label_components(img.view(), 0.5, Connectivity::Four);
+        assert_eq!(result.regions.len(), 1);
+        assert!(result.labels.data().iter().all(|&l| l <= 1));
+        assert_eq!(result.regions[0].area, 11);
+
+        let empty = super::label_components(img.view(), 1.0, Connectivity::Eight);
+        assert!(empty.regions.is_empty());
+        assert!(empty.labels.data().iter().all(|&l| l == 0));
+    }
+
+    #[test]
+    fn remove_small_components() {
+        let mut img = parse_image(&[
+            "##..#", //
+            "##...", //
+            "...#.", //
+        ]);
+        super::remove_small_components(&mut img, 0.5, Connectivity::Four, 2);
+        assert_eq!(
+            img.data(),
+            parse_image(&[
+                "##...", //
+                "##...", //
+                ".....", //
+            ])
+            .data()
+        );
+
+        let mut mask = read_binary_alpha();
+        super::remove_small_components(&mut mask, 0.5, Connectivity::Eight, 400);
+        mask.snapshot("components_remove_small");
+    }
+}
@@ -0,0 +1,373 @@
+use image_core::{Image, ImageView, NDimImage, NDimView, Size};
+use rayon::prelude::*;
+
+use crate::util::BorderMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelError {
+    /// The kernel has a width or height of 0.
+    Empty,
+    /// The kernel contains infinite or NaN weights.
+    NonFinite,
+}
+
+/// A 2D convolution kernel.
+///
+/// The center of the kernel is the pixel `(width / 2, height / 2)`.
+#[derive(Debug, Clone)]
+pub struct Kernel {
+    weights: Image<f32>,
+    /// The weights rotated by 180°, so convolution can be done as correlation.
+    flipped: Image<f32>,
+    /// The horizontal and vertical factors of the flipped weights, if the
+    /// kernel is separable.
+    separated: Option<(Vec<f32>, Vec<f32>)>,
+}
+
+impl Kernel {
+    pub fn new(weights: Image<f32>) -> Result<Self, KernelError> {
+        if weights.size().is_empty() {
+            return Err(KernelError::Empty);
+        }
+        if !weights.data().iter().all(|w| w.is_finite()) {
+            return Err(KernelError::NonFinite);
+        }
+
+        let flipped = Image::new(
+            weights.size(),
+            weights.data().iter().rev().copied().collect(),
+        );
+        let separated = separate(flipped.view());
+        Ok(Self {
+            weights,
+            flipped,
+            separated,
+        })
+    }
+
+    /// Creates the kernel `y * x^T`, i.e. the kernel that first convolves
+    /// rows with `x` and then columns with `y`.
+    pub fn from_separable(x: &[f32], y: &[f32]) -> Result<Self, KernelError> {
+        let size = Size::new(x.len(), y.len());
+        Self::new(Image::from_fn(size, |i, j| y[j] * x[i]))
+    }
+
+    pub fn size(&self) -> Size {
+        self.weights.size()
+    }
+    pub fn weights(&self) -> ImageView<'_, f32> {
+        self.weights.view()
+    }
+
+    /// Whether the kernel can be applied as two 1D convolutions. This is
+    /// detected automatically and makes [`convolve`] a lot faster for large
+    /// kernels.
+    pub fn is_separable(&self) -> bool {
+        self.separated.is_some()
+    }
+
+    /// The position of the pixel of the flipped kernel that is multiplied
+    /// with the center pixel.
+    fn anchor(&self) -> (usize, usize) {
+        let Size { width, height } = self.size();
+        (width - 1 - width / 2, height - 1 - height / 2)
+    }
+}
+
+/// Tries to split the given kernel into a horizontal and a vertical factor.
+fn separate(kernel: ImageView<f32>) -> Option<(Vec<f32>, Vec<f32>)> {
+    // The kernel is separable iff it has a rank of 1. Then all rows are
+    // multiples of the row with the largest weight.
+    let (max_x, max_y, max) = kernel
+        .rows()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &w)| (x, y, w)))
+        .max_by(|a, b| a.2.abs().total_cmp(&b.2.abs()))?;
+    if max == 0.0 {
+        return None;
+    }
+
+    let x: Vec<f32> = kernel.row(max_y).iter().map(|w| w / max).collect();
+    let y: Vec<f32> = kernel.rows().map(|row| row[max_x]).collect();
+
+    let tolerance = max.abs() * 1e-5;
+    let is_separable = kernel.rows().zip(&y).all(|(row, &fy)| {
+        row.iter()
+            .zip(&x)
+            .all(|(&w, &fx)| (w - fy * fx).abs() <= tolerance)
+    });
+    is_separable.then_some((x, y))
+}
+
+/// Convolves the given image with the given kernel.
+///
+/// All channels are convolved independently. This is a true convolution, so
+/// the kernel is rotated by 180° compared to correlation (OpenCV's `filter2D`).
+/// This only makes a difference for asymmetric kernels like Sobel or emboss.
+///
+/// The color of [`BorderMode::Constant`] must have as many values as the
+/// image has channels. With [`BorderMode::Transparent`], pixels outside the
+/// image are ignored without changing the weights of the other pixels, which
+/// is the same as a constant border of 0.
+pub fn convolve(img: NDimView, kernel: &Kernel, border: BorderMode<&[f32]>) -> NDimImage {
+    let shape = img.shape();
+    if let BorderMode::Constant(value) = border {
+        assert_eq!(
+            value.len(),
+            shape.channels,
+            "the border color must have one value per channel"
+        );
+    }
+    if shape.is_empty() {
+        return NDimImage::new(shape, Vec::new());
+    }
+
+    let filter = RowFilter {
+        channels: shape.channels,
+        left: kernel.anchor().0,
+        border,
+    };
+    let top = kernel.anchor().1;
+    let row_len = shape.width * shape.channels;
+    let img_row = |y: usize| &img.data()[y * row_len..(y + 1) * row_len];
+    let source_row = |y: usize, j: usize| {
+        let y = y as isize + j as isize - top as isize;
+        border.index(y, shape.height)
+    };
+
+    let mut data = vec![0.0; shape.len()];
+    match &kernel.separated {
+        Some((kx, ky)) => {
+            let mut horizontal = vec![0.0; shape.len()];
+            horizontal
+                .par_chunks_exact_mut(row_len)
+                .enumerate()
+                .for_each_init(Vec::new, |padded, (y, out)| {
+                    filter.add_filtered(img_row(y), kx, padded, out);
+                });
+            let constant = constant_row_sum(border, kx);
+
+            data.par_chunks_exact_mut(row_len)
+                .enumerate()
+                .for_each(|(y, out)| {
+                    for (j, &k) in ky.iter().enumerate() {
+                        match source_row(y, j) {
+                            Some(r) => add_scaled(out, &horizontal[r * row_len..], k),
+                            None => add_constant(out, &constant, k),
+                        }
+                    }
+                });
+        }
+        None => {
+            data.par_chunks_exact_mut(row_len)
+                .enumerate()
+                .for_each_init(Vec::new, |padded, (y, out)| {
+                    for (j, weights) in kernel.flipped.rows().enumerate() {
+                        match source_row(y, j) {
+                            Some(r) => filter.add_filtered(img_row(r), weights, padded, out),
+                            None => add_constant(out, &constant_row_sum(border, weights), 1.0),
+                        }
+                    }
+                });
+        }
+    }
+
+    NDimImage::new(shape, data)
+}
+
+/// Correlates rows of interleaved pixels with a 1D kernel.
+struct RowFilter<'a> {
+    channels: usize,
+    /// The number of pixels the kernel reaches to the left.
+    left: usize,
+    border: BorderMode<&'a [f32]>,
+}
+
+impl RowFilter<'_> {
+    /// Adds the given row correlated with the given weights onto `out`.
+    fn add_filtered(&self, row: &[f32], weights: &[f32], padded: &mut Vec<f32>, out: &mut [f32]) {
+        let c = self.channels;
+        let width = row.len() / c;
+        let right = weights.len() - 1 - self.left;
+
+        let pad = |padded: &mut Vec<f32>, x: isize| match self.border.index(x, width) {
+            Some(x) => padded.extend_from_slice(&row[x * c..(x + 1) * c]),
+            None => match self.border {
+                BorderMode::Constant(value) => padded.extend_from_slice(value),
+                _ => padded.extend((0..c).map(|_| 0.0)),
+            },
+        };
+        padded.clear();
+        for x in -(self.left as isize)..0 {
+            pad(padded, x);
+        }
+        padded.extend_from_slice(row);
+        for x in width..width + right {
+            pad(padded, x as isize);
+        }
+
+        for (i, &k) in weights.iter().enumerate() {
+            add_scaled(out, &padded[i * c..], k);
+        }
+    }
+}
+
+/// Returns the pixel that a row of the constant border color becomes after
+/// correlating it with the given weights.
+fn constant_row_sum(border: BorderMode<&[f32]>, weights: &[f32]) -> Vec<f32> {
+    match border {
+        BorderMode::Constant(value) => {
+            let sum: f32 = weights.iter().sum();
+            value.iter().map(|v| v * sum).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[inline]
+fn add_scaled(out: &mut [f32], src: &[f32], k: f32) {
+    if k == 0.0 {
+        return;
+    }
+    for (o, s) in out.iter_mut().zip(src) {
+        *o += s * k;
+    }
+}
+
+/// Adds the given pixel times `k` to all pixels of the given row.
+#[inline]
+fn add_constant(out: &mut [f32], pixel: &[f32], k: f32) {
+    if k == 0.0 || pixel.is_empty() {
+        return;
+    }
+    for chunk in out.chunks_exact_mut(pixel.len()) {
+        add_scaled(chunk, pixel, k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec3A};
+    use image_core::{Image, IntoPixels, NDimImage, Size};
+    use test_util::{
+        data::read_portrait,
+        fixtures::{assert_close, pattern},
+        snap::ImageSnapshot,
+    };
+
+    use super::Kernel;
+    use crate::util::BorderMode;
+
+    fn kernel<const W: usize, const H: usize>(rows: [[f32; W]; H]) -> Kernel {
+        let data = rows.iter().flatten().copied().collect();
+        Kernel::new(Image::new(Size::new(W, H), data)).unwrap()
+    }
+
+    #[test]
+    fn separable() {
+        assert!(kernel([[1.0, 2.0, 1.0], [2.0, 4.0, 2.0], [1.0, 2.0, 1.0]]).is_separable());
+        assert!(kernel([[-1.0, 0.0, 1.0], [-2.0, 0.0, 2.0], [-1.0, 0.0, 1.0]]).is_separable());
+        assert!(kernel([[0.5; 5]; 2]).is_separable());
+        assert!(!kernel([[0.0, 1.0, 0.0], [1.0, -4.0, 1.0], [0.0, 1.0, 0.0]]).is_separable());
+        assert!(!kernel([[0.0; 3]; 3]).is_separable());
+
+        assert!(Kernel::new(Image::new(Size::new(0, 3), Vec::new())).is_err());
+        assert!(Kernel::new(Image::from_const(Size::new(3, 3), f32::NAN)).is_err());
+    }
+
+    #[test]
+    fn matches_non_separable() {
+        let img: NDimImage = pattern::<Vec2>(Size::new(11, 7)).into();
+        let value = [0.5, -1.0];
+        let borders = [
+            BorderMode::Clamp,
+            BorderMode::Reflect,
+            BorderMode::Wrap,
+            BorderMode::Constant(&value[..]),
+            BorderMode::Transparent,
+        ];
+        let kernels = [
+            kernel([[-1.0, 0.0, 1.0], [-2.0, 0.0, 2.0], [-1.0, 0.0, 1.0]]),
+            Kernel::from_separable(&[1.0, 3.0, -2.0, 0.5], &[2.0, 1.0, 0.0, 4.0, 1.0]).unwrap(),
+            Kernel::from_separable(&[1.0; 15], &[0.25; 9]).unwrap(),
+        ];
+
+        for kernel in kernels {
+            assert!(kernel.is_separable());
+            let mut general = kernel.clone();
+            general.separated = None;
+
+            for border in borders {
+                let expected = super::convolve(img.view(), &general, border);
+                let actual = super::convolve(img.view(), &kernel, border);
+                assert_close(actual, expected, 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn flips_kernel() {
+        let img = NDimImage::from_fn_c(Size::new(5, 3), |x, y| [(x + y * 5) as f32]);
+        let shift = |k: Kernel| {
+            super::convolve(img.view(), &k, BorderMode::Transparent)
+                .data()
+                .to_vec()
+        };
+
+        // out(x, y) = img(x - 1, y)
+        let expected: Vec<f32> = img
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| if i % 5 == 0 { 0.0 } else { v - 1.0 })
+            .collect();
+        assert_eq!(shift(kernel([[0.0, 0.0, 1.0]])), expected);
+        assert_eq!(shift(kernel([[0.0, 0.0, 0.0], [0.0, 0.0, 1.0]])), expected);
+
+        // out(x, y) = img(x, y + 1)
+        let expected: Vec<f32> = img
+            .data()
+            .iter()
+            .map(|&v| if v >= 10.0 { 0.0 } else { v + 5.0 })
+            .collect();
+        assert_eq!(shift(kernel([[1.0], [0.0], [0.0]])), expected);
+        assert_eq!(
+            shift(kernel([[0.0, 1.0], [0.0, 0.0], [0.0, 0.0]])),
+            expected
+        );
+    }
+
+    #[test]
+    fn matches_blur() {
+        let img: NDimImage = pattern::<Vec2>(Size::new(11, 7)).into();
+        let sigma: f32 = 1.5;
+        let weights: Vec<f32> = (-5..=5)
+            .map(|i: i32| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let sum: f32 = weights.iter().sum();
+        let weights: Vec<f32> = weights.iter().map(|w| w / sum).collect();
+        let kernel = Kernel::from_separable(&weights, &weights).unwrap();
+
+        let result = super::convolve(img.view(), &kernel, BorderMode::Reflect);
+        let pixels: Image<Vec2> = img.view().into_pixels().unwrap();
+        let expected = crate::blur::gaussian_blur(pixels.view(), sigma, sigma, BorderMode::Reflect);
+        assert_close(result, expected, 1e-5);
+    }
+
+    #[test]
+    fn convolve() {
+        let original: NDimImage = read_portrait().into();
+
+        let emboss = kernel([[-2.0, -1.0, 0.0], [-1.0, 1.0, 1.0], [0.0, 1.0, 2.0]]);
+        assert!(!emboss.is_separable());
+        let result = super::convolve(original.view(), &emboss, BorderMode::Reflect);
+        let result: Image<Vec3A> = result.into_pixels().unwrap();
+        result.snapshot("convolve_emboss");
+
+        let sobel = kernel([[1.0, 0.0, -1.0], [2.0, 0.0, -2.0], [1.0, 0.0, -1.0]]);
+        let value = [0.5; 3];
+        let result = super::convolve(original.view(), &sobel, BorderMode::Constant(&value));
+        let result: Image<Vec3A> = result.into_pixels().unwrap();
+        result.map(|p| *p * 0.5 + 0.5).snapshot("convolve_sobel");
+    }
+}
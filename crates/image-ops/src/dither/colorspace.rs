@@ -0,0 +1,400 @@
+use glam::{Vec2, Vec3, Vec3A, Vec4};
+use rstar::Point;
+
+use crate::color::{
+    delta_e_2000, linear_to_lab, linear_to_oklab, redmean_2, srgb_to_linear, srgb_to_linear_rgb,
+};
+
+/// A space in which the distance between colors is measured.
+pub trait ColorSpace<P> {
+    type Coord: Point<Scalar = f32>;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord;
+
+    /// Returns the squared distance between two coordinates.
+    ///
+    /// The default implementation is the squared Euclidean distance.
+    #[inline(always)]
+    fn distance_2(&self, a: &Self::Coord, b: &Self::Coord) -> f32 {
+        (0..Self::Coord::DIMENSIONS)
+            .map(|i| a.nth(i) - b.nth(i))
+            .map(|d| d * d)
+            .sum()
+    }
+
+    /// Whether [`ColorSpace::distance_2`] is the squared Euclidean distance.
+    ///
+    /// Large palettes use a spatial index to find the nearest color, which is
+    /// only possible for Euclidean distances.
+    fn is_euclidean(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RGB;
+impl ColorSpace<f32> for RGB {
+    type Coord = [f32; 1];
+
+    fn get_coordinate(&self, color: f32) -> Self::Coord {
+        [color]
+    }
+}
+impl<const N: usize> ColorSpace<[f32; N]> for RGB {
+    type Coord = [f32; N];
+
+    fn get_coordinate(&self, color: [f32; N]) -> Self::Coord {
+        color
+    }
+}
+
+macro_rules! impl_srgb_into {
+    ($t:ty, $n:literal) => {
+        impl ColorSpace<$t> for RGB {
+            type Coord = [f32; $n];
+
+            fn get_coordinate(&self, color: $t) -> Self::Coord {
+                color.into()
+            }
+        }
+    };
+}
+impl_srgb_into!(Vec2, 2);
+impl_srgb_into!(Vec3, 3);
+impl_srgb_into!(Vec3A, 3);
+impl_srgb_into!(Vec4, 4);
+
+/// A gray or sRGB pixel with an optional alpha channel.
+pub trait ColorAlpha: Copy {
+    type Coord: Point<Scalar = f32>;
+
+    /// Converts the pixel into a coordinate by converting its color with
+    /// either `gray` or `rgb` and multiplying its alpha with `alpha_scale`.
+    fn to_coord(
+        self,
+        gray: impl Fn(f32) -> f32,
+        rgb: impl Fn(Vec3A) -> Vec3A,
+        alpha_scale: f32,
+    ) -> Self::Coord;
+
+    /// Splits a coordinate into its color and (scaled) alpha. The color of
+    /// gray pixels is returned in the first component and the other components
+    /// are 0. Pixels without alpha have an alpha of 0.
+    fn split_coord(coord: &Self::Coord) -> (Vec3A, f32);
+
+    /// Whether the color is a single gray channel.
+    fn is_gray() -> bool;
+
+    /// The relative luminance of the color, ignoring alpha.
+    fn luminance(self) -> f32;
+}
+impl ColorAlpha for f32 {
+    type Coord = [f32; 1];
+
+    #[inline]
+    fn to_coord(self, gray: impl Fn(f32) -> f32, _: impl Fn(Vec3A) -> Vec3A, _: f32) -> [f32; 1] {
+        [gray(self)]
+    }
+    #[inline]
+    fn split_coord(coord: &[f32; 1]) -> (Vec3A, f32) {
+        (Vec3A::new(coord[0], 0.0, 0.0), 0.0)
+    }
+    fn is_gray() -> bool {
+        true
+    }
+    #[inline]
+    fn luminance(self) -> f32 {
+        srgb_to_linear(self)
+    }
+}
+impl ColorAlpha for Vec2 {
+    type Coord = [f32; 2];
+
+    #[inline]
+    fn to_coord(self, gray: impl Fn(f32) -> f32, _: impl Fn(Vec3A) -> Vec3A, a: f32) -> [f32; 2] {
+        [gray(self.x), self.y * a]
+    }
+    #[inline]
+    fn split_coord(coord: &[f32; 2]) -> (Vec3A, f32) {
+        (Vec3A::new(coord[0], 0.0, 0.0), coord[1])
+    }
+    fn is_gray() -> bool {
+        true
+    }
+    #[inline]
+    fn luminance(self) -> f32 {
+        srgb_to_linear(self.x)
+    }
+}
+impl ColorAlpha for Vec3 {
+    type Coord = [f32; 3];
+
+    #[inline]
+    fn to_coord(self, _: impl Fn(f32) -> f32, rgb: impl Fn(Vec3A) -> Vec3A, _: f32) -> [f32; 3] {
+        rgb(self.into()).into()
+    }
+    #[inline]
+    fn split_coord(coord: &[f32; 3]) -> (Vec3A, f32) {
+        (Vec3A::from(*coord), 0.0)
+    }
+    fn is_gray() -> bool {
+        false
+    }
+    #[inline]
+    fn luminance(self) -> f32 {
+        rgb_luminance(self.into())
+    }
+}
+impl ColorAlpha for Vec3A {
+    type Coord = [f32; 3];
+
+    #[inline]
+    fn to_coord(self, _: impl Fn(f32) -> f32, rgb: impl Fn(Vec3A) -> Vec3A, _: f32) -> [f32; 3] {
+        rgb(self).into()
+    }
+    #[inline]
+    fn split_coord(coord: &[f32; 3]) -> (Vec3A, f32) {
+        (Vec3A::from(*coord), 0.0)
+    }
+    fn is_gray() -> bool {
+        false
+    }
+    #[inline]
+    fn luminance(self) -> f32 {
+        rgb_luminance(self)
+    }
+}
+impl ColorAlpha for Vec4 {
+    type Coord = [f32; 4];
+
+    #[inline]
+    fn to_coord(self, _: impl Fn(f32) -> f32, rgb: impl Fn(Vec3A) -> Vec3A, a: f32) -> [f32; 4] {
+        rgb(self.into()).extend(self.w * a).into()
+    }
+    #[inline]
+    fn split_coord(coord: &[f32; 4]) -> (Vec3A, f32) {
+        (Vec3A::new(coord[0], coord[1], coord[2]), coord[3])
+    }
+    fn is_gray() -> bool {
+        false
+    }
+    #[inline]
+    fn luminance(self) -> f32 {
+        rgb_luminance(self.into())
+    }
+}
+
+fn rgb_luminance(rgb: Vec3A) -> f32 {
+    srgb_to_linear_rgb(rgb).dot(Vec3A::new(0.2126, 0.7152, 0.0722))
+}
+
+fn gray_to_oklab(gray: f32) -> f32 {
+    linear_to_oklab(Vec3A::splat(srgb_to_linear(gray))).x
+}
+fn gray_to_lab(gray: f32) -> f32 {
+    linear_to_lab(Vec3A::splat(srgb_to_linear(gray))).x
+}
+fn srgb_to_lab(rgb: Vec3A) -> Vec3A {
+    linear_to_lab(srgb_to_linear_rgb(rgb))
+}
+fn srgb_to_oklab(rgb: Vec3A) -> Vec3A {
+    linear_to_oklab(srgb_to_linear_rgb(rgb))
+}
+
+/// The Euclidean distance in Oklab. Gray colors only use the lightness.
+///
+/// Alpha is used as-is, so a difference of 1 in alpha is as large as the
+/// difference between black and white.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Oklab;
+impl<P: ColorAlpha> ColorSpace<P> for Oklab {
+    type Coord = P::Coord;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord {
+        color.to_coord(gray_to_oklab, srgb_to_oklab, 1.0)
+    }
+}
+
+/// The CIE76 color difference (ΔE*ab), which is the Euclidean distance in
+/// CIE L*a*b*. Gray colors only use the lightness.
+///
+/// Alpha is scaled to 0..=100, so a difference of 1 in alpha is as large as
+/// the difference between black and white.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CieLab;
+impl<P: ColorAlpha> ColorSpace<P> for CieLab {
+    type Coord = P::Coord;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord {
+        color.to_coord(gray_to_lab, srgb_to_lab, 100.0)
+    }
+}
+
+/// The CIEDE2000 color difference (ΔE*00) in CIE L*a*b*.
+///
+/// Alpha is scaled to 0..=100 like [`CieLab`] and added to the color
+/// difference as another Euclidean dimension.
+///
+/// This distance is not Euclidean, so palettes are always searched linearly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CieDe2000;
+impl<P: ColorAlpha> ColorSpace<P> for CieDe2000 {
+    type Coord = P::Coord;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord {
+        color.to_coord(gray_to_lab, srgb_to_lab, 100.0)
+    }
+
+    #[inline]
+    fn distance_2(&self, a: &Self::Coord, b: &Self::Coord) -> f32 {
+        let (a, a_alpha) = P::split_coord(a);
+        let (b, b_alpha) = P::split_coord(b);
+        let d = delta_e_2000(a, b);
+        let d_alpha = a_alpha - b_alpha;
+        d * d + d_alpha * d_alpha
+    }
+
+    fn is_euclidean(&self) -> bool {
+        false
+    }
+}
+
+/// The "redmean" color difference in sRGB, a cheap approximation of a
+/// perceptual color difference. Gray colors use the Euclidean distance.
+///
+/// This distance is not Euclidean, so palettes are always searched linearly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Redmean;
+impl<P: ColorAlpha> ColorSpace<P> for Redmean {
+    type Coord = P::Coord;
+
+    fn get_coordinate(&self, color: P) -> Self::Coord {
+        color.to_coord(|g| g, |rgb| rgb, 1.0)
+    }
+
+    #[inline]
+    fn distance_2(&self, a: &Self::Coord, b: &Self::Coord) -> f32 {
+        let (a, a_alpha) = P::split_coord(a);
+        let (b, b_alpha) = P::split_coord(b);
+        let d = if P::is_gray() {
+            let d = a.x - b.x;
+            d * d
+        } else {
+            redmean_2(a, b)
+        };
+        let d_alpha = a_alpha - b_alpha;
+        d + d_alpha * d_alpha
+    }
+
+    fn is_euclidean(&self) -> bool {
+        P::is_gray()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3A, Vec4};
+    use test_util::{
+        data::{read_flower, read_flower_palette, read_portrait},
+        snap::ImageSnapshot,
+    };
+
+    use super::super::*;
+
+    fn nearest<P: Copy, C: ColorSpace<P>>(space: &C, palette: &[P], color: P) -> P {
+        let coord = space.get_coordinate(color);
+        *palette
+            .iter()
+            .min_by(|a, b| {
+                let a = space.distance_2(&space.get_coordinate(**a), &coord);
+                let b = space.distance_2(&space.get_coordinate(**b), &coord);
+                a.total_cmp(&b)
+            })
+            .unwrap()
+    }
+
+    fn check_lookup<P, C>(space: C, palette: &[P], colors: &[P])
+    where
+        P: Copy + std::ops::Sub<Output = P>,
+        C: ColorSpace<P> + Copy,
+        BoundError: ErrorCombinator<P>,
+    {
+        let lookup = ColorPalette::new(space, palette.iter().copied(), BoundError);
+        let distance =
+            |a: P, b: P| space.distance_2(&space.get_coordinate(a), &space.get_coordinate(b));
+        for &color in colors {
+            // compare distances, because there can be ties
+            let expected = distance(nearest(&space, palette, color), color);
+            let actual = distance(lookup.get_nearest_color(color), color);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn large_palettes() {
+        // enough colors to use a spatial index for Euclidean spaces
+        let palette: Vec<Vec3A> = read_portrait()
+            .data()
+            .iter()
+            .step_by(401)
+            .copied()
+            .collect();
+        assert!(palette.len() > 300);
+        let colors: Vec<Vec3A> = read_flower().data().iter().step_by(997).copied().collect();
+
+        check_lookup(RGB, &palette, &colors);
+        check_lookup(Oklab, &palette, &colors);
+        check_lookup(CieLab, &palette, &colors);
+        check_lookup(CieDe2000, &palette, &colors);
+        check_lookup(Redmean, &palette, &colors);
+
+        let alpha = |c: &Vec3A| c.extend(c.x * c.y);
+        let palette: Vec<Vec4> = palette.iter().map(alpha).collect();
+        let colors: Vec<Vec4> = colors.iter().map(alpha).collect();
+        check_lookup(Oklab, &palette, &colors);
+        check_lookup(CieLab, &palette, &colors);
+        check_lookup(CieDe2000, &palette, &colors);
+        check_lookup(Redmean, &palette, &colors);
+
+        let gray = |c: &Vec4| c.z;
+        let palette: Vec<f32> = palette.iter().map(gray).collect();
+        let colors: Vec<f32> = colors.iter().map(gray).collect();
+        check_lookup(Oklab, &palette, &colors);
+        check_lookup(CieLab, &palette, &colors);
+        check_lookup(CieDe2000, &palette, &colors);
+        check_lookup(Redmean, &palette, &colors);
+    }
+
+    #[test]
+    fn perceptual_nearest() {
+        // sRGB sees the dark blue as closer to black, but it's perceptually
+        // closer to the light blue
+        let palette = [Vec3A::ZERO, Vec3A::new(0.25, 0.25, 1.0)];
+        let color = Vec3A::new(0.0, 0.0, 0.5);
+
+        assert_eq!(nearest(&RGB, &palette, color), palette[0]);
+        assert_eq!(nearest(&Oklab, &palette, color), palette[1]);
+        assert_eq!(nearest(&CieLab, &palette, color), palette[1]);
+        assert_eq!(nearest(&CieDe2000, &palette, color), palette[1]);
+    }
+
+    #[test]
+    fn error_diffusion() {
+        fn dither<C: ColorSpace<Vec3A>>(space: C, name: &str) {
+            let mut img = read_flower();
+            let colors = read_flower_palette().row(0).to_vec();
+            let palette = ColorPalette::new(space, colors, BoundError);
+            error_diffusion_dither(
+                img.view_mut(),
+                FloydSteinberg,
+                &palette,
+                DiffusionOptions::default(),
+            );
+            img.snapshot(name);
+        }
+
+        dither(Oklab, "error_diffusion_palette_fs_oklab");
+        dither(CieDe2000, "error_diffusion_palette_fs_de2000");
+        dither(Redmean, "error_diffusion_palette_fs_redmean");
+    }
+}
@@ -1,78 +1,93 @@
-use image_core::Image;
+use image_core::{Image, ImageView, ImageViewMut};
 
 use crate::util::from_const;
 
 use super::{Diffuser, DiffusionAlgorithm, Pixel, Quantizer};
 
+/// Options for [`error_diffusion_dither`] and [`error_diffusion_dither_map`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffusionOptions {
+    /// If true, the image is treated as a tileable texture: errors that leave the image on one
+    /// side enter it on the opposite side. Since the error that leaves the bottom of the image is
+    /// only known after dithering the whole image, the image is processed twice in this mode.
+    pub wrap: bool,
+    /// If true, every other row is processed from right to left with a mirrored diffusion kernel.
+    /// This serpentine (boustrophedon) scanning breaks up the directional "worm" artifacts of
+    /// always scanning from left to right.
+    pub serpentine: bool,
+    /// The fraction of the quantization error that is diffused to neighboring pixels. 1 diffuses
+    /// the full error and 0 disables error diffusion entirely.
+    pub error_strength: f32,
+    /// If set, the error accumulated at a pixel is clamped to `[-limit, limit]` per channel before
+    /// it is added to the pixel. This prevents large errors, e.g. from colors a palette cannot
+    /// represent, from bleeding far into neighboring regions.
+    ///
+    /// The limit must not be negative.
+    pub error_clamp: Option<f32>,
+}
+
+impl Default for DiffusionOptions {
+    fn default() -> Self {
+        Self {
+            wrap: false,
+            serpentine: false,
+            error_strength: 1.0,
+            error_clamp: None,
+        }
+    }
+}
+
+/// Dithers the given image in place.
+///
+/// See [`DiffusionOptions`] for the available options.
 pub fn error_diffusion_dither<P: Pixel>(
-    src: &mut Image<P>,
+    mut src: ImageViewMut<P>,
     algorithm: impl DiffusionAlgorithm,
     quant: &impl Quantizer<P, P>,
+    options: DiffusionOptions,
 ) {
-    let w = src.width();
-    let h = src.height();
-    let data = src.data_mut();
-
-    let mut error_rows = ErrorRows::<P>::new(w);
-
-    for y in 0..h {
-        error_rows.rotate();
-
-        for x in 0..w {
-            let index = y * w + x;
-            let error_x = x + ERROR_ROW_OFFSET;
-
-            let color = quant.combine_error(data[index], error_rows.0[error_x]);
-            let nearest = quant.get_nearest_color(color);
-            let error = quant.get_error(color, nearest);
+    let mut diffusion = ErrorDiffusion::new(src.width(), options);
+    if options.wrap {
+        diffusion.warm_up(src.view(), &algorithm, quant);
+    }
 
-            data[index] = nearest;
+    for row in src.rows_mut() {
+        diffusion.next_row();
 
-            algorithm.define_weights(StandardDiffuser {
-                rows: [&mut *error_rows.0, &mut *error_rows.1, &mut *error_rows.2],
-                x: error_x,
-                error,
-            });
+        for i in 0..row.len() {
+            let x = diffusion.column(i);
+            row[x] = diffusion.diffuse(x, row[x], &algorithm, quant);
         }
     }
 }
 
+/// Dithers the given image into a new image.
+///
+/// See [`DiffusionOptions`] for the available options.
 pub fn error_diffusion_dither_map<P: Pixel, N>(
-    src: &Image<P>,
+    src: ImageView<P>,
     algorithm: impl DiffusionAlgorithm,
     quant: &impl Quantizer<P, N>,
+    options: DiffusionOptions,
     out: Option<Image<N>>,
 ) -> Image<N>
 where
     N: Clone + Default,
 {
     let mut dest = from_const(src.size(), Default::default(), out);
-    let dest_data = dest.data_mut();
-
-    let w = src.width();
-    let h = src.height();
-    let data = src.data();
+    let mut dest_view = dest.view_mut();
 
-    let mut error_rows = ErrorRows::<P>::new(w);
-
-    for y in 0..h {
-        error_rows.rotate();
-
-        for x in 0..w {
-            let index = y * w + x;
-            let error_x = x + ERROR_ROW_OFFSET;
-
-            let color = quant.combine_error(data[index], error_rows.0[error_x]);
-            let nearest = quant.get_nearest_color(color);
-            let error = quant.get_error(color, nearest.clone());
+    let mut diffusion = ErrorDiffusion::new(src.width(), options);
+    if options.wrap {
+        diffusion.warm_up(src, &algorithm, quant);
+    }
 
-            dest_data[index] = nearest;
+    for (src_row, dest_row) in src.rows().zip(dest_view.rows_mut()) {
+        diffusion.next_row();
 
-            algorithm.define_weights(StandardDiffuser {
-                rows: [&mut *error_rows.0, &mut *error_rows.1, &mut *error_rows.2],
-                x: error_x,
-                error,
-            });
+        for i in 0..src_row.len() {
+            let x = diffusion.column(i);
+            dest_row[x] = diffusion.diffuse(x, src_row[x], &algorithm, quant);
         }
     }
 
@@ -99,9 +114,114 @@ impl<P: Clone + Default> ErrorRows<P> {
     }
 }
 
+struct ErrorDiffusion<P> {
+    rows: ErrorRows<P>,
+    width: usize,
+    options: DiffusionOptions,
+    /// Whether the current row is processed from right to left.
+    reverse: bool,
+}
+
+impl<P: Pixel> ErrorDiffusion<P> {
+    fn new(width: usize, options: DiffusionOptions) -> Self {
+        if let Some(limit) = options.error_clamp {
+            assert!(limit >= 0.0, "error clamp limit must not be negative");
+        }
+
+        Self {
+            rows: ErrorRows::new(width),
+            width,
+            options,
+            // the first call to `next_row` flips this
+            reverse: true,
+        }
+    }
+
+    /// Moves on to the next row. This has to be called before the first row.
+    fn next_row(&mut self) {
+        self.rows.rotate();
+        self.reverse = self.options.serpentine && !self.reverse;
+    }
+
+    /// Returns the x coordinate of the `i`-th pixel processed in the current row.
+    #[inline(always)]
+    fn column(&self, i: usize) -> usize {
+        if self.reverse {
+            self.width - 1 - i
+        } else {
+            i
+        }
+    }
+
+    /// Quantizes the pixel at the given x coordinate of the current row and diffuses its error.
+    #[inline(always)]
+    fn diffuse<N: Clone>(
+        &mut self,
+        x: usize,
+        pixel: P,
+        algorithm: &impl DiffusionAlgorithm,
+        quant: &impl Quantizer<P, N>,
+    ) -> N {
+        let error_x = x + ERROR_ROW_OFFSET;
+
+        let mut accumulated = self.rows.0[error_x];
+        if let Some(limit) = self.options.error_clamp {
+            accumulated = accumulated.clamp_components(limit);
+        }
+
+        let color = quant.combine_error(pixel, accumulated);
+        let nearest = quant.get_nearest_color(color);
+        let error = quant.get_error(color, nearest.clone()) * self.options.error_strength;
+
+        let rows = [&mut *self.rows.0, &mut *self.rows.1, &mut *self.rows.2];
+        let direction = if self.reverse { -1 } else { 1 };
+        if self.options.wrap {
+            algorithm.define_weights(WrappingDiffuser {
+                rows,
+                x,
+                width: self.width,
+                direction,
+                error,
+            });
+        } else {
+            algorithm.define_weights(StandardDiffuser {
+                rows,
+                x: error_x,
+                direction,
+                error,
+            });
+        }
+
+        nearest
+    }
+
+    /// Dithers the whole image without writing the result to determine the error that leaves the
+    /// image at the bottom. This error is then carried over to the top rows.
+    fn warm_up<N: Clone>(
+        &mut self,
+        src: ImageView<P>,
+        algorithm: &impl DiffusionAlgorithm,
+        quant: &impl Quantizer<P, N>,
+    ) {
+        for row in src.rows() {
+            self.next_row();
+            for i in 0..row.len() {
+                let x = self.column(i);
+                self.diffuse(x, row[x], algorithm, quant);
+            }
+        }
+        // the next call to `next_row` moves the overflow into the first 2 rows
+    }
+}
+
+/// A diffuser that drops errors that leave the image.
+///
+/// If `direction` is -1, the kernel is mirrored horizontally for rows processed from right to
+/// left.
 struct StandardDiffuser<'a, P: Pixel> {
     rows: [&'a mut [P]; 3],
     x: usize,
+    direction: isize,
     error: P,
 }
 impl<'a, P: Pixel> Diffuser for StandardDiffuser<'a, P> {
@@ -110,11 +230,37 @@ impl<'a, P: Pixel> Diffuser for StandardDiffuser<'a, P> {
         assert!(y < 3);
         assert!(-(ERROR_ROW_OFFSET as isize) <= x && x <= ERROR_ROW_OFFSET as isize);
 
-        let x = (self.x as isize + x) as usize;
+        let x = (self.x as isize + x * self.direction) as usize;
         self.rows[y][x] += self.error * weight;
     }
 }
 
+/// A diffuser that wraps errors around the left and right edges of the image.
+///
+/// Pixels ahead of the current pixel that are beyond the edge of the image have already been
+/// processed, so their error is given to the pixel below instead.
+struct WrappingDiffuser<'a, P: Pixel> {
+    rows: [&'a mut [P]; 3],
+    x: usize,
+    width: usize,
+    direction: isize,
+    error: P,
+}
+impl<'a, P: Pixel> Diffuser for WrappingDiffuser<'a, P> {
+    #[inline(always)]
+    fn assign_weight(&mut self, y: usize, x: isize, weight: f32) {
+        assert!(y < 3);
+        assert!(-(ERROR_ROW_OFFSET as isize) <= x && x <= ERROR_ROW_OFFSET as isize);
+
+        let mut y = y;
+        let x = (self.x as isize + x * self.direction).rem_euclid(self.width as isize) as usize;
+        if y == 0 && (x as isize - self.x as isize) * self.direction < 0 {
+            y = 1;
+        }
+        self.rows[y][x + ERROR_ROW_OFFSET] += self.error * weight;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{super::*, *};
@@ -126,7 +272,12 @@ mod tests {
     #[test]
     fn error_diffusion() {
         let mut original = read_flower();
-        error_diffusion_dither(&mut original, FloydSteinberg, &ChannelQuantization::new(4));
+        error_diffusion_dither(
+            original.view_mut(),
+            FloydSteinberg,
+            &ChannelQuantization::new(4),
+            DiffusionOptions::default(),
+        );
         original.snapshot("error_diffusion_fs_4");
     }
     #[test]
@@ -134,38 +285,48 @@ mod tests {
         let original = read_flower();
 
         error_diffusion_dither_map(
-            &original,
+            original.view(),
             FloydSteinberg,
             &ChannelQuantization::new(2),
+            DiffusionOptions::default(),
             None,
         )
         .snapshot("error_diffusion_map_fs_2");
 
         error_diffusion_dither_map(
-            &original,
+            original.view(),
             FloydSteinberg,
             &ChannelQuantization::new(4),
+            DiffusionOptions::default(),
             None,
         )
         .snapshot("error_diffusion_map_fs_4");
 
         error_diffusion_dither_map(
-            &original,
+            original.view(),
             JarvisJudiceNinke,
             &ChannelQuantization::new(4),
+            DiffusionOptions::default(),
             None,
         )
         .snapshot("error_diffusion_map_jjn_4");
 
         error_diffusion_dither_map(
-            &original,
+            original.view(),
             FloydSteinberg,
             &ChannelQuantization::new(16),
+            DiffusionOptions::default(),
             None,
         )
         .snapshot("error_diffusion_map_flower_fs_16");
-        error_diffusion_dither_map(&original, Atkinson, &ChannelQuantization::new(16), None)
-            .snapshot("error_diffusion_map_atk_16");
+        error_diffusion_dither_map(
+            original.view(),
+            Atkinson,
+            &ChannelQuantization::new(16),
+            DiffusionOptions::default(),
+            None,
+        )
+        .snapshot("error_diffusion_map_atk_16");
     }
 
     #[test]
@@ -175,7 +336,155 @@ mod tests {
 
         let palette = ColorPalette::new(RGB, palette_img.row(0).iter().copied(), BoundError);
 
-        error_diffusion_dither_map(&img, FloydSteinberg, &palette, None)
-            .snapshot("error_diffusion_palette_fs");
+        error_diffusion_dither_map(
+            img.view(),
+            FloydSteinberg,
+            &palette,
+            DiffusionOptions::default(),
+            None,
+        )
+        .snapshot("error_diffusion_palette_fs");
+    }
+
+    #[test]
+    fn error_diffusion_wrap() {
+        let original = read_flower();
+        let tile = original.crop(200, 300, 128, 96);
+        let wrap = DiffusionOptions {
+            wrap: true,
+            ..Default::default()
+        };
+
+        error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &ChannelQuantization::new(2),
+            wrap,
+            None,
+        )
+        .snapshot("error_diffusion_map_fs_2_wrap");
+
+        let mut in_place = tile.clone();
+        error_diffusion_dither(
+            in_place.view_mut(),
+            JarvisJudiceNinke,
+            &ChannelQuantization::new(2),
+            wrap,
+        );
+        let mapped = error_diffusion_dither_map(
+            tile.view(),
+            JarvisJudiceNinke,
+            &ChannelQuantization::new(2),
+            wrap,
+            None,
+        );
+        assert_eq!(in_place.data(), mapped.data());
+    }
+
+    #[test]
+    fn error_diffusion_serpentine() {
+        let original = read_flower();
+        let tile = original.crop(200, 300, 128, 96);
+        let serpentine = DiffusionOptions {
+            serpentine: true,
+            ..Default::default()
+        };
+
+        error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &ChannelQuantization::new(2),
+            serpentine,
+            None,
+        )
+        .snapshot("error_diffusion_map_fs_2_serpentine");
+
+        // in-place and mapped dithering scan the same way
+        for options in [
+            serpentine,
+            DiffusionOptions {
+                wrap: true,
+                serpentine: true,
+                ..Default::default()
+            },
+        ] {
+            let mut in_place = tile.clone();
+            error_diffusion_dither(
+                in_place.view_mut(),
+                JarvisJudiceNinke,
+                &ChannelQuantization::new(2),
+                options,
+            );
+            let mapped = error_diffusion_dither_map(
+                tile.view(),
+                JarvisJudiceNinke,
+                &ChannelQuantization::new(2),
+                options,
+                None,
+            );
+            assert_eq!(in_place.data(), mapped.data());
+        }
+    }
+
+    #[test]
+    fn error_diffusion_strength() {
+        let original = read_flower();
+        let tile = original.crop(200, 300, 128, 96);
+        let quant = ChannelQuantization::new(2);
+
+        // without error diffusion, every pixel is simply quantized
+        let no_error = error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &quant,
+            DiffusionOptions {
+                error_strength: 0.0,
+                ..Default::default()
+            },
+            None,
+        );
+        let quantized: Vec<_> = tile
+            .data()
+            .iter()
+            .map(|p| quant.get_nearest_color(*p))
+            .collect();
+        assert_eq!(no_error.data(), quantized.as_slice());
+
+        // a clamp of 0 also disables error diffusion
+        let no_error = error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &quant,
+            DiffusionOptions {
+                error_clamp: Some(0.0),
+                ..Default::default()
+            },
+            None,
+        );
+        assert_eq!(no_error.data(), quantized.as_slice());
+
+        error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &quant,
+            DiffusionOptions {
+                error_strength: 0.5,
+                ..Default::default()
+            },
+            None,
+        )
+        .snapshot("error_diffusion_map_fs_2_strength_0.5");
+
+        error_diffusion_dither_map(
+            tile.view(),
+            FloydSteinberg,
+            &quant,
+            DiffusionOptions {
+                error_clamp: Some(0.1),
+                ..Default::default()
+            },
+            None,
+        )
+        .snapshot("error_diffusion_map_fs_2_clamp_0.1");
     }
 }
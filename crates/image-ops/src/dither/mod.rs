@@ -1,13 +1,17 @@
 mod algorithm;
+mod colorspace;
 mod diffusion;
 mod ordered;
 mod quant;
 mod riemersma;
+mod threshold;
 mod util;
 
 pub use algorithm::*;
+pub use colorspace::*;
 pub use diffusion::*;
-pub use ordered::ordered_dither;
+pub use ordered::{ordered_dither, ordered_dither_palette, ordered_dither_with_map};
 pub use quant::*;
 pub use riemersma::*;
+pub use threshold::*;
 pub use util::Pixel;
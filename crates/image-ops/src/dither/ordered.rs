@@ -1,40 +1,6 @@
-use image_core::{Image, NDimImage, Size};
+use image_core::{Image, ImageViewMut, NDimViewMut, Size};
 
-use super::ChannelQuantization;
-
-/// Creates a threshold map for ordered dithering.
-///
-/// `n` must be a power of 2.
-///
-/// Uses the algorithm presented here: https://bisqwit.iki.fi/story/howto/dither/jy/
-fn create_threshold_map(n: usize) -> Image<f32> {
-    assert!(n.is_power_of_two());
-    let m = n.trailing_zeros();
-
-    let mut result = Image::from_const(Size::new(n, n), 0.0);
-    let map = result.data_mut();
-
-    let area = (n * n) as f32;
-    for i in 0..n {
-        for j in 0..n {
-            let mut v = 0;
-            let xc = i ^ j;
-            let yc = i;
-
-            let mut bit = 0;
-            for mask in (0..m).rev() {
-                v |= ((yc >> mask) & 1) << (bit);
-                bit += 1;
-                v |= ((xc >> mask) & 1) << (bit);
-                bit += 1;
-            }
-
-            map[i * n + j] = v as f32 / area;
-        }
-    }
-
-    result
-}
+use super::{bayer_threshold_map, ChannelQuantization, ColorAlpha, Pixel, Quantizer};
 
 /// Stretch the image horizontally by a factor of `factor`. Each pixel is repeated `factor` times.
 fn stretch_x<P: Copy + Default>(img: &Image<P>, factor: usize) -> Image<P> {
@@ -86,11 +52,25 @@ fn tile_x<P: Copy + Default>(img: &Image<P>, new_width: usize) -> Image<P> {
     result
 }
 
-pub fn ordered_dither(img: &mut NDimImage, n: usize, quant: ChannelQuantization) {
+/// Ordered dithering with an `n`x`n` Bayer matrix.
+///
+/// `n` must be a power of 2.
+pub fn ordered_dither(img: NDimViewMut, n: usize, quant: ChannelQuantization) {
     assert!(n.is_power_of_two());
 
+    ordered_dither_with_map(img, &bayer_threshold_map(n), quant);
+}
+
+/// Ordered dithering with the given threshold map. The map is tiled across
+/// the image.
+///
+/// All values of the map must be in the range `[0, 1)`. See the
+/// `*_threshold_map` functions for how to create threshold maps.
+pub fn ordered_dither_with_map(mut img: NDimViewMut, map: &Image<f32>, quant: ChannelQuantization) {
+    assert!(!map.is_empty());
+
     if quant.per_channel() == 2 {
-        return binary_ordered_dither(img, n, 0.5);
+        return binary_ordered_dither(img, map, 0.5);
     }
 
     let f = (quant.per_channel() - 1) as f32;
@@ -99,16 +79,15 @@ pub fn ordered_dither(img: &mut NDimImage, n: usize, quant: ChannelQuantization)
     // This allows us to zip the current threshold row with the current image row, which
     // gets rid of the inner channel loop, which makes the code around 25% faster.
     let threshold_map = tile_x(
-        &stretch_x(&create_threshold_map(n), img.channels()),
+        &stretch_x(map, img.channels()),
         img.width() * img.channels(),
     );
-    let n_mask = n - 1;
 
     let shape = img.shape();
     let data = img.data_mut();
 
     for y in 0..shape.height {
-        let threshold_row = threshold_map.row(y & n_mask);
+        let threshold_row = threshold_map.row(y % map.height());
         let data_row =
             &mut data[(y * shape.width * shape.channels)..((y + 1) * shape.width * shape.channels)];
         assert_eq!(threshold_row.len(), data_row.len());
@@ -119,26 +98,20 @@ pub fn ordered_dither(img: &mut NDimImage, n: usize, quant: ChannelQuantization)
     }
 }
 
-fn binary_ordered_dither(img: &mut NDimImage, n: usize, bin_threshold: f32) {
-    assert!(n.is_power_of_two());
-
+fn binary_ordered_dither(mut img: NDimViewMut, map: &Image<f32>, bin_threshold: f32) {
     // Same idea as in the regular ordered dither, but we get even more out of it.
     // The inner channel loop prevented effective vectorization.
     // Binary ordered dithering is about 5x faster with this trick.
     let threshold_map = tile_x(
-        &stretch_x(
-            &create_threshold_map(n).map(|f| bin_threshold + 0.5 - f),
-            img.channels(),
-        ),
+        &stretch_x(&map.map(|f| bin_threshold + 0.5 - f), img.channels()),
         img.width() * img.channels(),
     );
-    let n_mask = n - 1;
 
     let shape = img.shape();
     let data = img.data_mut();
 
     for y in 0..shape.height {
-        let threshold_row = threshold_map.row(y & n_mask);
+        let threshold_row = threshold_map.row(y % map.height());
         let data_row =
             &mut data[(y * shape.width * shape.channels)..((y + 1) * shape.width * shape.channels)];
         assert_eq!(threshold_row.len(), data_row.len());
@@ -152,27 +125,128 @@ fn binary_ordered_dither(img: &mut NDimImage, n: usize, bin_threshold: f32) {
     }
 }
 
+/// Ordered dithering for arbitrary palettes with Thomas Knoll's pattern
+/// dithering algorithm, as described by Joel Yliluoma:
+/// https://bisqwit.iki.fi/story/howto/dither/jy/
+///
+/// For each pixel, a mix of `candidates` palette colors is chosen such that
+/// their average approximates the color of the pixel. The candidates are
+/// sorted by luminance and the threshold map selects one of them. The number
+/// of candidates should be about the number of cells in the threshold map,
+/// but more than 64 rarely improve the result.
+///
+/// All values of the map must be in the range `[0, 1)`.
+pub fn ordered_dither_palette<P, Q>(
+    mut img: ImageViewMut<P>,
+    map: &Image<f32>,
+    quant: &Q,
+    candidates: usize,
+) where
+    P: Pixel + ColorAlpha,
+    Q: Quantizer<P, P>,
+{
+    assert!(!map.is_empty());
+    assert!(candidates > 0);
+
+    let mut mix: Vec<P> = Vec::with_capacity(candidates);
+    for (y, row) in img.rows_mut().enumerate() {
+        let threshold_row = map.row(y % map.height());
+        for (x, p) in row.iter_mut().enumerate() {
+            let color = *p;
+
+            // each candidate compensates for the error of the previous ones
+            mix.clear();
+            let mut error = P::default();
+            for _ in 0..candidates {
+                let attempt = quant.combine_error(color, error);
+                let nearest = quant.get_nearest_color(attempt);
+                error += quant.get_error(color, nearest);
+                mix.push(nearest);
+            }
+            mix.sort_by(|a, b| a.luminance().total_cmp(&b.luminance()));
+
+            let threshold = threshold_row[x % map.width()];
+            let index = (threshold * candidates as f32) as usize;
+            *p = mix[index.min(candidates - 1)];
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use test_util::{data::read_flower, snap::ImageSnapshot};
+    use crate::dither::{
+        blue_noise_threshold_map, clustered_dot_threshold_map, BoundError, ColorPalette, Oklab, RGB,
+    };
+    use image_core::NDimImage;
+    use test_util::{
+        data::{read_flower, read_flower_palette},
+        snap::ImageSnapshot,
+    };
 
     #[test]
     fn ordered_dither_channels() {
-        let mut img = read_flower().into();
-        ordered_dither(&mut img, 4, ChannelQuantization::new(4));
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither(img.view_mut(), 4, ChannelQuantization::new(4));
         img.snapshot("ordered_4_4x4");
 
-        let mut img = read_flower().into();
-        ordered_dither(&mut img, 16, ChannelQuantization::new(4));
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither(img.view_mut(), 16, ChannelQuantization::new(4));
         img.snapshot("ordered_4_16x16");
 
-        let mut img = read_flower().into();
-        ordered_dither(&mut img, 64, ChannelQuantization::new(4));
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither(img.view_mut(), 64, ChannelQuantization::new(4));
         img.snapshot("ordered_4_64x64");
 
-        let mut img = read_flower().into();
-        ordered_dither(&mut img, 4, ChannelQuantization::new(2));
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither(img.view_mut(), 4, ChannelQuantization::new(2));
         img.snapshot("ordered_2_4x4");
     }
+
+    #[test]
+    fn ordered_dither_maps() {
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither_with_map(
+            img.view_mut(),
+            &bayer_threshold_map(6),
+            ChannelQuantization::new(4),
+        );
+        img.snapshot("ordered_4_bayer_6x6");
+
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither_with_map(
+            img.view_mut(),
+            &clustered_dot_threshold_map(8),
+            ChannelQuantization::new(4),
+        );
+        img.snapshot("ordered_4_clustered_8x8");
+
+        let mut img: NDimImage = read_flower().into();
+        ordered_dither_with_map(
+            img.view_mut(),
+            &blue_noise_threshold_map(32),
+            ChannelQuantization::new(2),
+        );
+        img.snapshot("ordered_2_blue_noise_32x32");
+    }
+
+    #[test]
+    fn ordered_dither_color_palette() {
+        let palette_img = read_flower_palette();
+        let colors = palette_img.row(0);
+
+        let original = read_flower().crop(300, 300, 256, 256);
+
+        let mut img = original.clone();
+        let palette = ColorPalette::new(RGB, colors.iter().copied(), BoundError);
+        ordered_dither_palette(img.view_mut(), &bayer_threshold_map(8), &palette, 64);
+        assert!(img.data().iter().all(|p| colors.contains(p)));
+        img.snapshot("ordered_palette_bayer_8x8");
+
+        let mut img = original;
+        let palette = ColorPalette::new(Oklab, colors.iter().copied(), BoundError);
+        ordered_dither_palette(img.view_mut(), &blue_noise_threshold_map(16), &palette, 32);
+        assert!(img.data().iter().all(|p| colors.contains(p)));
+        img.snapshot("ordered_palette_blue_noise_16x16_oklab");
+    }
 }
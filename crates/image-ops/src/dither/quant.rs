@@ -1,8 +1,8 @@
 use glam::{Vec2, Vec3, Vec3A, Vec4};
-use image_core::{Image, NDimImage};
+use image_core::{ImageViewMut, NDimViewMut};
 use rstar::{primitives::GeomWithData, Point, RTree};
 
-use super::Pixel;
+use super::{ColorSpace, Pixel};
 
 pub trait ErrorCombinator<P> {
     fn combine_error(&self, color: P, error: P) -> P;
@@ -110,74 +110,29 @@ impl_channels_vec!(Vec3);
 impl_channels_vec!(Vec3A);
 impl_channels_vec!(Vec4);
 
-pub trait ColorSpace<P> {
-    type Coord: Point<Scalar = f32>;
-
-    fn get_coordinate(&self, color: P) -> Self::Coord;
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct RGB;
-impl ColorSpace<f32> for RGB {
-    type Coord = [f32; 1];
-
-    fn get_coordinate(&self, color: f32) -> Self::Coord {
-        [color]
-    }
-}
-impl<const N: usize> ColorSpace<[f32; N]> for RGB {
-    type Coord = [f32; N];
-
-    fn get_coordinate(&self, color: [f32; N]) -> Self::Coord {
-        color
-    }
-}
-
-macro_rules! impl_srgb_into {
-    ($t:ty, $n:literal) => {
-        impl ColorSpace<$t> for RGB {
-            type Coord = [f32; $n];
-
-            fn get_coordinate(&self, color: $t) -> Self::Coord {
-                color.into()
-            }
-        }
-    };
-}
-impl_srgb_into!(Vec2, 2);
-impl_srgb_into!(Vec3, 3);
-impl_srgb_into!(Vec3A, 3);
-impl_srgb_into!(Vec4, 4);
-
 #[derive(Debug, Clone)]
 enum Lookup<G: Point<Scalar = f32>, P> {
     Linear(Vec<GeomWithData<G, P>>),
     Tree(RTree<GeomWithData<G, P>>),
 }
 impl<G: Point<Scalar = f32>, P: Clone> Lookup<G, P> {
-    pub fn new(colors: Vec<GeomWithData<G, P>>) -> Self {
-        if colors.len() < 300 {
-            // linear lookup is really fast for small palettes
+    pub fn new(colors: Vec<GeomWithData<G, P>>, euclidean: bool) -> Self {
+        // linear lookup is really fast for small palettes, and R-trees only
+        // support Euclidean distances with at least 2 dimensions
+        if colors.len() < 300 || !euclidean || G::DIMENSIONS < 2 {
             Self::Linear(colors)
         } else {
             Self::Tree(RTree::bulk_load(colors))
         }
     }
 
-    fn distance_2(a: &G, b: &G) -> f32 {
-        (0..G::DIMENSIONS)
-            .map(|i| a.nth(i) - b.nth(i))
-            .map(|d| d * d)
-            .sum()
-    }
-
-    pub fn get_nearest_color(&self, color: G) -> P {
+    pub fn get_nearest_color(&self, color: G, distance_2: impl Fn(&G, &G) -> f32) -> P {
         match self {
             Self::Linear(colors) => {
                 let mut nearest = &colors[0];
-                let mut nearest_dist = Self::distance_2(nearest.geom(), &color);
+                let mut nearest_dist = distance_2(nearest.geom(), &color);
                 for c in colors.iter().skip(1) {
-                    let dist = Self::distance_2(c.geom(), &color);
+                    let dist = distance_2(c.geom(), &color);
                     if dist < nearest_dist {
                         nearest = c;
                         nearest_dist = dist;
@@ -214,7 +169,7 @@ impl<P: Copy, C: ColorSpace<P>, E: ErrorCombinator<P>> ColorPalette<P, C, E> {
             !colors.is_empty(),
             "palette must contain at least one color"
         );
-        let lookup = Lookup::new(colors);
+        let lookup = Lookup::new(colors, colorspace.is_euclidean());
 
         Self {
             colorspace,
@@ -232,7 +187,8 @@ where
 
     fn get_nearest_color(&self, color: P) -> Self::Nearest {
         let coord = self.colorspace.get_coordinate(color);
-        self.lookup.get_nearest_color(coord)
+        self.lookup
+            .get_nearest_color(coord, |a, b| self.colorspace.distance_2(a, b))
     }
 
     #[inline(always)]
@@ -250,12 +206,14 @@ impl<P: Clone, C: ColorSpace<P>, E: ErrorCombinator<P>> ErrorCombinator<P>
     }
 }
 
-pub fn quantize<P: Clone>(img: &mut Image<P>, quant: &impl ColorLookup<P, Nearest = P>) {
-    for p in img.data_mut() {
-        *p = quant.get_nearest_color(p.clone());
+pub fn quantize<P: Clone>(mut img: ImageViewMut<P>, quant: &impl ColorLookup<P, Nearest = P>) {
+    for row in img.rows_mut() {
+        for p in row {
+            *p = quant.get_nearest_color(p.clone());
+        }
     }
 }
-pub fn quantize_ndim(img: &mut NDimImage, quant: ChannelQuantization) {
+pub fn quantize_ndim(mut img: NDimViewMut, quant: ChannelQuantization) {
     if quant.per_channel() == 2 {
         for p in img.data_mut() {
             *p = if *p >= 0.5 { 1.0 } else { 0.0 };
@@ -274,18 +232,37 @@ mod tests {
     use crate::dither::ChannelQuantization;
 
     use super::*;
+    use image_core::NDimImage;
     use test_util::{data::read_flower, snap::ImageSnapshot};
 
     #[test]
     fn quantize_image() {
         let mut img = read_flower();
-        quantize(&mut img, &ChannelQuantization::new(4));
+        quantize(img.view_mut(), &ChannelQuantization::new(4));
         img.snapshot("quantize_4");
     }
     #[test]
+    fn quantize_region() {
+        let original = read_flower();
+        let (x, y, w, h) = (10, 20, 30, 40);
+
+        let mut img = original.clone();
+        quantize(
+            img.view_region_mut(x, y, w, h),
+            &ChannelQuantization::new(4),
+        );
+
+        let mut expected = original.crop(x, y, w, h);
+        quantize(expected.view_mut(), &ChannelQuantization::new(4));
+
+        assert_eq!(img.crop(x, y, w, h).data(), expected.data());
+        assert_eq!(img.row(y + h), original.row(y + h));
+        assert_eq!(img.row(y)[..x], original.row(y)[..x]);
+    }
+    #[test]
     fn quantize_ndim_image() {
         let mut img: NDimImage = read_flower().into();
-        quantize_ndim(&mut img, ChannelQuantization::new(4));
+        quantize_ndim(img.view_mut(), ChannelQuantization::new(4));
         img.snapshot("quantize_ndim_4");
     }
 }
@@ -1,18 +1,19 @@
-use image_core::Image;
+use image_core::{Image, ImageView, ImageViewMut};
 
 use crate::util::from_const;
 
 use super::{Pixel, Quantizer};
 
 pub fn riemersma_dither<P: Pixel>(
-    src: &mut Image<P>,
+    mut src: ImageViewMut<P>,
     history_length: usize,
     decay_ratio: f32,
     quant: &impl Quantizer<P, P>,
 ) {
     let w = src.width();
     let h = src.height();
-    let data = src.data_mut();
+    let stride = src.stride();
+    let data = src.raw_data_mut();
 
     let base = f32::exp(decay_ratio.ln() / (history_length as f32 - 1.0));
     assert!(0.0 < base && base < 1.0);
@@ -32,7 +33,7 @@ pub fn riemersma_dither<P: Pixel>(
             *error = *error * base;
         }
 
-        let index = y * w + x;
+        let index = y * stride + x;
 
         let original = data[index];
         let color = quant.combine_error(original, current_error);
@@ -47,7 +48,7 @@ pub fn riemersma_dither<P: Pixel>(
 }
 
 pub fn riemersma_dither_map<P: Pixel, N>(
-    src: &Image<P>,
+    src: ImageView<P>,
     history_length: usize,
     decay_ratio: f32,
     quant: &impl Quantizer<P, N>,
@@ -61,7 +62,8 @@ where
 
     let w = src.width();
     let h = src.height();
-    let data = src.data();
+    let stride = src.stride();
+    let data = src.raw_data();
 
     let base = 1.0 / f32::exp((1.0 / decay_ratio).ln() / (history_length as f32 - 1.0));
     assert!(0.0 < base && base < 1.0);
@@ -81,14 +83,12 @@ where
             *error = *error * base;
         }
 
-        let index = y * w + x;
-
-        let original = data[index];
+        let original = data[y * stride + x];
         let color = quant.combine_error(original, current_error);
         let nearest = quant.get_nearest_color(color);
         let error = quant.get_error(original, nearest.clone());
 
-        dest_data[index] = nearest;
+        dest_data[y * w + x] = nearest;
 
         history[history_index] = error;
         history_index = (history_index + 1) % history_length;
@@ -108,7 +108,12 @@ mod tests {
     #[test]
     fn riemersma() {
         let mut original = read_flower();
-        riemersma_dither(&mut original, 16, 1.0 / 16.0, &ChannelQuantization::new(4));
+        riemersma_dither(
+            original.view_mut(),
+            16,
+            1.0 / 16.0,
+            &ChannelQuantization::new(4),
+        );
         original.snapshot("riemersma_flower_4");
     }
     #[test]
@@ -116,7 +121,7 @@ mod tests {
         let original = read_flower();
 
         riemersma_dither_map(
-            &original,
+            original.view(),
             16,
             1.0 / 16.0,
             &ChannelQuantization::new(4),
@@ -131,6 +136,7 @@ mod tests {
 
         let palette = ColorPalette::new(RGB, palette_img.row(0).iter().copied(), BoundError);
 
-        riemersma_dither_map(&img, 16, 1.0 / 16.0, &palette, None).snapshot("riemersma_palette");
+        riemersma_dither_map(img.view(), 16, 1.0 / 16.0, &palette, None)
+            .snapshot("riemersma_palette");
     }
 }
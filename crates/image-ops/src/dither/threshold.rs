@@ -0,0 +1,343 @@
+use image_core::{Image, Size};
+
+/// Creates an `n`x`n` Bayer matrix for ordered dithering.
+///
+/// `n` must be a product of 2s and 3s (e.g. 2, 3, 4, 6, 8, 9, 12, 16). Larger
+/// matrices are built recursively from the 2x2 and 3x3 Bayer matrices. For
+/// powers of 2, this is the classic Bayer matrix.
+///
+/// All values are in the range `[0, 1)`.
+pub fn bayer_threshold_map(n: usize) -> Image<f32> {
+    assert!(is_bayer_size(n), "n must be a product of 2s and 3s");
+
+    const BAYER_2: [usize; 4] = [0, 2, 3, 1];
+    const BAYER_3: [usize; 9] = [0, 7, 3, 6, 5, 2, 4, 1, 8];
+
+    let mut map = vec![0_usize];
+    let mut size = 1;
+    let mut rest = n;
+    while rest > 1 {
+        let (base, k): (&[usize], usize) = if rest % 2 == 0 {
+            (&BAYER_2, 2)
+        } else {
+            (&BAYER_3, 3)
+        };
+        rest /= k;
+
+        // Each cell of the base matrix is subdivided into a copy of the
+        // current matrix, such that consecutive thresholds are far apart.
+        let new_size = size * k;
+        let mut new_map = vec![0; new_size * new_size];
+        for y in 0..new_size {
+            for x in 0..new_size {
+                let outer = map[(y % size) * size + x % size];
+                let inner = base[(y / size) * k + x / size];
+                new_map[y * new_size + x] = outer * k * k + inner;
+            }
+        }
+        map = new_map;
+        size = new_size;
+    }
+
+    let area = (n * n) as f32;
+    Image::new(
+        Size::new(n, n),
+        map.into_iter().map(|v| v as f32 / area).collect(),
+    )
+}
+
+/// Returns whether [`bayer_threshold_map`] supports the given size.
+pub fn is_bayer_size(mut n: usize) -> bool {
+    if n == 0 {
+        return false;
+    }
+    while n % 2 == 0 {
+        n /= 2;
+    }
+    while n % 3 == 0 {
+        n /= 3;
+    }
+    n == 1
+}
+
+/// Creates an `n`x`n` clustered-dot threshold map for ordered dithering.
+///
+/// Unlike Bayer matrices, which disperse the dots of each level, this grows a
+/// round dot from the center of the cell. This mimics the halftone screens
+/// used in printing.
+///
+/// All values are in the range `[0, 1)`.
+pub fn clustered_dot_threshold_map(n: usize) -> Image<f32> {
+    assert!(n > 0);
+
+    let center = (n as f32 - 1.0) / 2.0;
+    let cells = Image::from_fn(Size::new(n, n), |x, y| {
+        let dx = x as f32 - center;
+        let dy = y as f32 - center;
+        // ties are broken by the angle, so the dot grows in a spiral
+        (dx * dx + dy * dy, dy.atan2(dx))
+    });
+
+    rank(cells.data(), |a, b| {
+        a.0.total_cmp(&b.0).then_with(|| a.1.total_cmp(&b.1))
+    })
+    .into_image(n)
+}
+
+/// Creates an `n`x`n` blue noise threshold map for ordered dithering using
+/// Ulichney's void-and-cluster method.
+///
+/// Blue noise has no low-frequency components, so the dithering pattern
+/// doesn't have the regular structure of Bayer matrices. The map tiles
+/// seamlessly.
+///
+/// The map is deterministic. Its generation takes `O(n^4)` time, so `n`
+/// should be at most 128 or so.
+///
+/// All values are in the range `[0, 1)`.
+pub fn blue_noise_threshold_map(n: usize) -> Image<f32> {
+    assert!(n > 0);
+
+    let mut field = EnergyField::new(n, 1.5);
+    let len = n * n;
+
+    // initial binary pattern with ~10% of all pixels set
+    let ones = (len / 10).max(1);
+    let mut random = XorShift(0x2545_f491_4f6c_dd1d);
+    while field.ones < ones {
+        let i = random.next() as usize % len;
+        if !field.pattern[i] {
+            field.toggle(i);
+        }
+    }
+
+    // move points from the tightest clusters to the largest voids until the
+    // pattern is evenly distributed
+    for _ in 0..len {
+        let cluster = field.tightest_cluster();
+        field.toggle(cluster);
+        let void = field.largest_void();
+        field.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+    let initial = field.clone();
+
+    let mut ranks = vec![0; len];
+
+    // phase 1: remove the initial points from the tightest clusters first
+    for rank in (0..field.ones).rev() {
+        let cluster = field.tightest_cluster();
+        field.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+
+    // Phase 2 and 3: fill the largest voids. Ulichney's phase 3 inserts into
+    // the tightest clusters of the zeros instead, but since the energy of the
+    // zeros is a constant minus the energy of the ones, that is the same as
+    // filling the largest voids.
+    let mut field = initial;
+    for rank in field.ones..len {
+        let void = field.largest_void();
+        field.toggle(void);
+        ranks[void] = rank;
+    }
+
+    Ranks(ranks).into_image(n)
+}
+
+/// Converts an image into a threshold map for ordered dithering by ranking its
+/// values.
+///
+/// The darkest pixel becomes 0 and the brightest `(n-1)/n` where `n` is the
+/// number of pixels, so the thresholds are uniformly distributed in `[0, 1)`
+/// no matter the distribution of the image. Equal values are ranked in
+/// row-major order.
+pub fn threshold_map_from_image(img: &Image<f32>) -> Image<f32> {
+    assert!(!img.is_empty());
+
+    let Ranks(ranks) = rank(img.data(), |a, b| a.total_cmp(b));
+    let len = ranks.len() as f32;
+    Image::new(
+        img.size(),
+        ranks.into_iter().map(|r| r as f32 / len).collect(),
+    )
+}
+
+struct Ranks(Vec<usize>);
+impl Ranks {
+    fn into_image(self, n: usize) -> Image<f32> {
+        let area = (n * n) as f32;
+        Image::new(
+            Size::new(n, n),
+            self.0.into_iter().map(|r| r as f32 / area).collect(),
+        )
+    }
+}
+
+/// Returns the rank of each value in the given order. Ties are broken by index.
+fn rank<T>(values: &[T], cmp: impl Fn(&T, &T) -> std::cmp::Ordering) -> Ranks {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| cmp(&values[*a], &values[*b]));
+
+    let mut ranks = vec![0; values.len()];
+    for (rank, i) in order.into_iter().enumerate() {
+        ranks[i] = rank;
+    }
+    Ranks(ranks)
+}
+
+/// A simple PRNG, so generated maps are the same everywhere.
+struct XorShift(u64);
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A toroidal binary pattern and the Gaussian-filtered energy of its ones.
+#[derive(Clone)]
+struct EnergyField {
+    n: usize,
+    /// The Gaussian kernel indexed by wrapped offset.
+    kernel: Vec<f32>,
+    pattern: Vec<bool>,
+    energy: Vec<f32>,
+    ones: usize,
+}
+impl EnergyField {
+    fn new(n: usize, sigma: f32) -> Self {
+        let kernel = Image::from_fn(Size::new(n, n), |x, y| {
+            let dx = x.min(n - x) as f32;
+            let dy = y.min(n - y) as f32;
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        });
+
+        Self {
+            n,
+            kernel: kernel.take(),
+            pattern: vec![false; n * n],
+            energy: vec![0.0; n * n],
+            ones: 0,
+        }
+    }
+
+    fn toggle(&mut self, i: usize) {
+        let n = self.n;
+        let sign = if self.pattern[i] { -1.0 } else { 1.0 };
+        self.pattern[i] = !self.pattern[i];
+        if self.pattern[i] {
+            self.ones += 1;
+        } else {
+            self.ones -= 1;
+        }
+
+        let (px, py) = (i % n, i / n);
+        for y in 0..n {
+            let ky = (y + n - py) % n;
+            let energy = &mut self.energy[y * n..(y + 1) * n];
+            let kernel = &self.kernel[ky * n..(ky + 1) * n];
+            for (x, e) in energy.iter_mut().enumerate() {
+                *e += sign * kernel[(x + n - px) % n];
+            }
+        }
+    }
+
+    /// The one with the highest energy.
+    fn tightest_cluster(&self) -> usize {
+        self.find(true, |a, b| a > b)
+    }
+    /// The zero with the lowest energy.
+    fn largest_void(&self) -> usize {
+        self.find(false, |a, b| a < b)
+    }
+    fn find(&self, value: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (i, (&p, &e)) in self.pattern.iter().zip(&self.energy).enumerate() {
+            if p == value && best.map_or(true, |b| better(e, self.energy[b])) {
+                best = Some(i);
+            }
+        }
+        best.expect("pattern to contain the value")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image_core::Image;
+    use test_util::snap::ImageSnapshot;
+
+    /// Checks that the map contains every threshold `k/n²` exactly once.
+    fn assert_permutation(map: &Image<f32>) {
+        let len = map.len();
+        let mut ranks: Vec<usize> = map
+            .data()
+            .iter()
+            .map(|v| (v * len as f32).round() as usize)
+            .collect();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..len).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bayer() {
+        let map = super::bayer_threshold_map(2);
+        assert_eq!(map.data(), &[0.0, 0.5, 0.75, 0.25]);
+
+        for n in [1, 2, 3, 4, 6, 8, 9, 12, 16, 18, 64] {
+            assert!(super::is_bayer_size(n));
+            assert_permutation(&super::bayer_threshold_map(n));
+        }
+        for n in [0, 5, 7, 10, 14] {
+            assert!(!super::is_bayer_size(n));
+        }
+    }
+
+    #[test]
+    fn clustered_dot() {
+        for n in [1, 2, 5, 8] {
+            assert_permutation(&super::clustered_dot_threshold_map(n));
+        }
+
+        // the dot grows from the center
+        let map = super::clustered_dot_threshold_map(5);
+        assert_eq!(map.row(2)[2], 0.0);
+        assert!(map.row(0)[0] >= 20.0 / 25.0);
+    }
+
+    #[test]
+    fn blue_noise() {
+        for n in [1, 2, 7, 16] {
+            assert_permutation(&super::blue_noise_threshold_map(n));
+        }
+
+        let map = super::blue_noise_threshold_map(32);
+        assert_permutation(&map);
+        map.snapshot("threshold_map_blue_noise_32");
+
+        // Blue noise has no clumps, so the brightest 10% of pixels should
+        // rarely touch each other.
+        let n = map.width();
+        let bright = |x: usize, y: usize| map.row(y % n)[x % n] >= 0.9;
+        let mut touching = 0;
+        for y in 0..n {
+            for x in 0..n {
+                if bright(x, y) && (bright(x + 1, y) || bright(x, y + 1)) {
+                    touching += 1;
+                }
+            }
+        }
+        assert!(touching <= n * n / 100, "{} touching", touching);
+    }
+
+    #[test]
+    fn from_image() {
+        let img = Image::new(image_core::Size::new(2, 2), vec![0.5, 0.1, 0.9, 0.5]);
+        let map = super::threshold_map_from_image(&img);
+        assert_eq!(map.data(), &[0.25, 0.0, 0.75, 0.5]);
+    }
+}
@@ -1,3 +1,28 @@
-pub trait Pixel: Copy + Default + std::ops::AddAssign + std::ops::Mul<f32, Output = Self> {}
-impl<P> Pixel for P where P: Copy + Default + std::ops::AddAssign + std::ops::Mul<f32, Output = Self>
-{}
+use glam::{Vec2, Vec3, Vec3A, Vec4};
+
+pub trait Pixel: Copy + Default + std::ops::AddAssign + std::ops::Mul<f32, Output = Self> {
+    /// Clamps each component to the range `[-limit, limit]`.
+    fn clamp_components(self, limit: f32) -> Self;
+}
+
+impl Pixel for f32 {
+    #[inline]
+    fn clamp_components(self, limit: f32) -> Self {
+        self.clamp(-limit, limit)
+    }
+}
+
+macro_rules! impl_pixel_vec {
+    ($t:ident) => {
+        impl Pixel for $t {
+            #[inline]
+            fn clamp_components(self, limit: f32) -> Self {
+                self.clamp($t::splat(-limit), $t::splat(limit))
+            }
+        }
+    };
+}
+impl_pixel_vec!(Vec2);
+impl_pixel_vec!(Vec3);
+impl_pixel_vec!(Vec3A);
+impl_pixel_vec!(Vec4);
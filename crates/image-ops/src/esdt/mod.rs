@@ -3,12 +3,56 @@
 
 use image_core::Image;
 
+use crate::util::{pad, BorderMode};
+
+mod msdf;
+
+pub use msdf::{msdf, mtsdf};
+
+/// Computes the Euclidean signed distance field of the given image.
+///
+/// The border mode determines how the image continues beyond its edges. Use
+/// [`BorderMode::Wrap`] for tileable images. With [`BorderMode::Transparent`],
+/// nothing is assumed about the pixels outside the image.
 pub fn esdf(
     img: &Image<f32>,
     radius: f32,
     cutoff: f32,
     pre_process: bool,
     post_process: bool,
+    border: BorderMode<f32>,
+) -> Image<f32> {
+    with_padding(img, radius, cutoff, border, |img| {
+        esdf_unpadded(img, radius, cutoff, pre_process, post_process)
+    })
+}
+
+/// Pads the image according to the border mode, computes the distance field
+/// of the padded image, and crops the result to the original size.
+fn with_padding<P: Clone>(
+    img: &Image<f32>,
+    radius: f32,
+    cutoff: f32,
+    border: BorderMode<f32>,
+    distance_field: impl FnOnce(&Image<f32>) -> Image<P>,
+) -> Image<P> {
+    // pixels further away than this don't affect the result
+    let reach = radius.abs() * (1.0 + cutoff.abs());
+    let padding = |len: usize| reach.ceil().min(len as f32) as usize + 2;
+    let (pad_x, pad_y) = (padding(img.width()), padding(img.height()));
+
+    match pad(img.view(), pad_x, pad_y, &border) {
+        Some(padded) => distance_field(&padded).crop(pad_x, pad_y, img.width(), img.height()),
+        None => distance_field(img),
+    }
+}
+
+fn esdf_unpadded(
+    img: &Image<f32>,
+    radius: f32,
+    cutoff: f32,
+    pre_process: bool,
+    post_process: bool,
 ) -> Image<f32> {
     let w = img.width();
     let h = img.height();
@@ -652,27 +696,57 @@ mod tests {
         snap::ImageSnapshot,
     };
 
+    use crate::util::BorderMode;
+
     #[test]
     fn at() {
         let original = read_at();
-        super::esdf(&original, 200.0, 0.25, false, false).snapshot("at_esdf");
+        super::esdf(
+            &original,
+            200.0,
+            0.25,
+            false,
+            false,
+            BorderMode::Transparent,
+        )
+        .snapshot("at_esdf");
     }
 
     #[test]
     fn flower() {
         let original = read_flower().map(|p| p.x);
-        super::esdf(&original, 10.0, 0.25, false, false).snapshot("flower_esdf");
+        super::esdf(&original, 10.0, 0.25, false, false, BorderMode::Transparent)
+            .snapshot("flower_esdf");
     }
 
     #[test]
     fn checker() {
         let original = read_checker();
-        super::esdf(&original, 10.0, 0.5, false, false).snapshot("checker_esdf");
+        super::esdf(&original, 10.0, 0.5, false, false, BorderMode::Transparent)
+            .snapshot("checker_esdf");
     }
 
     #[test]
     fn binary_alpha() {
         let original = read_binary_alpha();
-        super::esdf(&original, 20.0, 0.5, false, false).snapshot("binary_alpha_esdf");
+        super::esdf(&original, 20.0, 0.5, false, false, BorderMode::Transparent)
+            .snapshot("binary_alpha_esdf");
+    }
+
+    #[test]
+    fn flower_wrap() {
+        let original = read_flower().map(|p| p.x);
+        let tile = original.crop(300, 350, 120, 90);
+        let (w, h) = (tile.width(), tile.height());
+
+        let wrapped = super::esdf(&tile, 10.0, 0.25, false, false, BorderMode::Wrap);
+        wrapped.snapshot("flower_esdf_wrap");
+
+        // the distance field of a wrapped tile is the same as the center of a grid of tiles
+        let grid = crate::util::pad(tile.view(), w, h, &BorderMode::Wrap).unwrap();
+        let grid = super::esdf(&grid, 10.0, 0.25, false, false, BorderMode::Transparent);
+        for (a, b) in wrapped.data().iter().zip(grid.crop(w, h, w, h).data()) {
+            assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        }
     }
 }
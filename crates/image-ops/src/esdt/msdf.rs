@@ -0,0 +1,437 @@
+use glam::{Vec2, Vec3A, Vec4};
+use image_core::Image;
+use rstar::{primitives::GeomWithData, RTree};
+
+use super::{esdf_unpadded, esdt, paint_subpixel_offsets, with_padding, SDFStage, INF};
+use crate::util::BorderMode;
+
+// The channels of an edge as a bit set
+const RED: u8 = 0b001;
+const GREEN: u8 = 0b010;
+const BLUE: u8 = 0b100;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// The number of outline points before and after a point that are used to
+/// estimate the direction of the outline at that point.
+const WINDOW: usize = 3;
+
+/// Computes the multi-channel signed distance field (MSDF) of the given image.
+///
+/// The outline of the shape is split into edges at its corners, and each edge
+/// is assigned 2 or 3 of the RGB channels (edge coloring) such that the two
+/// edges of a corner share only one channel. Each channel is the signed
+/// pseudo-distance to the nearest edge of that channel. The median of the 3
+/// channels reproduces the outline with sharp corners.
+///
+/// Points where the direction of the outline changes by more than
+/// `angle_threshold` (in radians) are corners. `radius`, `cutoff`, and `border`
+/// are the same as for [`esdf`](super::esdf).
+pub fn msdf(
+    img: &Image<f32>,
+    radius: f32,
+    cutoff: f32,
+    angle_threshold: f32,
+    border: BorderMode<f32>,
+) -> Image<Vec3A> {
+    mtsdf(img, radius, cutoff, angle_threshold, border).map(|p| Vec3A::from(p.truncate()))
+}
+
+/// Same as [`msdf`], but the alpha channel additionally contains the true
+/// signed distance field (MTSDF) as computed by [`esdf`](super::esdf).
+pub fn mtsdf(
+    img: &Image<f32>,
+    radius: f32,
+    cutoff: f32,
+    angle_threshold: f32,
+    border: BorderMode<f32>,
+) -> Image<Vec4> {
+    with_padding(img, radius, cutoff, border, |img| {
+        mtsdf_unpadded(img, radius, cutoff, angle_threshold)
+    })
+}
+
+fn mtsdf_unpadded(img: &Image<f32>, radius: f32, cutoff: f32, angle_threshold: f32) -> Image<Vec4> {
+    let w = img.width();
+    let h = img.height();
+
+    let mut stage = SDFStage::new(w, h);
+    paint_subpixel_offsets(&mut stage, img, false);
+
+    let outline: Vec<_> = trace_outlines(img)
+        .iter()
+        .flat_map(|points| color_edges(points, angle_threshold))
+        .map(|p| GeomWithData::new(p.position.to_array(), (p.normal, p.color)))
+        .collect();
+    let outline = RTree::bulk_load(outline);
+
+    // The boundary pixels found by paint_subpixel_offsets are the seeds of the
+    // distance transforms. They take the normal and color of a nearby outline
+    // point. Preferring points whose tangent is close assigns pixels next to
+    // corners to the right edge.
+    let mut offsets = vec![Vec2::ZERO; img.len()];
+    let mut normals = vec![Vec2::ZERO; img.len()];
+    let mut colors = vec![0_u8; img.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let j = y * w + x;
+            let outer = Vec2::new(stage.xo[j], stage.yo[j]);
+            let inner = Vec2::new(stage.xi[j], stage.yi[j]);
+            if outer == Vec2::ZERO && inner == Vec2::ZERO {
+                continue;
+            }
+
+            // the outer and inner zero points are on opposite sides of the boundary
+            let offset = (outer + inner) / 2.0;
+            let position = Vec2::new(x as f32, y as f32) + offset;
+            let score = |p: &GeomWithData<[f32; 2], (Vec2, u8)>| {
+                let v = position - Vec2::from(*p.geom());
+                v.dot(p.data.0).abs() + 0.25 * v.length()
+            };
+            let nearest = outline
+                .nearest_neighbor_iter(&position.to_array())
+                .take(4)
+                .min_by(|a, b| score(a).total_cmp(&score(b)));
+            if let Some(nearest) = nearest {
+                offsets[j] = offset;
+                normals[j] = nearest.data.0;
+                colors[j] = nearest.data.1;
+            }
+        }
+    }
+
+    let distances = [RED, GREEN, BLUE].map(|channel| {
+        let mut mask: Vec<f32> = colors
+            .iter()
+            .map(|&c| if c & channel != 0 { 0.0 } else { INF })
+            .collect();
+        let has_seeds = mask.contains(&0.0);
+
+        let mut xs: Vec<f32> = offsets.iter().map(|o| o.x).collect();
+        let mut ys: Vec<f32> = offsets.iter().map(|o| o.y).collect();
+        if has_seeds {
+            esdt(
+                &mut mask,
+                &mut xs,
+                &mut ys,
+                w,
+                h,
+                &mut stage.f,
+                &mut stage.z,
+                &mut stage.b,
+                &mut stage.t,
+                &mut stage.v,
+            );
+        }
+
+        let data = img.data();
+        (0..img.len())
+            .map(|i| {
+                let inside = data[i] > 0.5;
+                if !has_seeds {
+                    return if inside { -INF } else { INF };
+                }
+
+                // the seed pixel is the pixel closest to the target point
+                let v = Vec2::new(xs[i], ys[i]);
+                let target = Vec2::new((i % w) as f32, (i / w) as f32) + v;
+                let (tx, ty) = (target.x.round(), target.y.round());
+                let normal = if tx >= 0.0 && ty >= 0.0 && (tx as usize) < w && (ty as usize) < h {
+                    normals[ty as usize * w + tx as usize]
+                } else {
+                    Vec2::ZERO
+                };
+
+                if normal != Vec2::ZERO {
+                    // pseudo-distance to the tangent of the edge at the target
+                    v.dot(normal)
+                } else if inside {
+                    -v.length()
+                } else {
+                    v.length()
+                }
+            })
+            .collect::<Vec<f32>>()
+    });
+
+    // Edges of different channels can disagree about the inside of a pixel,
+    // e.g. around small details. Those pixels use the true distance instead.
+    let true_sdf = esdf_unpadded(img, radius, cutoff, false, false);
+    let to_alpha = |d: f32| (1.0 - (d / radius + cutoff)).clamp(0.0, 1.0);
+    let [r, g, b] = distances;
+    let data = (0..img.len())
+        .map(|i| {
+            let (r, g, b) = (to_alpha(r[i]), to_alpha(g[i]), to_alpha(b[i]));
+            let median = r.max(g).min(r.min(g).max(b));
+            let inside = img.data()[i] > 0.5;
+            let true_alpha = true_sdf.data()[i];
+
+            let rgb = if (median > 1.0 - cutoff) == inside {
+                Vec3A::new(r, g, b)
+            } else {
+                Vec3A::splat(true_alpha)
+            };
+            rgb.extend(true_alpha)
+        })
+        .collect();
+    Image::new(img.size(), data)
+}
+
+/// Traces the outlines of all shapes in the image with marching squares. A
+/// pixel is inside a shape if its value is greater than 0.5.
+///
+/// Pixels outside the image count as outside, so all outlines are closed. The
+/// returned outlines are oriented such that `(t.y, -t.x)` points to the inside
+/// for the direction `t` of the outline.
+fn trace_outlines(img: &Image<f32>) -> Vec<Vec<Vec2>> {
+    let w = img.width() as isize;
+    let h = img.height() as isize;
+    let data = img.data();
+    let value = |x: isize, y: isize| {
+        if x >= 0 && y >= 0 && x < w && y < h {
+            data[(y * w + x) as usize]
+        } else {
+            0.0
+        }
+    };
+
+    // grid edges are identified by their first pixel and whether they are vertical
+    let grid_w = w as usize + 2;
+    let id = |x: isize, y: isize, vertical: bool| {
+        ((y + 1) as usize * grid_w + (x + 1) as usize) * 2 + vertical as usize
+    };
+    let len = grid_w * (h as usize + 2) * 2;
+    let mut next = vec![usize::MAX; len];
+    let mut points = vec![Vec2::ZERO; len];
+
+    for y in -1..h {
+        for x in -1..w {
+            // the corners and edges of the cell in clockwise order
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let edges = [
+                id(x, y, false),
+                id(x + 1, y, true),
+                id(x, y + 1, false),
+                id(x, y, true),
+            ];
+            let values = corners.map(|(x, y)| value(x, y));
+            let inside = values.map(|v| v > 0.5);
+            if inside.iter().all(|&i| i == inside[0]) {
+                continue;
+            }
+
+            // for each edge: whether the outline enters or exits the shape there
+            let mut crossings = [None; 4];
+            for i in 0..4 {
+                let j = (i + 1) % 4;
+                if inside[i] != inside[j] {
+                    let t = (0.5 - values[i]) / (values[j] - values[i]);
+                    let a = Vec2::new(corners[i].0 as f32, corners[i].1 as f32);
+                    let b = Vec2::new(corners[j].0 as f32, corners[j].1 as f32);
+                    points[edges[i]] = a.lerp(b, t);
+                    crossings[i] = Some(inside[j]);
+                }
+            }
+
+            let saddle = crossings.iter().all(|c| c.is_some());
+            let center_inside = values.iter().sum::<f32>() / 4.0 > 0.5;
+            for i in 0..4 {
+                if crossings[i] != Some(true) {
+                    continue;
+                }
+                let exit = if saddle && center_inside {
+                    // connect the two inside corners
+                    (i + 3) % 4
+                } else {
+                    (1..4)
+                        .map(|k| (i + k) % 4)
+                        .find(|&k| crossings[k] == Some(false))
+                        .unwrap()
+                };
+                next[edges[i]] = edges[exit];
+            }
+        }
+    }
+
+    let mut outlines = Vec::new();
+    for start in 0..len {
+        let mut current = start;
+        let mut outline = Vec::new();
+        while next[current] != usize::MAX {
+            outline.push(points[current]);
+            current = std::mem::replace(&mut next[current], usize::MAX);
+        }
+        if !outline.is_empty() {
+            outlines.push(outline);
+        }
+    }
+    outlines
+}
+
+struct OutlinePoint {
+    position: Vec2,
+    /// The normal of the edge pointing to the inside of the shape.
+    normal: Vec2,
+    color: u8,
+}
+
+/// Splits the given closed outline into edges at corners and assigns colors
+/// to the edges such that the edges of every corner have different colors.
+fn color_edges(points: &[Vec2], angle_threshold: f32) -> Vec<OutlinePoint> {
+    let n = points.len();
+    let at = |i: isize| points[i.rem_euclid(n as isize) as usize];
+    // small outlines must not wrap around
+    let window = WINDOW.min((n - 1) / 2);
+
+    // how much the direction of the outline changes at each point
+    let turn: Vec<f32> = (0..n as isize)
+        .map(|i| {
+            if n <= 2 * WINDOW {
+                return 0.0;
+            }
+            let k = WINDOW as isize;
+            let before = at(i) - at(i - k);
+            let after = at(i + k) - at(i);
+            before.angle_between(after).abs()
+        })
+        .collect();
+
+    // the corner is the point with the largest turn in each run of points
+    // above the threshold
+    let is_candidate = |i: usize| turn[i] > angle_threshold;
+    let mut corners = Vec::new();
+    if let Some(start) = (0..n).find(|&i| !is_candidate(i)) {
+        let mut best: Option<usize> = None;
+        for i in (start + 1..=start + n).map(|i| i % n) {
+            if is_candidate(i) {
+                if best.map_or(true, |b| turn[i] > turn[b]) {
+                    best = Some(i);
+                }
+            } else if let Some(b) = best.take() {
+                corners.push(b);
+            }
+        }
+    }
+    corners.sort_unstable();
+
+    // edges as (first point, number of points, color)
+    let edges: Vec<(usize, usize, u8)> = match corners.len() {
+        0 => vec![(0, n, WHITE)],
+        1 => vec![(corners[0], n, WHITE)],
+        m => corners
+            .iter()
+            .enumerate()
+            .map(|(e, &c)| {
+                let len = (corners[(e + 1) % m] + n - c) % n;
+                let color = if e == m - 1 && m % 2 == 1 {
+                    YELLOW
+                } else if e % 2 == 0 {
+                    CYAN
+                } else {
+                    MAGENTA
+                };
+                (c, len, color)
+            })
+            .collect(),
+    };
+
+    let mut result = Vec::with_capacity(n);
+    for (first, len, color) in edges {
+        // Marching squares cuts corners, so corners aren't in line with either
+        // of their edges. They are left out.
+        let skip = (!corners.is_empty()) as usize;
+        for k in skip..len {
+            let i = (first + k) as isize;
+            let direction = if corners.is_empty() {
+                at(i + window as isize) - at(i - window as isize)
+            } else {
+                // don't look past the corners of the edge
+                let back = k.saturating_sub(window).max(1);
+                let forward = (k + window).min(len - 1);
+                let direction = at((first + forward) as isize) - at((first + back) as isize);
+                if direction == Vec2::ZERO {
+                    at((first + len) as isize) - at(first as isize)
+                } else {
+                    direction
+                }
+            };
+
+            let color = if corners.len() == 1 {
+                // a single corner needs at least 2 colors, so split its edge
+                [CYAN, WHITE, MAGENTA][k * 3 / len]
+            } else {
+                color
+            };
+            result.push(OutlinePoint {
+                position: at(i),
+                normal: Vec2::new(direction.y, -direction.x).normalize_or_zero(),
+                color,
+            });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use image_core::{Image, Size};
+    use test_util::{
+        data::{read_at, read_binary_alpha},
+        snap::ImageSnapshot,
+    };
+
+    use crate::{esdt::esdf, util::BorderMode};
+
+    fn median(p: Vec3A) -> f32 {
+        p.x.max(p.y).min(p.x.min(p.y).max(p.z))
+    }
+
+    #[test]
+    fn square_corners() {
+        let img = Image::from_fn(Size::new(40, 40), |x, y| {
+            ((10..30).contains(&x) && (10..30).contains(&y)) as u8 as f32
+        });
+
+        let outlines = super::trace_outlines(&img);
+        assert_eq!(outlines.len(), 1);
+        let points = super::color_edges(&outlines[0], 1.0);
+        let color_changes = (0..points.len())
+            .filter(|&i| points[i].color != points[(i + 1) % points.len()].color)
+            .count();
+        assert_eq!(color_changes, 4);
+
+        // the normals point to the inside
+        for p in &points {
+            let inside = p.position + p.normal;
+            assert!((10.0..30.0).contains(&inside.x) || (10.0..30.0).contains(&inside.y));
+        }
+
+        // the median reproduces the square, including its corners
+        let sdf = super::msdf(&img, 4.0, 0.5, 1.0, BorderMode::Transparent);
+        for (&a, &m) in img.data().iter().zip(sdf.data()) {
+            assert_eq!(a > 0.5, median(m) > 0.5);
+        }
+    }
+
+    #[test]
+    fn matches_shape() {
+        // pixels where the channels disagree fall back to the true SDF
+        let original = read_binary_alpha();
+        let sdf = super::msdf(&original, 20.0, 0.5, 1.0, BorderMode::Transparent);
+        let true_sdf = esdf(&original, 20.0, 0.5, false, false, BorderMode::Transparent);
+        for ((&a, &m), &t) in original.data().iter().zip(sdf.data()).zip(true_sdf.data()) {
+            let m = median(m);
+            assert!(m == t || (a > 0.5) == (m > 0.5), "{} {} {}", a, m, t);
+        }
+    }
+
+    #[test]
+    fn at() {
+        let original = read_at();
+        super::msdf(&original, 16.0, 0.5, 1.0, BorderMode::Transparent).snapshot("at_msdf");
+        super::mtsdf(&original, 16.0, 0.5, 1.0, BorderMode::Transparent).snapshot("at_mtsdf");
+    }
+}
@@ -6,7 +6,7 @@ use std::ops::Range;
 use crate::{
     blend::{overlay_mut, overlay_self_mut},
     fragment_blur::fragment_blur_alpha,
-    util::{div_ceil, from_image_cow, move_range, Grid},
+    util::{div_ceil, from_image_cow, move_range, pad, BorderMode, Grid},
 };
 
 pub enum FillMode {
@@ -23,24 +23,70 @@ pub enum FillMode {
     },
 }
 
+/// Fills the transparent pixels of the given image using the given method.
+///
+/// The border mode determines the pixels beyond the edges of the image that can be used to fill
+/// transparent pixels. Use [`BorderMode::Wrap`] for tileable textures and
+/// [`BorderMode::Transparent`] to only use the pixels of the image.
 pub fn fill_alpha(
     image: &mut Image<Vec4>,
     threshold: f32,
     mode: FillMode,
+    border: BorderMode<Vec4>,
     temp: Option<&mut Image<Vec4>>,
 ) {
+    let border = border.map(|mut c| {
+        make_binary_alpha(std::slice::from_mut(&mut c), threshold);
+        c
+    });
     make_binary_alpha(image.data_mut(), threshold);
 
+    let (w, h) = (image.width(), image.height());
     match mode {
         FillMode::Fragment {
             iterations,
             fragment_count,
-        } => fill_alpha_fragment_blur(image, iterations, fragment_count, temp),
-        FillMode::ExtendColor { iterations } => fill_alpha_extend(image, iterations as usize),
+        } => fill_alpha_fragment_blur(image, iterations, fragment_count, border, temp),
+        FillMode::ExtendColor { iterations } => {
+            // colors spread by one pixel per iteration, and all pixels are filled after w + h
+            // iterations
+            let p = (iterations as usize).min(w + h);
+            with_border(image, p, p, &border, |image| {
+                fill_alpha_extend(image, iterations as usize)
+            })
+        }
         FillMode::Nearest {
             min_radius: radius,
             anti_aliasing,
-        } => fill_alpha_nearest(image, radius, anti_aliasing),
+        } => {
+            // the padding must contain the nearest copy of every pixel outside the image
+            let (pad_x, pad_y) = match border {
+                BorderMode::Wrap => (w / 2 + 1, h / 2 + 1),
+                BorderMode::Reflect => (w, h),
+                _ => (1, 1),
+            };
+            with_border(image, pad_x, pad_y, &border, |image| {
+                fill_alpha_nearest(image, radius, anti_aliasing)
+            })
+        }
+    }
+}
+
+/// Applies the given function to the image padded with the given border mode and copies the
+/// center back into the image.
+fn with_border(
+    image: &mut Image<Vec4>,
+    pad_x: usize,
+    pad_y: usize,
+    border: &BorderMode<Vec4>,
+    f: impl FnOnce(&mut Image<Vec4>),
+) {
+    match pad(image.view(), pad_x, pad_y, border) {
+        Some(mut padded) => {
+            f(&mut padded);
+            *image = padded.crop(pad_x, pad_y, image.width(), image.height());
+        }
+        None => f(image),
     }
 }
 
@@ -56,6 +102,7 @@ fn fill_alpha_fragment_blur(
     image: &mut Image<Vec4>,
     iterations: u32,
     fragment_count: u32,
+    border: BorderMode<Vec4>,
     temp: Option<&mut Image<Vec4>>,
 ) {
     if iterations == 0 {
@@ -70,10 +117,11 @@ fn fill_alpha_fragment_blur(
         let angle_offset = i as f32;
 
         buffer = fragment_blur_alpha(
-            original,
+            original.view(),
             radius,
             fragment_count as usize,
             angle_offset,
+            border,
             Some(buffer),
         );
         overlay_self_mut(&mut buffer, 2);
@@ -400,6 +448,8 @@ fn create_sampler_around(
 mod tests {
     use test_util::{data::read_flower_transparent, snap::ImageSnapshot};
 
+    use crate::util::{pad, BorderMode};
+
     #[test]
     fn fill_alpha_texture() {
         let mut original = read_flower_transparent();
@@ -410,6 +460,7 @@ mod tests {
                 iterations: 6,
                 fragment_count: 5,
             },
+            BorderMode::Transparent,
             None,
         );
         original.snapshot("fill_alpha_texture");
@@ -422,6 +473,7 @@ mod tests {
             &mut original,
             0.15,
             super::FillMode::ExtendColor { iterations: 64 },
+            BorderMode::Transparent,
             None,
         );
         original.snapshot("fill_alpha_color");
@@ -437,8 +489,48 @@ mod tests {
                 min_radius: 50,
                 anti_aliasing: false,
             },
+            BorderMode::Transparent,
             None,
         );
         original.snapshot("fill_alpha_nearest");
     }
+
+    #[test]
+    fn fill_alpha_wrap() {
+        let original = read_flower_transparent();
+        let (w, h) = (160, 120);
+        let tile = original.crop(470, 640, w, h);
+
+        let fill = |mut img: image_core::Image<glam::Vec4>, mode, border| {
+            super::fill_alpha(&mut img, 0.15, mode, border, None);
+            img
+        };
+        let fragment = || super::FillMode::Fragment {
+            iterations: 5,
+            fragment_count: 5,
+        };
+
+        // filling a wrapped tile is the same as filling the center of a grid of tiles
+        let wrapped = fill(tile.clone(), fragment(), BorderMode::Wrap);
+        let grid = pad(tile.view(), w, h, &BorderMode::Wrap).unwrap();
+        let grid = fill(grid, fragment(), BorderMode::Transparent);
+        assert_eq!(wrapped.data(), grid.crop(w, h, w, h).data());
+        wrapped.snapshot("fill_alpha_texture_wrap");
+
+        fill(
+            tile.clone(),
+            super::FillMode::ExtendColor { iterations: 64 },
+            BorderMode::Wrap,
+        )
+        .snapshot("fill_alpha_color_wrap");
+        fill(
+            tile,
+            super::FillMode::Nearest {
+                min_radius: 50,
+                anti_aliasing: false,
+            },
+            BorderMode::Wrap,
+        )
+        .snapshot("fill_alpha_nearest_wrap");
+    }
 }
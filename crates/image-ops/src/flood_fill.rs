@@ -0,0 +1,207 @@
+use image_core::{Image, ImageView, ImageViewMut};
+
+use crate::{components::Connectivity, dither::ColorSpace};
+
+/// Selects all pixels that are connected to the seed pixel and whose color is
+/// within `tolerance` of the color of the seed pixel (magic wand).
+///
+/// The distance between two colors is measured by
+/// [`ColorSpace::distance_2`] of the given color space. The returned mask is 1 for selected
+/// pixels and 0 otherwise. If the seed is outside the image, nothing is
+/// selected.
+pub fn flood_fill_mask<P: Copy, C: ColorSpace<P>>(
+    img: ImageView<P>,
+    seed: (usize, usize),
+    tolerance: f32,
+    connectivity: Connectivity,
+    colorspace: &C,
+) -> Image<f32> {
+    let size = img.size();
+    let mut mask = Image::from_const(size, 0.0);
+    let (seed_x, seed_y) = seed;
+    if seed_x >= size.width || seed_y >= size.height {
+        return mask;
+    }
+
+    let seed_color = colorspace.get_coordinate(img.row(seed_y)[seed_x]);
+    let tolerance_2 = tolerance * tolerance;
+    let matches =
+        |p: P| colorspace.distance_2(&colorspace.get_coordinate(p), &seed_color) <= tolerance_2;
+
+    // scanline fill: each stack entry is a pixel that is known to match
+    let w = size.width;
+    let data = mask.data_mut();
+    let mut stack = vec![seed];
+    while let Some((x, y)) = stack.pop() {
+        if data[y * w + x] != 0.0 {
+            continue;
+        }
+
+        let row = img.row(y);
+        let mut left = x;
+        while left > 0 && data[y * w + left - 1] == 0.0 && matches(row[left - 1]) {
+            left -= 1;
+        }
+        let mut right = x + 1;
+        while right < w && data[y * w + right] == 0.0 && matches(row[right]) {
+            right += 1;
+        }
+        data[y * w + left..y * w + right].fill(1.0);
+
+        // the range of pixels in the rows above and below that touch the span
+        let (start, end) = match connectivity {
+            Connectivity::Four => (left, right),
+            Connectivity::Eight => (left.saturating_sub(1), (right + 1).min(w)),
+        };
+        let neighbors = [y.checked_sub(1), Some(y + 1).filter(|&y| y < size.height)];
+        for ny in neighbors.into_iter().flatten() {
+            let row = img.row(ny);
+            let mut in_run = false;
+            for nx in start..end {
+                let selectable = data[ny * w + nx] == 0.0 && matches(row[nx]);
+                // only push the first pixel of each run
+                if selectable && !in_run {
+                    stack.push((nx, ny));
+                }
+                in_run = selectable;
+            }
+        }
+    }
+
+    mask
+}
+
+/// Fills all pixels selected by [`flood_fill_mask`] with the given color and
+/// returns the mask.
+pub fn flood_fill<P: Copy, C: ColorSpace<P>>(
+    mut img: ImageViewMut<P>,
+    seed: (usize, usize),
+    tolerance: f32,
+    connectivity: Connectivity,
+    colorspace: &C,
+    color: P,
+) -> Image<f32> {
+    let mask = flood_fill_mask(img.view(), seed, tolerance, connectivity, colorspace);
+    for (row, mask) in img.rows_mut().zip(mask.rows()) {
+        for (p, &m) in row.iter_mut().zip(mask) {
+            if m != 0.0 {
+                *p = color;
+            }
+        }
+    }
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use image_core::{Image, Size};
+    use test_util::{data::read_portrait, fixtures::parse_image, snap::ImageSnapshot};
+
+    use crate::{
+        components::Connectivity,
+        dither::{Redmean, RGB},
+    };
+
+    #[test]
+    fn mask() {
+        let img = parse_image(&[
+            "0010000", //
+            "0110110", //
+            "1001010", //
+            "0011000", //
+            "5110009", //
+        ]);
+        let select = |seed, tolerance, connectivity| {
+            let mask = super::flood_fill_mask(img.view(), seed, tolerance, connectivity, &RGB);
+            let rows: Vec<String> = mask
+                .rows()
+                .map(|r| r.iter().map(|&m| if m > 0.0 { '#' } else { '.' }).collect())
+                .collect();
+            rows
+        };
+
+        assert_eq!(
+            select((0, 0), 0.0, Connectivity::Four),
+            [
+                "##.....", //
+                "#......", //
+                ".......", //
+                ".......", //
+                ".......", //
+            ]
+        );
+        assert_eq!(
+            select((0, 0), 0.0, Connectivity::Eight),
+            [
+                "##.####", //
+                "#..#..#", //
+                ".##.#.#", //
+                "##..###", //
+                "...###.", //
+            ]
+        );
+        assert_eq!(
+            select((3, 3), 0.0, Connectivity::Four),
+            [
+                ".......", //
+                ".......", //
+                "...#...", //
+                "..##...", //
+                ".##....", //
+            ]
+        );
+        // 0.5 is within the tolerance of 0.1, but 0.9 isn't
+        assert_eq!(
+            select((1, 4), 0.45, Connectivity::Four),
+            [
+                "#######", //
+                "#######", //
+                "#######", //
+                "#######", //
+                "######.", //
+            ]
+        );
+
+        // seeds outside the image select nothing
+        assert!(select((7, 0), 1.0, Connectivity::Four)
+            .iter()
+            .all(|r| !r.contains('#')));
+    }
+
+    #[test]
+    fn distance_metric() {
+        let img = Image::new(
+            Size::new(3, 1),
+            vec![
+                Vec3A::new(0.0, 0.0, 0.3),
+                Vec3A::ZERO,
+                Vec3A::new(0.0, 0.3, 0.0),
+            ],
+        );
+
+        // both colors are 0.3 away from black in RGB
+        let rgb = super::flood_fill_mask(img.view(), (1, 0), 0.32, Connectivity::Four, &RGB);
+        assert_eq!(rgb.data(), &[1.0, 1.0, 1.0]);
+
+        // redmean weighs green differences more than blue differences
+        let redmean =
+            super::flood_fill_mask(img.view(), (1, 0), 0.32, Connectivity::Four, &Redmean);
+        assert_eq!(redmean.data(), &[1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn flood_fill() {
+        let mut img = read_portrait();
+        let mask = super::flood_fill(
+            img.view_mut(),
+            (10, 10),
+            0.15,
+            Connectivity::Eight,
+            &RGB,
+            Vec3A::new(1.0, 0.0, 1.0),
+        );
+        img.snapshot("flood_fill");
+        mask.snapshot("flood_fill_mask");
+    }
+}
@@ -1,12 +1,12 @@
 use std::{
     f32::consts::PI,
-    ops::{AddAssign, DivAssign, MulAssign, Range},
+    ops::{AddAssign, DivAssign, Range},
 };
 
 use glam::Vec4;
-use image_core::Image;
+use image_core::{Image, ImageView, PremultiplyAlpha};
 
-use crate::util::{from_const, move_range, move_range_i};
+use crate::util::{from_const, move_range, move_range_i, pad, BorderMode};
 
 struct Offset(isize, isize);
 
@@ -23,49 +23,53 @@ fn get_offsets(radius: f32, count: usize, angle_offset: f32) -> Vec<Offset> {
         .collect()
 }
 
-/// Returns a range such that all values in the range `+ offset` are in the range `0..len`.
-fn offset_range(offset: isize, len: usize) -> Range<usize> {
+/// Returns a range such that all values in the range `+ offset` are in the range `0..src_len`
+/// and all values in the range are in the range `0..len`.
+fn offset_range(offset: isize, len: usize, src_len: usize) -> Range<usize> {
     let start = (-offset).clamp(0, len as isize) as usize;
-    let end = (len as isize - offset).clamp(0, len as isize) as usize;
+    let end = (src_len as isize - offset).clamp(0, len as isize) as usize;
     start..end
 }
 
-/// Applies fragment blur to the given image.
+/// Adds all offset copies of the source image onto the destination and returns how many copies
+/// were added to each pixel.
 ///
-/// This method assumes that the given image has premultiplied alpha.
-pub fn fragment_blur_premultiplied_alpha(
-    src: &Image<Vec4>,
-    radius: f32,
-    count: usize,
-    angle_offset: f32,
-    out: Option<Image<Vec4>>,
-) -> Image<Vec4> {
-    let mut dest = from_const(src.size(), Vec4::ZERO, out);
-    let w = src.width();
-    let h = src.height();
-
-    let s_data = src.data();
+/// The source image is padded by `pad_x` and `pad_y` pixels on each side, so the destination
+/// pixel `(x, y)` corresponds to the source pixel `(x + pad_x, y + pad_y)`.
+fn accumulate<P>(
+    src: ImageView<P>,
+    pad_x: usize,
+    pad_y: usize,
+    dest: &mut Image<P>,
+    offsets: &[Offset],
+) -> Vec<u8>
+where
+    P: Clone + AddAssign,
+{
+    let w = dest.width();
+    let h = dest.height();
     let d = dest.data_mut();
 
-    assert!(count <= 255);
+    assert!(offsets.len() <= 255);
     let mut count_array: Vec<u8> = vec![0; d.len()];
 
-    for Offset(offset_x, offset_y) in get_offsets(radius, count, angle_offset) {
-        let x_range = offset_range(offset_x, w);
-        let y_range = offset_range(offset_y, h);
+    for Offset(offset_x, offset_y) in offsets {
+        let offset_x = offset_x + pad_x as isize;
+        let offset_y = offset_y + pad_y as isize;
+        let x_range = offset_range(offset_x, w, src.width());
+        let y_range = offset_range(offset_y, h, src.height());
         if x_range.is_empty() || y_range.is_empty() {
             continue;
         }
-        let index_offset = offset_y * w as isize + offset_x;
+        let src_x_range = move_range_i(&x_range, offset_x);
         for y in y_range {
             let dest_range = move_range(&x_range, y * w);
-            let src_range = move_range_i(&dest_range, index_offset);
 
-            let src_data = &s_data[src_range];
+            let src_data = &src.row((y as isize + offset_y) as usize)[src_x_range.clone()];
             let dst_data = &mut d[dest_range.clone()];
             assert_eq!(src_data.len(), dst_data.len());
             for (d, s) in dst_data.iter_mut().zip(src_data) {
-                *d += *s;
+                *d += s.clone();
             }
 
             for c in &mut count_array[dest_range] {
@@ -74,10 +78,60 @@ pub fn fragment_blur_premultiplied_alpha(
         }
     }
 
-    for (p, c) in d.iter_mut().zip(count_array) {
-        let rgb = if p.w == 0. { 1. } else { 1. / p.w };
-        let a = if c == 0 { 1. } else { 1. / c as f32 };
-        p.mul_assign(Vec4::new(rgb, rgb, rgb, a));
+    count_array
+}
+
+/// Adds all offset copies of the source image onto the destination using the given border
+/// mode and returns how many copies were added to each pixel.
+fn accumulate_with_border<P>(
+    src: ImageView<P>,
+    border: &BorderMode<P>,
+    dest: &mut Image<P>,
+    offsets: &[Offset],
+) -> Vec<u8>
+where
+    P: Copy + AddAssign,
+{
+    let pad_x = offsets
+        .iter()
+        .map(|o| o.0.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    let pad_y = offsets
+        .iter()
+        .map(|o| o.1.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+
+    match pad(src, pad_x, pad_y, border) {
+        Some(padded) => accumulate(padded.view(), pad_x, pad_y, dest, offsets),
+        None => accumulate(src, 0, 0, dest, offsets),
+    }
+}
+
+/// Applies fragment blur to the given image.
+///
+/// This method assumes that the given image has premultiplied alpha. The color of
+/// [`BorderMode::Constant`] has to be premultiplied as well.
+pub fn fragment_blur_premultiplied_alpha(
+    src: ImageView<Vec4>,
+    radius: f32,
+    count: usize,
+    angle_offset: f32,
+    border: BorderMode<Vec4>,
+    out: Option<Image<Vec4>>,
+) -> Image<Vec4> {
+    let mut dest = from_const(src.size(), Vec4::ZERO, out);
+
+    let offsets = get_offsets(radius, count, angle_offset);
+    let count_array = accumulate_with_border(src, &border, &mut dest, &offsets);
+
+    for (p, c) in dest.data_mut().iter_mut().zip(count_array) {
+        // the alpha channel holds the sum of alphas, so it cancels out the count
+        *p = p.unpremultiply_alpha();
+        if c != 0 {
+            p.w /= c as f32;
+        }
     }
 
     dest
@@ -85,73 +139,44 @@ pub fn fragment_blur_premultiplied_alpha(
 
 /// Applies fragment blur to the given image.
 pub fn fragment_blur_alpha(
-    src: &Image<Vec4>,
+    src: ImageView<Vec4>,
     radius: f32,
     count: usize,
     angle_offset: f32,
+    border: BorderMode<Vec4>,
     out: Option<Image<Vec4>>,
 ) -> Image<Vec4> {
-    let pre = src.map(|v| Vec4::new(v.x * v.w, v.y * v.w, v.z * v.w, v.w));
-    fragment_blur_premultiplied_alpha(&pre, radius, count, angle_offset, out)
+    let pre = src.map(|v| v.premultiply_alpha());
+    let border = border.map(|c| c.premultiply_alpha());
+    fragment_blur_premultiplied_alpha(pre.view(), radius, count, angle_offset, border, out)
 }
 
 /// Applies fragment blur to the given image.
 ///
 /// Each channel will be blurred independently of each other. If the image has an alpha channel,
 /// use [`fragment_blur_alpha`] instead.
+///
+/// With [`BorderMode::Transparent`], offsets that fall outside the image are ignored. Use
+/// [`BorderMode::Wrap`] for tileable images.
 pub fn fragment_blur<P>(
-    src: &Image<P>,
+    src: ImageView<P>,
     radius: f32,
     count: usize,
     angle_offset: f32,
+    border: BorderMode<P>,
     out: Option<Image<P>>,
 ) -> Image<P>
 where
-    P: Clone + Default + AddAssign + DivAssign<f32>,
+    P: Copy + Default + AddAssign + DivAssign<f32>,
 {
     let mut dest = from_const(src.size(), Default::default(), out);
-    let w = src.width();
-    let h = src.height();
 
-    let s_data = src.data();
-    let d = dest.data_mut();
+    let offsets = get_offsets(radius, count, angle_offset);
+    let count_array = accumulate_with_border(src, &border, &mut dest, &offsets);
 
-    assert!(count <= 255);
-    let mut count_array: Vec<u8> = vec![0; d.len()];
-
-    for Offset(offset_x, offset_y) in get_offsets(radius, count, angle_offset) {
-        let x_range = offset_range(offset_x, w);
-        let y_range = offset_range(offset_y, h);
-        if x_range.is_empty() || y_range.is_empty() {
-            continue;
-        }
-        let index_offset = offset_y * w as isize + offset_x;
-        for y in y_range {
-            let dest_range = move_range(&x_range, y * w);
-            let src_range = move_range_i(&dest_range, index_offset);
-
-            let src_data = &s_data[src_range];
-            let dst_data = &mut d[dest_range.clone()];
-            assert_eq!(src_data.len(), dst_data.len());
-            for (d, s) in dst_data.iter_mut().zip(src_data) {
-                *d += s.clone();
-            }
-
-            for c in &mut count_array[dest_range] {
-                *c += 1;
-            }
-        }
-    }
-
-    // set all count==0 to 1
-    for c in &mut count_array {
-        *c = if *c == 0 { 1 } else { *c }
-    }
-
-    let c = &count_array[..];
-    assert!(c.len() == d.len());
-    for i in 0..d.len() {
-        d[i].div_assign(c[i] as f32);
+    for (p, c) in dest.data_mut().iter_mut().zip(count_array) {
+        // pixels without any samples stay at zero
+        p.div_assign(c.max(1) as f32);
     }
 
     dest
@@ -164,21 +189,91 @@ mod tests {
         snap::ImageSnapshot,
     };
 
+    use crate::util::BorderMode;
+
     #[test]
     fn fragment_blur() {
         let original = read_portrait();
-        let result = super::fragment_blur(&original, 20., 5, 1.234, None);
+        let result = super::fragment_blur(
+            original.view(),
+            20.,
+            5,
+            1.234,
+            BorderMode::Transparent,
+            None,
+        );
         result.snapshot("fragment_blur");
     }
 
+    #[test]
+    fn fragment_blur_region() {
+        let original = read_portrait();
+        let (x, y, w, h) = (20, 30, 120, 90);
+        let from_view = super::fragment_blur(
+            original.view_region(x, y, w, h),
+            20.,
+            5,
+            1.234,
+            BorderMode::Transparent,
+            None,
+        );
+        let from_copy = super::fragment_blur(
+            original.crop(x, y, w, h).view(),
+            20.,
+            5,
+            1.234,
+            BorderMode::Transparent,
+            None,
+        );
+        assert_eq!(from_view.data(), from_copy.data());
+    }
+
     #[test]
     fn fragment_blur_alpha() {
         let original = read_flower_transparent();
-        let result = super::fragment_blur_alpha(&original, 20., 5, 1.234, None);
+        let result = super::fragment_blur_alpha(
+            original.view(),
+            20.,
+            5,
+            1.234,
+            BorderMode::Transparent,
+            None,
+        );
         result.snapshot("fragment_blur_alpha-1");
 
         let original = read_abstract_transparent();
-        let result = super::fragment_blur_alpha(&original, 20., 5, 1.234, None);
+        let result = super::fragment_blur_alpha(
+            original.view(),
+            20.,
+            5,
+            1.234,
+            BorderMode::Transparent,
+            None,
+        );
         result.snapshot("fragment_blur_alpha-2");
     }
+
+    #[test]
+    fn fragment_blur_border() {
+        let original = read_portrait();
+        let tile = original.crop(100, 150, 120, 90);
+
+        let result = super::fragment_blur(tile.view(), 20., 5, 1.234, BorderMode::Wrap, None);
+        result.snapshot("fragment_blur_wrap");
+        super::fragment_blur(tile.view(), 20., 5, 1.234, BorderMode::Reflect, None)
+            .snapshot("fragment_blur_reflect");
+
+        // the blurred tile must match the center of a blurred 3x3 grid of tiles
+        let (w, h) = (tile.width(), tile.height());
+        let grid = image_core::Image::new(
+            image_core::Size::new(w * 3, h * 3),
+            (0..h * 3)
+                .flat_map(|y| (0..w * 3).map(move |x| (x % w, y % h)))
+                .map(|(x, y)| tile.row(y)[x])
+                .collect(),
+        );
+        let grid_result =
+            super::fragment_blur(grid.view(), 20., 5, 1.234, BorderMode::Transparent, None);
+        assert_eq!(result.data(), grid_result.crop(w, h, w, h).data());
+    }
 }
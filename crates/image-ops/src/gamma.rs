@@ -1,10 +1,10 @@
-use image_core::NDimImage;
+use image_core::NDimViewMut;
 use rayon::prelude::*;
 
-pub fn gamma_ndim(image: &mut NDimImage, gamma: f32) {
-    // we want to divide the image into chunks
-    const BLOCK_SIZE: usize = 1024 * 8;
+// we want to divide the image into chunks
+const BLOCK_SIZE: usize = 1024 * 8;
 
+pub fn gamma_ndim(mut image: NDimViewMut, gamma: f32) {
     if image.channels() == 4 {
         // only apply gamma to RGB channels
         image
@@ -40,7 +40,7 @@ pub fn gamma_ndim(image: &mut NDimImage, gamma: f32) {
 
                         chunks
                             .iter_mut()
-                            .for_each(|f| unsafe { avx2::pow_clamp(f, gamma) });
+                            .for_each(|f| unsafe { crate::util::avx2::pow_clamp(f, gamma) });
                         return;
                     }
                 }
@@ -51,247 +51,148 @@ pub fn gamma_ndim(image: &mut NDimImage, gamma: f32) {
     }
 }
 
-#[allow(clippy::excessive_precision)]
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-mod avx2 {
-
-    #[cfg(target_arch = "x86")]
-    use std::arch::x86::*;
-    #[cfg(target_arch = "x86_64")]
-    use std::arch::x86_64::*;
-
-    #[target_feature(enable = "avx2")]
-    pub unsafe fn pow_clamp(x: &mut [f32; 8], y: f32) {
-        // pow(x, y) == exp(y * log(x))
-        let x_m = _mm256_loadu_ps(x as *const _);
-        let y_m = _mm256_set1_ps(y);
-        let p = pow(x_m, y_m);
-
-        // clamp to 0..1
-        let p = _mm256_max_ps(p, _mm256_setzero_ps());
-        let p = _mm256_min_ps(p, ONE);
-
-        _mm256_storeu_ps(x as *mut _, p);
-    }
-
-    #[target_feature(enable = "avx2")]
-    pub unsafe fn pow(x: __m256, y: __m256) -> __m256 {
-        let mut t = log(x);
-        t = _mm256_mul_ps(t, y);
-        t = exp(t);
-
-        // this doesn't work for x==0, so we have to set t to 0 where x==0
-        let c = _mm256_cmp_ps(x, _mm256_setzero_ps(), _CMP_EQ_OQ);
-        t = _mm256_andnot_ps(c, t);
+/// A transfer function that maps between linear light and an encoded signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    /// A pure power function with the given gamma, e.g. `Power(2.2)`.
+    Power(f32),
+    /// The piecewise sRGB transfer function (IEC 61966-2-1).
+    Srgb,
+    /// The ITU-R BT.709 OETF. This is also used by BT.2020.
+    Rec709,
+    /// The SMPTE ST 2084 perceptual quantizer. Linear 1.0 corresponds to
+    /// 10000 cd/m².
+    Pq,
+    /// The hybrid log-gamma OETF of ITU-R BT.2100.
+    Hlg,
+}
 
-        t
-    }
+mod pq {
+    pub const M1: f32 = 2610.0 / 16384.0;
+    pub const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    pub const C1: f32 = 3424.0 / 4096.0;
+    pub const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    pub const C3: f32 = 2392.0 / 4096.0 * 32.0;
+}
+#[allow(clippy::excessive_precision)]
+mod hlg {
+    pub const A: f32 = 0.17883277;
+    pub const B: f32 = 0.28466892;
+    pub const C: f32 = 0.55991073;
+}
 
-    const fn const_f32(x: f32) -> __m256 {
-        // https://www.reddit.com/r/rust/comments/8ltns0/how_to_set_some_simd_constants/
-        #[repr(C)]
-        union U {
-            a: __m256,
-            b: [f32; 8],
-        }
-        unsafe {
-            U {
-                b: [x, x, x, x, x, x, x, x],
+impl TransferFunction {
+    /// Decodes an encoded value into linear light.
+    #[inline]
+    pub fn to_linear(self, v: f32) -> f32 {
+        match self {
+            TransferFunction::Power(gamma) => v.powf(gamma),
+            TransferFunction::Srgb => crate::color::srgb_to_linear(v),
+            TransferFunction::Rec709 => {
+                if v < 0.081 {
+                    v / 4.5
+                } else {
+                    ((v + 0.099) / 1.099).powf(1.0 / 0.45)
+                }
+            }
+            TransferFunction::Pq => {
+                let p = v.max(0.0).powf(1.0 / pq::M2);
+                ((p - pq::C1).max(0.0) / (pq::C2 - pq::C3 * p)).powf(1.0 / pq::M1)
+            }
+            TransferFunction::Hlg => {
+                if v <= 0.5 {
+                    v * v / 3.0
+                } else {
+                    (((v - hlg::C) / hlg::A).exp() + hlg::B) / 12.0
+                }
             }
-            .a
         }
     }
-    const fn const_i32(x: i32) -> __m256i {
-        #[repr(C)]
-        union U {
-            a: __m256i,
-            b: [i32; 8],
-        }
-        unsafe {
-            U {
-                b: [x, x, x, x, x, x, x, x],
+    /// Encodes a linear light value.
+    #[inline]
+    pub fn from_linear(self, v: f32) -> f32 {
+        match self {
+            TransferFunction::Power(gamma) => v.powf(1.0 / gamma),
+            TransferFunction::Srgb => crate::color::linear_to_srgb(v),
+            TransferFunction::Rec709 => {
+                if v < 0.018 {
+                    v * 4.5
+                } else {
+                    1.099 * v.powf(0.45) - 0.099
+                }
+            }
+            TransferFunction::Pq => {
+                let y = v.max(0.0).powf(pq::M1);
+                ((pq::C1 + pq::C2 * y) / (1.0 + pq::C3 * y)).powf(pq::M2)
+            }
+            TransferFunction::Hlg => {
+                if v <= 1.0 / 12.0 {
+                    (3.0 * v.max(0.0)).sqrt()
+                } else {
+                    hlg::A * (12.0 * v - hlg::B).ln() + hlg::C
+                }
             }
-            .a
         }
     }
-    fn bit_cast_i32_to_f32(x: __m256i) -> __m256 {
-        #[repr(C)]
-        union U {
-            a: __m256,
-            b: __m256i,
+}
+
+/// Decodes the given image into linear light.
+///
+/// The values are clamped to 0..1 before decoding, since the transfer
+/// functions are only defined for this range. Same as [`gamma_ndim`], the
+/// alpha channel of RGBA images is left unchanged.
+pub fn to_linear_ndim(mut image: NDimViewMut, transfer: TransferFunction) {
+    map_color_ndim(image.reborrow(), |v| v.clamp(0.0, 1.0));
+    match transfer {
+        TransferFunction::Power(gamma) => gamma_ndim(image, gamma),
+        // the alpha channel must not be decoded
+        TransferFunction::Srgb if image.channels() != 4 => {
+            crate::color::srgb_to_linear_slice(image.data_mut())
         }
-        unsafe { U { b: x }.a }
+        _ => map_color_ndim(image, |v| transfer.to_linear(v)),
     }
-    fn bit_cast_f32_to_i32(x: __m256) -> __m256i {
-        #[repr(C)]
-        union U {
-            a: __m256,
-            b: __m256i,
+}
+/// Encodes the given image from linear light.
+///
+/// The values are clamped to 0..1 before encoding, see [`to_linear_ndim`].
+pub fn from_linear_ndim(mut image: NDimViewMut, transfer: TransferFunction) {
+    map_color_ndim(image.reborrow(), |v| v.clamp(0.0, 1.0));
+    match transfer {
+        TransferFunction::Power(gamma) => gamma_ndim(image, 1.0 / gamma),
+        TransferFunction::Srgb if image.channels() != 4 => {
+            crate::color::linear_to_srgb_slice(image.data_mut())
         }
-        unsafe { U { a: x }.b }
-    }
-
-    // The following code is adapted from
-    // https://github.com/yuyichao/avx2_mathfun/blob/3c48718fd7fa4f427906e59d43e7cc1ef69cc276/avx2_mathfun.h
-    //
-    // Copyright (C) 2012 Giovanni Garberoglio
-    // Interdisciplinary Laboratory for Computational Science (LISC)
-    // Fondazione Bruno Kessler and University of Trento
-    // via Sommarive, 18
-    // I-38123 Trento (Italy)
-    // zlib license
-
-    const ONE: __m256 = const_f32(1.0);
-    const HALF: __m256 = const_f32(0.5);
-
-    const X7F: __m256i = const_i32(0x7F);
-
-    #[target_feature(enable = "avx2")]
-    pub unsafe fn exp(mut x: __m256) -> __m256 {
-        const EXP_HI: __m256 = const_f32(88.3762626647949);
-        const EXP_LO: __m256 = const_f32(-88.3762626647949);
-
-        const LOG2EF: __m256 = const_f32(1.44269504088896341);
-        const EXP_C1: __m256 = const_f32(0.693359375);
-        const EXP_C2: __m256 = const_f32(-2.12194440e-4);
-
-        const EXP_P0: __m256 = const_f32(1.9875691500E-4);
-        const EXP_P1: __m256 = const_f32(1.3981999507E-3);
-        const EXP_P2: __m256 = const_f32(8.3334519073E-3);
-        const EXP_P3: __m256 = const_f32(4.1665795894E-2);
-        const EXP_P4: __m256 = const_f32(1.6666665459E-1);
-        const EXP_P5: __m256 = const_f32(5.0000001201E-1);
-
-        x = _mm256_min_ps(x, EXP_HI);
-        x = _mm256_max_ps(x, EXP_LO);
-
-        /* express exp(x) as exp(g + n*log(2)) */
-        let mut fx = _mm256_mul_ps(x, LOG2EF);
-        fx = _mm256_add_ps(fx, HALF);
-
-        /* how to perform a floorf with SSE: just below */
-        // imm0 = _mm256_cvttps_epi32(fx);
-        // tmp  = _mm256_cvtepi32_ps(imm0);
-
-        let tmp = _mm256_floor_ps(fx);
-
-        /* if greater, substract 1 */
-        let mut mask = _mm256_cmp_ps(tmp, fx, _CMP_GT_OS);
-        mask = _mm256_and_ps(mask, ONE);
-        fx = _mm256_sub_ps(tmp, mask);
-
-        let mut z = _mm256_mul_ps(fx, EXP_C2);
-        x = _mm256_sub_ps(x, _mm256_mul_ps(fx, EXP_C1));
-        x = _mm256_sub_ps(x, z);
-
-        z = _mm256_mul_ps(x, x);
-
-        let mut y = _mm256_mul_ps(EXP_P0, x);
-        y = _mm256_add_ps(y, EXP_P1);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, EXP_P2);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, EXP_P3);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, EXP_P4);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, EXP_P5);
-        y = _mm256_mul_ps(y, z);
-        y = _mm256_add_ps(y, x);
-        y = _mm256_add_ps(y, ONE);
-
-        /* build 2^n */
-        let mut imm0 = _mm256_cvttps_epi32(fx);
-        // another two AVX2 instructions
-        imm0 = _mm256_add_epi32(imm0, X7F);
-        imm0 = _mm256_slli_epi32(imm0, 23);
-        y = _mm256_mul_ps(y, bit_cast_i32_to_f32(imm0));
-        y
+        _ => map_color_ndim(image, |v| transfer.from_linear(v)),
     }
+}
 
-    #[target_feature(enable = "avx2")]
-    pub unsafe fn log(mut x: __m256) -> __m256 {
-        const MIN_NORM_POS: __m256i = const_i32(0x00800000);
-        const INV_MANT_MASK: __m256i = const_i32(!0x7f800000);
-
-        const SQRTHF: __m256 = const_f32(0.707106781186547524);
-        const LOG_P0: __m256 = const_f32(7.0376836292E-2);
-        const LOG_P1: __m256 = const_f32(-1.1514610310E-1);
-        const LOG_P2: __m256 = const_f32(1.1676998740E-1);
-        const LOG_P3: __m256 = const_f32(-1.2420140846E-1);
-        const LOG_P4: __m256 = const_f32(1.4249322787E-1);
-        const LOG_P5: __m256 = const_f32(-1.6668057665E-1);
-        const LOG_P6: __m256 = const_f32(2.0000714765E-1);
-        const LOG_P7: __m256 = const_f32(-2.4999993993E-1);
-        const LOG_P8: __m256 = const_f32(3.3333331174E-1);
-        const LOG_Q1: __m256 = const_f32(-2.12194440e-4);
-        const LOG_Q2: __m256 = const_f32(0.693359375);
-
-        let invalid_mask = _mm256_cmp_ps(x, _mm256_setzero_ps(), _CMP_LE_OS);
-
-        x = _mm256_max_ps(x, bit_cast_i32_to_f32(MIN_NORM_POS)); /* cut off denormalized stuff */
-
-        // can be done with AVX2
-        let mut imm0 = _mm256_srli_epi32(bit_cast_f32_to_i32(x), 23);
-
-        /* keep only the fractional part */
-        x = _mm256_and_ps(x, bit_cast_i32_to_f32(INV_MANT_MASK));
-        x = _mm256_or_ps(x, HALF);
-
-        // this is again another AVX2 instruction
-        imm0 = _mm256_sub_epi32(imm0, X7F);
-        let mut e = _mm256_cvtepi32_ps(imm0);
-
-        e = _mm256_add_ps(e, ONE);
-
-        /* part2:
-           if( x < SQRTHF ) {
-           e -= 1;
-           x = x + x - 1.0;
-           } else { x = x - 1.0; }
-        */
-        let mask = _mm256_cmp_ps(x, SQRTHF, _CMP_LT_OS);
-        let tmp = _mm256_and_ps(x, mask);
-        x = _mm256_sub_ps(x, ONE);
-        e = _mm256_sub_ps(e, _mm256_and_ps(ONE, mask));
-        x = _mm256_add_ps(x, tmp);
-
-        let z = _mm256_mul_ps(x, x);
-
-        let mut y = _mm256_mul_ps(LOG_P0, x);
-        y = _mm256_add_ps(y, LOG_P1);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P2);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P3);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P4);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P5);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P6);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P7);
-        y = _mm256_mul_ps(y, x);
-        y = _mm256_add_ps(y, LOG_P8);
-        y = _mm256_mul_ps(y, x);
-
-        y = _mm256_mul_ps(y, z);
-
-        y = _mm256_add_ps(y, _mm256_mul_ps(e, LOG_Q1));
-
-        y = _mm256_sub_ps(y, _mm256_mul_ps(z, HALF));
+fn map_color_ndim(mut image: NDimViewMut, f: impl Fn(f32) -> f32 + Sync) {
+    if image.channels() == 4 {
+        image
+            .data_mut()
+            .par_chunks_mut(BLOCK_SIZE)
+            .for_each(|chunk| {
+                let (chunks, rest) = image_core::util::slice_as_chunks_mut::<f32, 4>(chunk);
+                assert!(rest.is_empty());
 
-        x = _mm256_add_ps(x, y);
-        x = _mm256_add_ps(x, _mm256_mul_ps(e, LOG_Q2));
-        x = _mm256_or_ps(x, invalid_mask); // negative arg will be NAN
-        x
+                chunks.iter_mut().for_each(|p| {
+                    // only apply to the RGB channels
+                    p[0] = f(p[0]);
+                    p[1] = f(p[1]);
+                    p[2] = f(p[2]);
+                });
+            });
+    } else {
+        image
+            .data_mut()
+            .par_chunks_mut(BLOCK_SIZE)
+            .for_each(|chunk| chunk.iter_mut().for_each(|v| *v = f(*v)));
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use image_core::NDimImage;
+    use image_core::{NDimImage, Shape};
     use test_util::{
         data::{read_flower_transparent, read_portrait},
         snap::ImageSnapshot,
@@ -300,11 +201,100 @@ mod tests {
     #[test]
     fn gamma() {
         let mut img: NDimImage = read_flower_transparent().into();
-        super::gamma_ndim(&mut img, 2.2);
+        super::gamma_ndim(img.view_mut(), 2.2);
         img.snapshot("gamma_rgba");
 
         let mut img: NDimImage = read_portrait().into();
-        super::gamma_ndim(&mut img, 2.2);
+        super::gamma_ndim(img.view_mut(), 2.2);
         img.snapshot("gamma_rgb");
     }
+
+    #[test]
+    fn transfer_functions() {
+        use super::TransferFunction;
+
+        let all = [
+            TransferFunction::Power(2.2),
+            TransferFunction::Srgb,
+            TransferFunction::Rec709,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+        ];
+        for transfer in all {
+            assert_eq!(transfer.to_linear(0.0), 0.0, "{:?}", transfer);
+            assert!(
+                (transfer.to_linear(1.0) - 1.0).abs() < 1e-5,
+                "{:?}",
+                transfer
+            );
+
+            for i in 0..=100 {
+                let v = i as f32 / 100.0;
+                let back = transfer.from_linear(transfer.to_linear(v));
+                assert!((back - v).abs() < 1e-4, "{:?}: {} != {}", transfer, back, v);
+            }
+        }
+
+        assert!((TransferFunction::Srgb.to_linear(0.5) - 0.21404114).abs() < 1e-6);
+        assert!((TransferFunction::Hlg.from_linear(1.0 / 12.0) - 0.5).abs() < 1e-6);
+        // 100 cd/m² is roughly 0.508 in PQ
+        assert!((TransferFunction::Pq.from_linear(0.01) - 0.5081).abs() < 1e-3);
+    }
+
+    #[test]
+    fn transfer_ndim() {
+        let mut img: NDimImage = read_flower_transparent().into();
+        super::to_linear_ndim(img.view_mut(), super::TransferFunction::Srgb);
+        img.snapshot("transfer_srgb_to_linear_rgba");
+
+        let original: NDimImage = read_portrait().into();
+        let mut img = original.clone();
+        super::to_linear_ndim(img.view_mut(), super::TransferFunction::Rec709);
+        super::from_linear_ndim(img.view_mut(), super::TransferFunction::Rec709);
+        for (a, b) in img.data().iter().zip(original.data()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn transfer_ndim_clamps() {
+        use super::TransferFunction;
+
+        let all = [
+            TransferFunction::Power(2.2),
+            TransferFunction::Srgb,
+            TransferFunction::Rec709,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+        ];
+        // enough values for the SIMD paths
+        let values: Vec<f32> = (0..40).map(|i| i as f32 / 20.0 - 0.5).collect();
+        for transfer in all {
+            for channels in [1, 4] {
+                let shape = Shape::new(values.len() / channels, 1, channels);
+                let expected = |f: &dyn Fn(f32) -> f32| -> Vec<f32> {
+                    let mut expected = values.clone();
+                    for (i, v) in expected.iter_mut().enumerate() {
+                        if channels != 4 || i % 4 != 3 {
+                            *v = f(v.clamp(0.0, 1.0));
+                        }
+                    }
+                    expected
+                };
+                let check = |actual: &[f32], expected: &[f32]| {
+                    for (a, e) in actual.iter().zip(expected) {
+                        assert!((a - e).abs() < 1e-4, "{:?}: {} != {}", transfer, a, e);
+                    }
+                };
+
+                let mut img = NDimImage::new(shape, values.clone());
+                super::to_linear_ndim(img.view_mut(), transfer);
+                check(img.data(), &expected(&|v| transfer.to_linear(v)));
+
+                let mut img = NDimImage::new(shape, values.clone());
+                super::from_linear_ndim(img.view_mut(), transfer);
+                check(img.data(), &expected(&|v| transfer.from_linear(v)));
+            }
+        }
+    }
 }
@@ -1,11 +1,20 @@
 pub mod blend;
+pub mod blur;
+pub mod channel;
+pub mod color;
+pub mod components;
+pub mod convolve;
 pub mod dither;
 pub mod esdt;
 pub mod fill_alpha;
+pub mod flood_fill;
 pub mod fragment_blur;
 pub mod gamma;
+pub mod morphology;
 pub mod palette;
 pub mod pixel_art;
 pub mod scale;
+pub mod sharpen;
 pub mod threshold;
-mod util;
+pub mod util;
+pub mod warp;
@@ -0,0 +1,573 @@
+use image_core::{Image, ImageView, NDimImage, NDimView};
+use rayon::prelude::*;
+
+use crate::{
+    channel::{merge_ndim, split_ndim},
+    util::{pad, BorderMode, FixedBits},
+};
+
+/// The shape of the neighborhood of a morphological operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuringElement {
+    /// A square with a side length of `2 * radius + 1`.
+    Square(usize),
+    /// All pixels with a Euclidean distance of at most `radius`.
+    Disk(usize),
+    /// A plus sign with arms of length `radius`.
+    Cross(usize),
+}
+
+impl StructuringElement {
+    fn radius(self) -> usize {
+        match self {
+            StructuringElement::Square(r)
+            | StructuringElement::Disk(r)
+            | StructuringElement::Cross(r) => r,
+        }
+    }
+
+    /// Returns the half widths of the horizontal spans that make up the
+    /// element, one for each row offset `-radius..=radius`.
+    fn spans(self) -> Vec<usize> {
+        let r = self.radius();
+        (0..=2 * r)
+            .map(|i| {
+                let dy = i.abs_diff(r);
+                match self {
+                    StructuringElement::Square(_) => r,
+                    StructuringElement::Disk(_) => ((r * r - dy * dy) as f64).sqrt() as usize,
+                    StructuringElement::Cross(_) => {
+                        if dy == 0 {
+                            r
+                        } else {
+                            0
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MorphOp {
+    /// The minimum of the neighborhood.
+    Erode,
+    /// The maximum of the neighborhood.
+    Dilate,
+    /// Erode followed by dilate. Removes small bright features.
+    Open,
+    /// Dilate followed by erode. Removes small dark features.
+    Close,
+    /// Dilate minus erode. Highlights edges.
+    Gradient,
+    /// The image minus its opening. Keeps only small bright features.
+    TopHat,
+    /// The closing of the image minus the image. Keeps only small dark
+    /// features.
+    BlackHat,
+}
+
+impl MorphOp {
+    /// The number of erosions and dilations that are applied one after the
+    /// other.
+    fn steps(self) -> usize {
+        match self {
+            MorphOp::Erode | MorphOp::Dilate | MorphOp::Gradient => 1,
+            MorphOp::Open | MorphOp::Close | MorphOp::TopHat | MorphOp::BlackHat => 2,
+        }
+    }
+}
+
+/// Extends the image by the given border, so that all pixels of the
+/// neighborhoods of all steps of the operation are inside the extended image,
+/// applies the given function, and crops the result to the original size.
+///
+/// Pixels outside the extended image are ignored, which is exactly what
+/// transparent borders need, so they aren't extended at all.
+fn with_border(
+    img: ImageView<f32>,
+    op: MorphOp,
+    element: StructuringElement,
+    border: &BorderMode<f32>,
+    f: impl FnOnce(ImageView<f32>) -> Image<f32>,
+) -> Image<f32> {
+    let r = element.radius() * op.steps();
+    match pad(img, r, r, border) {
+        Some(padded) => f(padded.view())
+            .view()
+            .crop(r, r, img.width(), img.height())
+            .into_owned(),
+        None => f(img),
+    }
+}
+
+/// Applies the given grayscale morphological operation to the given image.
+///
+/// Pixels outside the image are determined by the given border mode. With
+/// [`BorderMode::Transparent`], they are ignored, so the edges of the image
+/// are neither eroded nor dilated by the border.
+pub fn morphology(
+    img: ImageView<f32>,
+    op: MorphOp,
+    element: StructuringElement,
+    border: BorderMode<f32>,
+) -> Image<f32> {
+    with_border(img, op, element, &border, |img| {
+        morphology_unbordered(img, op, element)
+    })
+}
+
+/// Same as [`morphology`], but ignores pixels outside the image.
+fn morphology_unbordered(
+    img: ImageView<f32>,
+    op: MorphOp,
+    element: StructuringElement,
+) -> Image<f32> {
+    let spans = element.spans();
+    let erode = |img: ImageView<f32>| flat_filter(img, &spans, Extremum::Min);
+    let dilate = |img: ImageView<f32>| flat_filter(img, &spans, Extremum::Max);
+    let difference = |a: &Image<f32>, b: ImageView<f32>| {
+        Image::new(
+            a.size(),
+            a.data()
+                .iter()
+                .zip(b.rows().flatten())
+                .map(|(a, b)| a - b)
+                .collect(),
+        )
+    };
+
+    match op {
+        MorphOp::Erode => erode(img),
+        MorphOp::Dilate => dilate(img),
+        MorphOp::Open => dilate(erode(img).view()),
+        MorphOp::Close => erode(dilate(img).view()),
+        MorphOp::Gradient => difference(&dilate(img), erode(img).view()),
+        MorphOp::TopHat => {
+            let opened = dilate(erode(img).view());
+            difference(&img.into_owned(), opened.view())
+        }
+        MorphOp::BlackHat => difference(&erode(dilate(img).view()), img),
+    }
+}
+
+/// Applies the given grayscale morphological operation to each channel of the
+/// given image.
+///
+/// See [`morphology`].
+pub fn morphology_ndim(
+    img: NDimView,
+    op: MorphOp,
+    element: StructuringElement,
+    border: BorderMode<&[f32]>,
+) -> NDimImage {
+    if let BorderMode::Constant(value) = border {
+        assert_eq!(
+            value.len(),
+            img.channels(),
+            "the border color must have one value per channel"
+        );
+    }
+    if img.channels() == 0 {
+        return img.into_owned();
+    }
+
+    let channels: Vec<NDimImage> = split_ndim(img)
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| morphology(c.view(), op, element, border.map(|v| v[i])).into())
+        .collect();
+    let views: Vec<NDimView> = channels.iter().map(|c| c.view()).collect();
+    merge_ndim(&views).expect("all channels have the same size")
+}
+
+/// Applies the given morphological operation to the binary mask of all pixels
+/// above the given threshold. The result is 1 for set pixels and 0 otherwise.
+///
+/// Pixels outside the image are determined by the given border mode like for
+/// [`morphology`], and a constant border is thresholded like the image. This
+/// is the same as thresholding the image and applying [`morphology`], just
+/// faster.
+pub fn binary_morphology(
+    img: ImageView<f32>,
+    threshold: f32,
+    op: MorphOp,
+    element: StructuringElement,
+    border: BorderMode<f32>,
+) -> Image<f32> {
+    with_border(img, op, element, &border, |img| {
+        binary_morphology_unbordered(img, threshold, op, element)
+    })
+}
+
+/// Same as [`binary_morphology`], but ignores pixels outside the image.
+fn binary_morphology_unbordered(
+    img: ImageView<f32>,
+    threshold: f32,
+    op: MorphOp,
+    element: StructuringElement,
+) -> Image<f32> {
+    let spans = element.spans();
+    let mask: Vec<FixedBits> = img
+        .rows()
+        .map(|row| FixedBits::from_slice(row, |p| *p > threshold))
+        .collect();
+
+    let erode = |mask: &[FixedBits]| binary_erode(mask, &spans);
+    let dilate = |mask: &[FixedBits]| binary_dilate(mask, &spans);
+    let difference = |mut a: Vec<FixedBits>, b: &[FixedBits]| {
+        for (a, b) in a.iter_mut().zip(b) {
+            let mut b = b.clone();
+            b.not();
+            a.and(&b);
+        }
+        a
+    };
+
+    let result = match op {
+        MorphOp::Erode => erode(&mask),
+        MorphOp::Dilate => dilate(&mask),
+        MorphOp::Open => dilate(&erode(&mask)),
+        MorphOp::Close => erode(&dilate(&mask)),
+        MorphOp::Gradient => difference(dilate(&mask), &erode(&mask)),
+        MorphOp::TopHat => {
+            let opened = dilate(&erode(&mask));
+            difference(mask, &opened)
+        }
+        MorphOp::BlackHat => difference(erode(&dilate(&mask)), &mask),
+    };
+
+    let size = img.size();
+    let data = result
+        .iter()
+        .flat_map(|row| (0..size.width).map(move |x| row.get(x) == Some(true)))
+        .map(|b| b as u8 as f32)
+        .collect();
+    Image::new(size, data)
+}
+
+fn binary_dilate(mask: &[FixedBits], spans: &[usize]) -> Vec<FixedBits> {
+    let width = mask.first().map_or(0, FixedBits::len);
+    let r = spans.len() / 2;
+    let max_half_width = spans.iter().copied().max().unwrap_or(0);
+
+    let mut result = vec![FixedBits::new(width); mask.len()];
+    let mut horizontal = mask.to_vec();
+    for half_width in 0..=max_half_width {
+        if half_width > 0 {
+            horizontal.iter_mut().for_each(FixedBits::expand_one);
+        }
+
+        for (i, _) in spans.iter().enumerate().filter(|(_, w)| **w == half_width) {
+            let dy = i as isize - r as isize;
+            for (y, out) in result.iter_mut().enumerate() {
+                if let Some(row) = horizontal.get((y as isize + dy) as usize) {
+                    out.or(row);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn binary_erode(mask: &[FixedBits], spans: &[usize]) -> Vec<FixedBits> {
+    // erosion is the complement of the dilation of the complement
+    let mut inverted = mask.to_vec();
+    inverted.iter_mut().for_each(FixedBits::not);
+    let mut result = binary_dilate(&inverted, spans);
+    result.iter_mut().for_each(FixedBits::not);
+    result
+}
+
+#[derive(Clone, Copy)]
+enum Extremum {
+    Min,
+    Max,
+}
+
+impl Extremum {
+    /// The value that doesn't change the result.
+    #[inline]
+    fn identity(self) -> f32 {
+        match self {
+            Extremum::Min => f32::INFINITY,
+            Extremum::Max => f32::NEG_INFINITY,
+        }
+    }
+    #[inline]
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Extremum::Min => a.min(b),
+            Extremum::Max => a.max(b),
+        }
+    }
+}
+
+/// Returns the minimum or maximum of the neighborhood described by the given
+/// spans for each pixel.
+fn flat_filter(img: ImageView<f32>, spans: &[usize], op: Extremum) -> Image<f32> {
+    let size = img.size();
+    if size.is_empty() {
+        return img.into_owned();
+    }
+
+    // filter rows with each distinct span width
+    let mut half_widths = spans.to_vec();
+    half_widths.sort_unstable();
+    half_widths.dedup();
+    let horizontal: Vec<(usize, Image<f32>)> = half_widths
+        .into_iter()
+        .map(|half_width| {
+            let mut data = vec![0.0; size.len()];
+            data.par_chunks_exact_mut(size.width)
+                .enumerate()
+                .for_each_init(Default::default, |buffers, (y, out)| {
+                    filter_line(img.row(y), half_width, op, buffers, out);
+                });
+            (half_width, Image::new(size, data))
+        })
+        .collect();
+    let filtered = |half_width: usize| {
+        let (_, img) = horizontal.iter().find(|(w, _)| *w == half_width).unwrap();
+        img
+    };
+
+    // combine the rows of the spans
+    let r = spans.len() / 2;
+    let mut data = vec![op.identity(); size.len()];
+    data.par_chunks_exact_mut(size.width)
+        .enumerate()
+        .for_each(|(y, out)| {
+            for (i, &half_width) in spans.iter().enumerate() {
+                let y = y + i;
+                if y < r || y - r >= size.height {
+                    continue;
+                }
+                for (o, &p) in out.iter_mut().zip(filtered(half_width).row(y - r)) {
+                    *o = op.apply(*o, p);
+                }
+            }
+        });
+    Image::new(size, data)
+}
+
+/// Computes the minimum or maximum of every window of `2 * half_width + 1`
+/// pixels centered on each pixel of the line.
+///
+/// This uses the van Herk/Gil-Werman algorithm, so the running time does not
+/// depend on the window size.
+fn filter_line(
+    line: &[f32],
+    half_width: usize,
+    op: Extremum,
+    (padded, prefix, suffix): &mut (Vec<f32>, Vec<f32>, Vec<f32>),
+    out: &mut [f32],
+) {
+    if half_width == 0 {
+        out.copy_from_slice(line);
+        return;
+    }
+
+    let window = 2 * half_width + 1;
+    padded.clear();
+    padded.resize(half_width, op.identity());
+    padded.extend_from_slice(line);
+    padded.resize(line.len() + 2 * half_width, op.identity());
+
+    // the extremum from the start of each block of `window` pixels
+    prefix.clear();
+    prefix.resize(padded.len(), op.identity());
+    for (i, chunk) in padded.chunks(window).enumerate() {
+        let mut acc = op.identity();
+        for (j, &p) in chunk.iter().enumerate() {
+            acc = op.apply(acc, p);
+            prefix[i * window + j] = acc;
+        }
+    }
+
+    // the extremum until the end of each block
+    suffix.clear();
+    suffix.resize(padded.len(), op.identity());
+    for (i, chunk) in padded.chunks(window).enumerate() {
+        let mut acc = op.identity();
+        for (j, &p) in chunk.iter().enumerate().rev() {
+            acc = op.apply(acc, p);
+            suffix[i * window + j] = acc;
+        }
+    }
+
+    // every window spans at most 2 blocks
+    for (x, out) in out.iter_mut().enumerate() {
+        *out = op.apply(suffix[x], prefix[x + window - 1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image_core::{Image, NDimImage, Size};
+    use test_util::{
+        data::{read_binary_alpha, read_portrait},
+        fixtures::pattern,
+        snap::ImageSnapshot,
+    };
+
+    use super::{MorphOp, StructuringElement};
+    use crate::util::BorderMode;
+
+    const BORDERS: [BorderMode<f32>; 6] = [
+        BorderMode::Clamp,
+        BorderMode::Reflect,
+        BorderMode::Wrap,
+        BorderMode::Constant(0.3),
+        BorderMode::Constant(0.7),
+        BorderMode::Transparent,
+    ];
+
+    const OPS: [MorphOp; 7] = [
+        MorphOp::Erode,
+        MorphOp::Dilate,
+        MorphOp::Open,
+        MorphOp::Close,
+        MorphOp::Gradient,
+        MorphOp::TopHat,
+        MorphOp::BlackHat,
+    ];
+
+    /// Erodes or dilates the given image without any tricks.
+    fn naive(
+        img: &Image<f32>,
+        element: StructuringElement,
+        dilate: bool,
+        border: BorderMode<f32>,
+    ) -> Image<f32> {
+        let spans = element.spans();
+        let r = spans.len() as isize / 2;
+        img.map_pos(|_, x, y| {
+            let mut result = if dilate {
+                f32::NEG_INFINITY
+            } else {
+                f32::INFINITY
+            };
+            for (dy, &w) in (-r..=r).zip(&spans) {
+                for dx in -(w as isize)..=w as isize {
+                    let (x, y) = (x as isize + dx, y as isize + dy);
+                    if let Some(p) = border.get(img.view(), x, y) {
+                        result = if dilate { result.max(p) } else { result.min(p) };
+                    }
+                }
+            }
+            result
+        })
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(StructuringElement::Square(2).spans(), vec![2; 5]);
+        assert_eq!(StructuringElement::Cross(2).spans(), vec![0, 0, 2, 0, 0]);
+        assert_eq!(StructuringElement::Disk(2).spans(), vec![0, 1, 2, 1, 0]);
+        assert_eq!(
+            StructuringElement::Disk(4).spans(),
+            vec![0, 2, 3, 3, 4, 3, 3, 2, 0]
+        );
+        assert_eq!(StructuringElement::Disk(0).spans(), vec![0]);
+    }
+
+    #[test]
+    fn matches_naive() {
+        let img: Image<f32> = pattern(Size::new(23, 17));
+        for element in [
+            StructuringElement::Square(0),
+            StructuringElement::Square(2),
+            StructuringElement::Disk(5),
+            StructuringElement::Cross(3),
+            StructuringElement::Square(30),
+        ] {
+            for border in BORDERS {
+                let eroded = super::morphology(img.view(), MorphOp::Erode, element, border);
+                assert_eq!(eroded.data(), naive(&img, element, false, border).data());
+                let dilated = super::morphology(img.view(), MorphOp::Dilate, element, border);
+                assert_eq!(dilated.data(), naive(&img, element, true, border).data());
+            }
+        }
+    }
+
+    #[test]
+    fn binary_matches_grayscale() {
+        let img: Image<f32> = pattern(Size::new(23, 17));
+        let threshold = 0.45;
+        let binary = img.map(|p| (*p > threshold) as u8 as f32);
+
+        for element in [
+            StructuringElement::Square(1),
+            StructuringElement::Disk(4),
+            StructuringElement::Cross(2),
+            StructuringElement::Square(70),
+        ] {
+            for (op, border) in OPS.into_iter().flat_map(|op| BORDERS.map(|b| (op, b))) {
+                let binary_border = border.map(|p| (p > threshold) as u8 as f32);
+                let expected = super::morphology(binary.view(), op, element, binary_border);
+                let result = super::binary_morphology(img.view(), threshold, op, element, border);
+                assert_eq!(
+                    result.data(),
+                    expected.data(),
+                    "{:?} {:?} {:?}",
+                    op,
+                    element,
+                    border
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ndim() {
+        let img: Image<f32> = pattern(Size::new(23, 17));
+        let ndim = NDimImage::from_fn_c(img.size(), |x, y| {
+            let p = img.row(y)[x];
+            [p, 1.0 - p]
+        });
+        let element = StructuringElement::Disk(3);
+
+        let border = BorderMode::Constant(&[0.2, 0.6][..]);
+
+        let result = super::morphology_ndim(ndim.view(), MorphOp::Gradient, element, border);
+        let expected = super::morphology(
+            img.view(),
+            MorphOp::Gradient,
+            element,
+            BorderMode::Constant(0.2),
+        );
+        let inverted = img.map(|p| 1.0 - p);
+        let expected_inverted = super::morphology(
+            inverted.view(),
+            MorphOp::Gradient,
+            element,
+            BorderMode::Constant(0.6),
+        );
+        for (i, p) in result.data().chunks_exact(2).enumerate() {
+            assert_eq!(p, [expected.data()[i], expected_inverted.data()[i]]);
+        }
+    }
+
+    #[test]
+    fn morphology() {
+        let original = read_portrait().map(|p| p.x * 0.299 + p.y * 0.587 + p.z * 0.114);
+        let element = StructuringElement::Disk(4);
+        let border = BorderMode::Transparent;
+        super::morphology(original.view(), MorphOp::Open, element, border)
+            .snapshot("morphology_open");
+        super::morphology(original.view(), MorphOp::Gradient, element, border)
+            .snapshot("morphology_gradient");
+
+        let mask = read_binary_alpha();
+        super::binary_morphology(
+            mask.view(),
+            0.5,
+            MorphOp::Close,
+            StructuringElement::Square(6),
+            BorderMode::Transparent,
+        )
+        .snapshot("morphology_binary_close");
+    }
+}
@@ -0,0 +1,516 @@
+use std::borrow::Cow;
+
+use glam::Vec3A;
+use image_core::{util::vec_into_flattened, NDimImage, NDimView, Shape};
+
+use crate::color::{lab_to_linear, linear_to_srgb_rgb};
+
+/// A file format for color palettes.
+///
+/// All formats store 8-bit sRGB colors without alpha, except for ASE, which
+/// stores floating-point colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteFormat {
+    /// GIMP palette (`.gpl`).
+    Gpl,
+    /// Adobe Color Table (`.act`). Stores at most 256 colors.
+    Act,
+    /// Adobe Swatch Exchange (`.ase`).
+    Ase,
+    /// JASC palette (`.pal`), as used by Paint Shop Pro.
+    Pal,
+    /// A list of hex colors (`.hex`), as used by Lospec.
+    Hex,
+}
+
+impl PaletteFormat {
+    /// Returns the format for the given file extension (without the leading
+    /// dot), ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gpl" => Some(Self::Gpl),
+            "act" => Some(Self::Act),
+            "ase" => Some(Self::Ase),
+            "pal" => Some(Self::Pal),
+            "hex" => Some(Self::Hex),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteFileError {
+    /// The file does not start with the signature of its format.
+    InvalidHeader,
+    /// The file ends before all colors were read.
+    UnexpectedEnd,
+    /// A line of a text format could not be parsed. Lines start at 1.
+    InvalidLine { line: usize },
+    /// An ASE color uses a color model other than RGB, CMYK, LAB, or Gray.
+    UnsupportedColorModel { model: [u8; 4] },
+    /// The file doesn't contain any colors.
+    NoColors,
+    /// The format cannot store more than `max_colors` colors.
+    TooManyColors {
+        max_colors: usize,
+        actual_colors: usize,
+    },
+    /// Only palettes with 1 (gray), 3 (RGB), or 4 (RGBA) channels can be
+    /// written.
+    UnsupportedChannels { channels: usize },
+}
+
+/// Reads a palette file.
+///
+/// The palette is returned as an RGB image with a height of 1 and one pixel
+/// per color, in the order of the file.
+pub fn read_palette(data: &[u8], format: PaletteFormat) -> Result<NDimImage, PaletteFileError> {
+    let colors = match format {
+        PaletteFormat::Gpl => read_gpl(data)?,
+        PaletteFormat::Act => read_act(data)?,
+        PaletteFormat::Ase => read_ase(data)?,
+        PaletteFormat::Pal => read_pal(data)?,
+        PaletteFormat::Hex => read_hex(data)?,
+    };
+
+    if colors.is_empty() {
+        return Err(PaletteFileError::NoColors);
+    }
+
+    let shape = Shape::new(colors.len(), 1, 3);
+    Ok(NDimImage::new(shape, vec_into_flattened(colors)))
+}
+
+/// Writes a palette file.
+///
+/// The palette can be any gray, RGB, or RGBA image. Its pixels are written in
+/// row-major order. Alpha is ignored, since none of the formats support it.
+pub fn write_palette(
+    palette: NDimView,
+    format: PaletteFormat,
+) -> Result<Vec<u8>, PaletteFileError> {
+    let colors: Vec<[f32; 3]> = match palette.channels() {
+        1 => palette.data().iter().map(|&g| [g; 3]).collect(),
+        3 | 4 => palette
+            .data()
+            .chunks_exact(palette.channels())
+            .map(|c| [c[0], c[1], c[2]])
+            .collect(),
+        channels => return Err(PaletteFileError::UnsupportedChannels { channels }),
+    };
+
+    if colors.is_empty() {
+        return Err(PaletteFileError::NoColors);
+    }
+
+    match format {
+        PaletteFormat::Gpl => Ok(write_gpl(&colors)),
+        PaletteFormat::Act => write_act(&colors),
+        PaletteFormat::Ase => Ok(write_ase(&colors)),
+        PaletteFormat::Pal => Ok(write_pal(&colors)),
+        PaletteFormat::Hex => Ok(write_hex(&colors)),
+    }
+}
+
+fn from_u8(rgb: [u8; 3]) -> [f32; 3] {
+    rgb.map(|c| c as f32 / 255.0)
+}
+fn to_u8(rgb: &[f32; 3]) -> [u8; 3] {
+    rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Returns the trimmed lines of a text file with their 1-based line numbers.
+fn lines(data: &[u8]) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+    // palette files are ASCII, but names may use any encoding
+    data.split(|&b| b == b'\n')
+        .map(|line| match String::from_utf8_lossy(line) {
+            Cow::Borrowed(line) => Cow::Borrowed(line.trim()),
+            Cow::Owned(line) => Cow::Owned(line.trim().to_string()),
+        })
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+}
+
+/// Parses 3 decimal color components separated by whitespace. Anything after
+/// them is ignored.
+fn parse_rgb(line: &str) -> Option<[u8; 3]> {
+    let mut parts = line.split_whitespace();
+    let mut next = || parts.next()?.parse::<u8>().ok();
+    Some([next()?, next()?, next()?])
+}
+
+fn read_gpl(data: &[u8]) -> Result<Vec<[f32; 3]>, PaletteFileError> {
+    let mut lines = lines(data);
+    match lines.next() {
+        Some((_, line)) if line == "GIMP Palette" => {}
+        _ => return Err(PaletteFileError::InvalidHeader),
+    }
+
+    let mut colors = Vec::new();
+    for (i, line) in lines {
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+
+        let rgb = parse_rgb(&line).ok_or(PaletteFileError::InvalidLine { line: i })?;
+        colors.push(from_u8(rgb));
+    }
+    Ok(colors)
+}
+fn write_gpl(colors: &[[f32; 3]]) -> Vec<u8> {
+    let mut out = String::from("GIMP Palette\n#\n");
+    for color in colors {
+        let [r, g, b] = to_u8(color);
+        out += &format!("{:3} {:3} {:3}\tUntitled\n", r, g, b);
+    }
+    out.into_bytes()
+}
+
+fn read_pal(data: &[u8]) -> Result<Vec<[f32; 3]>, PaletteFileError> {
+    let mut lines = lines(data);
+    let signature = lines.next().map(|(_, line)| line);
+    let version = lines.next().map(|(_, line)| line);
+    if signature.as_deref() != Some("JASC-PAL") || version.as_deref() != Some("0100") {
+        return Err(PaletteFileError::InvalidHeader);
+    }
+    let count: usize = match lines.next() {
+        Some((i, line)) => line
+            .parse()
+            .map_err(|_| PaletteFileError::InvalidLine { line: i })?,
+        None => return Err(PaletteFileError::UnexpectedEnd),
+    };
+
+    let mut colors = Vec::with_capacity(count.min(4096));
+    for (i, line) in lines.filter(|(_, line)| !line.is_empty()).take(count) {
+        let rgb = parse_rgb(&line).ok_or(PaletteFileError::InvalidLine { line: i })?;
+        colors.push(from_u8(rgb));
+    }
+    if colors.len() < count {
+        return Err(PaletteFileError::UnexpectedEnd);
+    }
+    Ok(colors)
+}
+fn write_pal(colors: &[[f32; 3]]) -> Vec<u8> {
+    let mut out = format!("JASC-PAL\r\n0100\r\n{}\r\n", colors.len());
+    for color in colors {
+        let [r, g, b] = to_u8(color);
+        out += &format!("{} {} {}\r\n", r, g, b);
+    }
+    out.into_bytes()
+}
+
+fn read_hex(data: &[u8]) -> Result<Vec<[f32; 3]>, PaletteFileError> {
+    let mut colors = Vec::new();
+    for (i, line) in lines(data) {
+        if line.is_empty() {
+            continue;
+        }
+
+        let hex = line.strip_prefix('#').unwrap_or(&line);
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(PaletteFileError::InvalidLine { line: i });
+        }
+        let rgb = u32::from_str_radix(hex, 16).unwrap();
+        let [_, r, g, b] = rgb.to_be_bytes();
+        colors.push(from_u8([r, g, b]));
+    }
+    Ok(colors)
+}
+fn write_hex(colors: &[[f32; 3]]) -> Vec<u8> {
+    let mut out = String::new();
+    for color in colors {
+        let [r, g, b] = to_u8(color);
+        out += &format!("{:02x}{:02x}{:02x}\n", r, g, b);
+    }
+    out.into_bytes()
+}
+
+const ACT_COLORS: usize = 256;
+/// The size of an ACT file without the optional color count and transparency
+/// index at the end.
+const ACT_SIZE: usize = ACT_COLORS * 3;
+
+fn read_act(data: &[u8]) -> Result<Vec<[f32; 3]>, PaletteFileError> {
+    let count = match data.len() {
+        ACT_SIZE => ACT_COLORS,
+        len if len == ACT_SIZE + 4 => {
+            let count = u16::from_be_bytes([data[ACT_SIZE], data[ACT_SIZE + 1]]) as usize;
+            count.min(ACT_COLORS)
+        }
+        len if len < ACT_SIZE => return Err(PaletteFileError::UnexpectedEnd),
+        _ => return Err(PaletteFileError::InvalidHeader),
+    };
+
+    Ok(data[..count * 3]
+        .chunks_exact(3)
+        .map(|c| from_u8([c[0], c[1], c[2]]))
+        .collect())
+}
+fn write_act(colors: &[[f32; 3]]) -> Result<Vec<u8>, PaletteFileError> {
+    if colors.len() > ACT_COLORS {
+        return Err(PaletteFileError::TooManyColors {
+            max_colors: ACT_COLORS,
+            actual_colors: colors.len(),
+        });
+    }
+
+    let mut out = Vec::with_capacity(ACT_SIZE + 4);
+    out.extend(colors.iter().flat_map(to_u8));
+    out.resize(ACT_SIZE, 0);
+    out.extend((colors.len() as u16).to_be_bytes());
+    // no transparent color
+    out.extend(u16::MAX.to_be_bytes());
+    Ok(out)
+}
+
+const ASE_SIGNATURE: &[u8; 4] = b"ASEF";
+const ASE_COLOR_ENTRY: u16 = 0x0001;
+/// The type of colors that aren't global or spot colors.
+const ASE_NORMAL_COLOR: u16 = 2;
+
+/// A cursor over big-endian binary data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], PaletteFileError> {
+        if self.data.len() < len {
+            return Err(PaletteFileError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], PaletteFileError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+    fn u16(&mut self) -> Result<u16, PaletteFileError> {
+        self.array().map(u16::from_be_bytes)
+    }
+    fn u32(&mut self) -> Result<u32, PaletteFileError> {
+        self.array().map(u32::from_be_bytes)
+    }
+    fn f32(&mut self) -> Result<f32, PaletteFileError> {
+        self.array().map(f32::from_be_bytes)
+    }
+}
+
+fn read_ase(data: &[u8]) -> Result<Vec<[f32; 3]>, PaletteFileError> {
+    let mut reader = Reader { data };
+    if &reader.array::<4>()? != ASE_SIGNATURE {
+        return Err(PaletteFileError::InvalidHeader);
+    }
+    let _version = reader.u32()?;
+    let blocks = reader.u32()?;
+
+    let mut colors = Vec::new();
+    for _ in 0..blocks {
+        let block_type = reader.u16()?;
+        let len = reader.u32()? as usize;
+        let mut block = Reader {
+            data: reader.bytes(len)?,
+        };
+        // groups don't affect the colors they contain
+        if block_type != ASE_COLOR_ENTRY {
+            continue;
+        }
+
+        let name_len = block.u16()? as usize;
+        block.bytes(name_len * 2)?;
+        let model = block.array::<4>()?;
+        let color = match &model {
+            b"RGB " => [block.f32()?, block.f32()?, block.f32()?],
+            b"CMYK" => {
+                let [c, m, y, k] = [block.f32()?, block.f32()?, block.f32()?, block.f32()?];
+                [c, m, y].map(|x| (1.0 - x) * (1.0 - k))
+            }
+            b"LAB " => {
+                // L is stored as 0..=1 instead of 0..=100
+                let lab = Vec3A::new(block.f32()? * 100.0, block.f32()?, block.f32()?);
+                linear_to_srgb_rgb(lab_to_linear(lab)).into()
+            }
+            b"Gray" => [block.f32()?; 3],
+            _ => return Err(PaletteFileError::UnsupportedColorModel { model }),
+        };
+        colors.push(color.map(|c| c.clamp(0.0, 1.0)));
+    }
+    Ok(colors)
+}
+fn write_ase(colors: &[[f32; 3]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend(ASE_SIGNATURE);
+    // version 1.0
+    out.extend(1_u16.to_be_bytes());
+    out.extend(0_u16.to_be_bytes());
+    out.extend((colors.len() as u32).to_be_bytes());
+
+    for color in colors {
+        // the name is the hex code as null-terminated UTF-16
+        let [r, g, b] = to_u8(color);
+        let name = format!("#{:02x}{:02x}{:02x}\0", r, g, b);
+        let name: Vec<u16> = name.encode_utf16().collect();
+
+        let len = 2 + name.len() * 2 + 4 + 3 * 4 + 2;
+        out.extend(ASE_COLOR_ENTRY.to_be_bytes());
+        out.extend((len as u32).to_be_bytes());
+        out.extend((name.len() as u16).to_be_bytes());
+        out.extend(name.iter().flat_map(|c| c.to_be_bytes()));
+        out.extend(b"RGB ");
+        out.extend(color.iter().flat_map(|c| c.clamp(0.0, 1.0).to_be_bytes()));
+        out.extend(ASE_NORMAL_COLOR.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use image_core::{NDimImage, Shape};
+    use test_util::data::read_flower_palette;
+
+    use super::{PaletteFileError, PaletteFormat};
+
+    const FORMATS: [PaletteFormat; 5] = [
+        PaletteFormat::Gpl,
+        PaletteFormat::Act,
+        PaletteFormat::Ase,
+        PaletteFormat::Pal,
+        PaletteFormat::Hex,
+    ];
+
+    fn read(data: &[u8], format: PaletteFormat) -> Vec<[u8; 3]> {
+        let palette = super::read_palette(data, format).unwrap();
+        assert_eq!(palette.height(), 1);
+        assert_eq!(palette.channels(), 3);
+        palette
+            .data()
+            .chunks_exact(3)
+            .map(|c| super::to_u8(&[c[0], c[1], c[2]]))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let palette: NDimImage = read_flower_palette().into();
+        let expected: Vec<f32> = palette
+            .data()
+            .iter()
+            .map(|c| (c * 255.0).round() / 255.0)
+            .collect();
+
+        for format in FORMATS {
+            let data = super::write_palette(palette.view(), format).unwrap();
+            let actual = super::read_palette(&data, format).unwrap();
+            assert_eq!(actual.shape(), palette.shape(), "{:?}", format);
+            for (a, e) in actual.data().iter().zip(&expected) {
+                assert!((a - e).abs() < 1e-6, "{:?}: {} != {}", format, a, e);
+            }
+        }
+    }
+
+    #[test]
+    fn text_formats() {
+        let expected = vec![[255, 0, 0], [0, 128, 255], [17, 34, 51]];
+
+        let gpl = "GIMP Palette\nName: Test\nColumns: 3\n#\n255   0   0\tRed\n  0 128 255\n\n 17  34  51  Some name\n";
+        assert_eq!(read(gpl.as_bytes(), PaletteFormat::Gpl), expected);
+
+        let pal = "JASC-PAL\r\n0100\r\n3\r\n255 0 0\r\n0 128 255\r\n17 34 51\r\n";
+        assert_eq!(read(pal.as_bytes(), PaletteFormat::Pal), expected);
+
+        let hex = "ff0000\n#0080FF\r\n112233\n";
+        assert_eq!(read(hex.as_bytes(), PaletteFormat::Hex), expected);
+
+        assert_eq!(
+            super::read_palette(b"GIMP Palette\n1 2\n", PaletteFormat::Gpl).err(),
+            Some(PaletteFileError::InvalidLine { line: 2 })
+        );
+        assert_eq!(
+            super::read_palette(b"JASC-PAL\n0100\n2\n1 2 3\n", PaletteFormat::Pal).err(),
+            Some(PaletteFileError::UnexpectedEnd)
+        );
+        assert_eq!(
+            super::read_palette(b"ff0000\nf00\n", PaletteFormat::Hex).err(),
+            Some(PaletteFileError::InvalidLine { line: 2 })
+        );
+        assert_eq!(
+            super::read_palette(b"GIMP Palette\n", PaletteFormat::Gpl).err(),
+            Some(PaletteFileError::NoColors)
+        );
+        assert_eq!(
+            super::read_palette(pal.as_bytes(), PaletteFormat::Gpl).err(),
+            Some(PaletteFileError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn act() {
+        let mut data = vec![0_u8; 768];
+        data[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+
+        // without count, all 256 colors are used
+        let colors = read(&data, PaletteFormat::Act);
+        assert_eq!(colors.len(), 256);
+        assert_eq!(colors[..2], [[1, 2, 3], [4, 5, 6]]);
+
+        data.extend([0, 2, 0xFF, 0xFF]);
+        assert_eq!(read(&data, PaletteFormat::Act), [[1, 2, 3], [4, 5, 6]]);
+
+        let too_many = NDimImage::zeros(Shape::new(257, 1, 3));
+        assert_eq!(
+            super::write_palette(too_many.view(), PaletteFormat::Act),
+            Err(PaletteFileError::TooManyColors {
+                max_colors: 256,
+                actual_colors: 257
+            })
+        );
+    }
+
+    #[test]
+    fn ase() {
+        fn entry(model: &[u8; 4], values: &[f32]) -> Vec<u8> {
+            let mut body = vec![0, 2, 0, b'a', 0, 0];
+            body.extend(model);
+            body.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+            body.extend([0, 2]);
+
+            let mut block = vec![0, 1];
+            block.extend((body.len() as u32).to_be_bytes());
+            block.extend(body);
+            block
+        }
+
+        let mut data = b"ASEF\0\x01\0\0\0\0\0\x06".to_vec();
+        // group start with the name "g"
+        data.extend([0xC0, 0x01, 0, 0, 0, 6, 0, 2, 0, b'g', 0, 0]);
+        data.extend(entry(b"RGB ", &[1.0, 0.5, 0.0]));
+        data.extend(entry(b"CMYK", &[1.0, 0.0, 0.0, 0.5]));
+        data.extend(entry(b"Gray", &[0.2]));
+        data.extend(entry(b"LAB ", &[1.0, 0.0, 0.0]));
+        // group end
+        data.extend([0xC0, 0x02, 0, 0, 0, 0]);
+
+        assert_eq!(
+            read(&data, PaletteFormat::Ase),
+            [[255, 128, 0], [0, 128, 128], [51, 51, 51], [255, 255, 255]]
+        );
+
+        let truncated = &data[..data.len() - 10];
+        assert_eq!(
+            super::read_palette(truncated, PaletteFormat::Ase).err(),
+            Some(PaletteFileError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn gray_palettes() {
+        let gray = NDimImage::new(Shape::new(2, 1, 1), vec![0.0, 0.5]);
+        for format in FORMATS {
+            let data = super::write_palette(gray.view(), format).unwrap();
+            assert_eq!(read(&data, format), [[0, 0, 0], [128, 128, 128]]);
+        }
+    }
+}
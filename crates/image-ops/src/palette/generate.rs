@@ -0,0 +1,652 @@
+use std::ops::Range;
+
+use ahash::AHashMap;
+use glam::Vec3A;
+use image_core::{
+    util::{slice_as_chunks, vec_into_flattened},
+    NDimImage, NDimView, Shape,
+};
+use rayon::prelude::*;
+use rstar::{primitives::GeomWithData, RTree};
+
+use super::{extract_unique_const, ExtractionError};
+use crate::color::{linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear};
+
+/// An algorithm that reduces the colors of an image to a palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteAlgorithm {
+    /// Heckbert's median cut. The box of colors with the largest range is
+    /// repeatedly split at the median of its longest channel.
+    MedianCut,
+    /// Octree quantization. Colors are grouped by their most significant bits,
+    /// and the least common groups are merged until few enough are left.
+    Octree,
+    /// Wu's greedy orthogonal bipartition. The box of colors with the largest
+    /// variance is repeatedly split such that the variance of the two halves
+    /// is minimal.
+    Wu,
+}
+
+/// A color and the number of pixels with that color.
+type Weighted<const N: usize> = ([f32; N], f32);
+
+/// Generates a palette of at most `max_colors` colors that represents the
+/// given colors well.
+///
+/// If there are at most `max_colors` unique colors, the palette contains
+/// exactly those colors. Otherwise, the palette is generated with the given
+/// algorithm and then refined with `kmeans_iterations` iterations of k-means
+/// clustering in Oklab (for 3 or 4 channels) or Oklab lightness (for 1 or 2
+/// channels). The 4th and 2nd channel are assumed to be alpha.
+///
+/// The palette is sorted the same way as [`extract_unique_const`].
+pub fn generate_palette<const N: usize>(
+    src: impl IntoIterator<Item = [f32; N]>,
+    max_colors: usize,
+    algorithm: PaletteAlgorithm,
+    kmeans_iterations: usize,
+) -> Vec<[f32; N]> {
+    assert_ne!(N, 0);
+
+    let colors = histogram(src);
+    let palette = if colors.len() <= max_colors {
+        colors.into_iter().map(|(c, _)| c).collect()
+    } else if max_colors == 0 {
+        Vec::new()
+    } else {
+        let palette = match algorithm {
+            PaletteAlgorithm::MedianCut => median_cut(colors.clone(), max_colors),
+            PaletteAlgorithm::Octree => octree(&colors, max_colors),
+            PaletteAlgorithm::Wu => wu(&colors, max_colors),
+        };
+        kmeans(&colors, palette, kmeans_iterations)
+    };
+
+    extract_unique_const(palette, usize::MAX).expect("there is no limit on the number of colors")
+}
+
+pub fn generate_palette_ndim(
+    src: NDimView,
+    max_colors: usize,
+    algorithm: PaletteAlgorithm,
+    kmeans_iterations: usize,
+) -> Result<NDimImage, ExtractionError> {
+    fn generate<const N: usize>(
+        src: NDimView,
+        max_colors: usize,
+        algorithm: PaletteAlgorithm,
+        kmeans_iterations: usize,
+    ) -> NDimImage {
+        let (pixels, rest) = slice_as_chunks::<f32, N>(src.data());
+        assert!(rest.is_empty());
+
+        let colors = generate_palette(
+            pixels.iter().copied(),
+            max_colors,
+            algorithm,
+            kmeans_iterations,
+        );
+        let shape = Shape::new(colors.len(), 1, N);
+        NDimImage::new(shape, vec_into_flattened(colors))
+    }
+
+    match src.channels() {
+        1 => Ok(generate::<1>(src, max_colors, algorithm, kmeans_iterations)),
+        2 => Ok(generate::<2>(src, max_colors, algorithm, kmeans_iterations)),
+        3 => Ok(generate::<3>(src, max_colors, algorithm, kmeans_iterations)),
+        4 => Ok(generate::<4>(src, max_colors, algorithm, kmeans_iterations)),
+        _ => Err(ExtractionError::UnsupportedChannels {
+            channels: src.channels(),
+        }),
+    }
+}
+
+/// Returns all unique colors and how often they occur.
+fn histogram<const N: usize>(src: impl IntoIterator<Item = [f32; N]>) -> Vec<Weighted<N>> {
+    let mut counts: AHashMap<[u32; N], u32> = AHashMap::new();
+    for color in src {
+        *counts.entry(color.map(f32::to_bits)).or_insert(0) += 1;
+    }
+
+    // the iteration order of the map is random, but the algorithms shouldn't be
+    let mut colors: Vec<([u32; N], u32)> = counts.into_iter().collect();
+    colors.sort_unstable_by_key(|(c, _)| *c);
+    colors
+        .into_iter()
+        .map(|(c, count)| (c.map(f32::from_bits), count as f32))
+        .collect()
+}
+
+fn weighted_mean<const N: usize>(colors: &[Weighted<N>]) -> [f32; N] {
+    let mut sum = [0.0_f64; N];
+    let mut weight = 0.0_f64;
+    for (c, w) in colors {
+        for i in 0..N {
+            sum[i] += c[i] as f64 * *w as f64;
+        }
+        weight += *w as f64;
+    }
+    sum.map(|s| (s / weight) as f32)
+}
+
+fn median_cut<const N: usize>(mut colors: Vec<Weighted<N>>, max_colors: usize) -> Vec<[f32; N]> {
+    // each box is a range of colors
+    let mut boxes: Vec<Range<usize>> = std::iter::once(0..colors.len()).collect();
+    while boxes.len() < max_colors {
+        let longest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .flat_map(|(i, b)| {
+                let colors = &colors[b.clone()];
+                (0..N).map(move |channel| {
+                    let (min, max) = colors.iter().fold((f32::MAX, f32::MIN), |(min, max), c| {
+                        (min.min(c.0[channel]), max.max(c.0[channel]))
+                    });
+                    (i, channel, max - min)
+                })
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+        let (i, channel) = match longest {
+            Some((i, channel, _)) => (i, channel),
+            None => break,
+        };
+
+        let b = boxes[i].clone();
+        let colors = &mut colors[b.clone()];
+        colors.sort_unstable_by(|a, b| a.0[channel].total_cmp(&b.0[channel]));
+
+        // split at the weighted median
+        let half = colors.iter().map(|c| c.1).sum::<f32>() / 2.0;
+        let mut acc = 0.0;
+        let median = colors
+            .iter()
+            .position(|c| {
+                acc += c.1;
+                acc >= half
+            })
+            .unwrap_or(0);
+        let split = b.start + (median + 1).clamp(1, colors.len() - 1);
+
+        boxes[i] = b.start..split;
+        boxes.push(split..b.end);
+    }
+
+    boxes
+        .into_iter()
+        .map(|b| weighted_mean(&colors[b]))
+        .collect()
+}
+
+/// The number of bits of each channel used by the octree.
+const OCTREE_DEPTH: usize = 8;
+
+struct OctreeNode<const N: usize> {
+    /// The indexes of the children. 0 means no child, since the root can't be
+    /// a child. Only the first `2^N` are used.
+    children: [usize; 16],
+    sum: [f64; N],
+    weight: f64,
+    leaf: bool,
+}
+
+fn octree<const N: usize>(colors: &[Weighted<N>], max_colors: usize) -> Vec<[f32; N]> {
+    assert!(N <= 4);
+
+    let new_node = || OctreeNode {
+        children: [0; 16],
+        sum: [0.0; N],
+        weight: 0.0,
+        leaf: false,
+    };
+    let mut nodes = vec![new_node()];
+    // the nodes at each depth
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH + 1];
+    levels[0].push(0);
+
+    for (color, weight) in colors {
+        let bits = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let mut node = 0;
+        for depth in 0..=OCTREE_DEPTH {
+            let n = &mut nodes[node];
+            for (sum, c) in n.sum.iter_mut().zip(color) {
+                *sum += *c as f64 * *weight as f64;
+            }
+            n.weight += *weight as f64;
+            if depth == OCTREE_DEPTH {
+                n.leaf = true;
+                break;
+            }
+
+            let shift = OCTREE_DEPTH - 1 - depth;
+            let child = (0..N).fold(0, |acc, i| acc | ((bits[i] as usize >> shift) & 1) << i);
+            node = match nodes[node].children[child] {
+                0 => {
+                    nodes.push(new_node());
+                    let index = nodes.len() - 1;
+                    nodes[node].children[child] = index;
+                    levels[depth + 1].push(index);
+                    index
+                }
+                index => index,
+            };
+        }
+    }
+
+    // Merge the children of the least common nodes, deepest level first. Since
+    // all deeper levels are merged first, all children are leaves.
+    let mut leaves = levels[OCTREE_DEPTH].len();
+    'reduce: for depth in (0..OCTREE_DEPTH).rev() {
+        let mut level = std::mem::take(&mut levels[depth]);
+        level.sort_by(|a, b| nodes[*a].weight.total_cmp(&nodes[*b].weight));
+        for node in level {
+            if leaves <= max_colors {
+                break 'reduce;
+            }
+            let children = nodes[node].children.iter().filter(|&&c| c != 0).count();
+            nodes[node].leaf = true;
+            leaves -= children - 1;
+        }
+    }
+
+    let mut palette = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(node) = stack.pop() {
+        let n = &nodes[node];
+        if n.leaf {
+            palette.push(n.sum.map(|s| (s / n.weight) as f32));
+        } else {
+            stack.extend(n.children.iter().filter(|&&c| c != 0));
+        }
+    }
+    palette
+}
+
+/// The weight, weighted sum, and weighted sum of squares of colors.
+#[derive(Clone, Copy)]
+struct Moments<const N: usize> {
+    weight: f64,
+    sum: [f64; N],
+    squares: f64,
+}
+
+impl<const N: usize> Moments<N> {
+    const ZERO: Self = Self {
+        weight: 0.0,
+        sum: [0.0; N],
+        squares: 0.0,
+    };
+
+    fn add(&mut self, other: &Self, sign: f64) {
+        self.weight += other.weight * sign;
+        for i in 0..N {
+            self.sum[i] += other.sum[i] * sign;
+        }
+        self.squares += other.squares * sign;
+    }
+    /// `|sum|² / weight`
+    fn norm(&self) -> f64 {
+        self.sum.iter().map(|s| s * s).sum::<f64>() / self.weight
+    }
+    fn variance(&self) -> f64 {
+        if self.weight > 0.0 {
+            self.squares - self.norm()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A box of histogram cells. `lo` is exclusive and `hi` is inclusive.
+#[derive(Clone, Copy)]
+struct WuBox<const N: usize> {
+    lo: [usize; N],
+    hi: [usize; N],
+}
+
+fn wu<const N: usize>(colors: &[Weighted<N>], max_colors: usize) -> Vec<[f32; N]> {
+    // keep the histogram small for many channels
+    let bins: usize = match N {
+        1 => 256,
+        2 => 64,
+        3 => 32,
+        _ => 16,
+    };
+    // cell 0 of each channel is empty so boxes can be exclusive at the bottom
+    let side = bins + 1;
+    let strides: [usize; N] = std::array::from_fn(|i| side.pow(i as u32));
+    let mut cells = vec![Moments::<N>::ZERO; side.pow(N as u32)];
+
+    for (color, weight) in colors {
+        let weight = *weight as f64;
+        let index: usize = (0..N)
+            .map(|i| {
+                let bin = (color[i].clamp(0.0, 1.0) * bins as f32) as usize;
+                (bin.min(bins - 1) + 1) * strides[i]
+            })
+            .sum();
+        let cell = &mut cells[index];
+        cell.weight += weight;
+        for (sum, c) in cell.sum.iter_mut().zip(color) {
+            *sum += *c as f64 * weight;
+            cell.squares += (c * c) as f64 * weight;
+        }
+    }
+
+    // cumulative moments, so each cell contains the moments of all cells below it
+    for &stride in &strides {
+        for index in 0..cells.len() {
+            if (index / stride) % side > 0 {
+                let below = cells[index - stride];
+                cells[index].add(&below, 1.0);
+            }
+        }
+    }
+
+    let moments = |b: &WuBox<N>| {
+        // inclusion-exclusion over the corners of the box
+        let mut m = Moments::ZERO;
+        for corner in 0..1_usize << N {
+            let mut index = 0;
+            let mut sign = 1.0;
+            for (i, stride) in strides.iter().enumerate() {
+                if corner & (1 << i) != 0 {
+                    index += b.hi[i] * stride;
+                } else {
+                    index += b.lo[i] * stride;
+                    sign = -sign;
+                }
+            }
+            m.add(&cells[index], sign);
+        }
+        m
+    };
+    // returns the channel and position of the best split
+    let best_split = |b: &WuBox<N>| {
+        let whole = moments(b);
+        let mut best: Option<(usize, usize, f64)> = None;
+        for i in 0..N {
+            for cut in b.lo[i] + 1..b.hi[i] {
+                let mut lower = *b;
+                lower.hi[i] = cut;
+                let lower = moments(&lower);
+                let mut upper = whole;
+                upper.add(&lower, -1.0);
+                if lower.weight <= 0.0 || upper.weight <= 0.0 {
+                    continue;
+                }
+
+                // maximizing this minimizes the sum of the variances of both halves
+                let score = lower.norm() + upper.norm();
+                if best.map_or(true, |(_, _, s)| score > s) {
+                    best = Some((i, cut, score));
+                }
+            }
+        }
+        best.map(|(i, cut, _)| (i, cut))
+    };
+
+    let full = WuBox {
+        lo: [0; N],
+        hi: [bins; N],
+    };
+    let mut boxes = vec![full];
+    let mut variances = vec![moments(&full).variance()];
+    while boxes.len() < max_colors {
+        let next = (0..boxes.len())
+            .filter(|&i| variances[i] > 0.0)
+            .max_by(|&a, &b| variances[a].total_cmp(&variances[b]));
+        let i = match next {
+            Some(i) => i,
+            None => break,
+        };
+
+        match best_split(&boxes[i]) {
+            Some((channel, cut)) => {
+                let mut upper = boxes[i];
+                upper.lo[channel] = cut;
+                boxes[i].hi[channel] = cut;
+                variances[i] = moments(&boxes[i]).variance();
+                boxes.push(upper);
+                variances.push(moments(&upper).variance());
+            }
+            // all colors of the box are in the same cell
+            None => variances[i] = 0.0,
+        }
+    }
+
+    boxes
+        .iter()
+        .map(moments)
+        .filter(|m| m.weight > 0.0)
+        .map(|m| m.sum.map(|s| (s / m.weight) as f32))
+        .collect()
+}
+
+/// Converts a color into the space used by k-means.
+fn to_perceptual<const N: usize>(mut color: [f32; N]) -> [f32; N] {
+    if N >= 3 {
+        let linear = Vec3A::new(color[0], color[1], color[2])
+            .to_array()
+            .map(srgb_to_linear);
+        let lab = linear_to_oklab(Vec3A::from(linear));
+        color[..3].copy_from_slice(&lab.to_array());
+    } else {
+        color[0] = linear_to_oklab(Vec3A::splat(srgb_to_linear(color[0]))).x;
+    }
+    color
+}
+fn from_perceptual<const N: usize>(mut color: [f32; N]) -> [f32; N] {
+    if N >= 3 {
+        let linear = oklab_to_linear(Vec3A::new(color[0], color[1], color[2]));
+        color[..3].copy_from_slice(&linear.to_array().map(linear_to_srgb));
+    } else {
+        color[0] = linear_to_srgb(oklab_to_linear(Vec3A::new(color[0], 0.0, 0.0)).x);
+    }
+    color.map(|c| c.clamp(0.0, 1.0))
+}
+
+/// Moves each palette color to the mean of the colors closest to it
+/// (Lloyd's algorithm).
+fn kmeans<const N: usize>(
+    colors: &[Weighted<N>],
+    palette: Vec<[f32; N]>,
+    iterations: usize,
+) -> Vec<[f32; N]> {
+    if iterations == 0 {
+        return palette;
+    }
+
+    let points: Vec<[f32; N]> = colors.par_iter().map(|c| to_perceptual(c.0)).collect();
+    let mut centroids: Vec<[f32; N]> = palette.into_iter().map(to_perceptual).collect();
+
+    for _ in 0..iterations {
+        let nearest = nearest_centroids(&points, &centroids);
+        let mut clusters = vec![([0.0_f64; N], 0.0_f64); centroids.len()];
+        for ((point, (_, weight)), cluster) in points.iter().zip(colors).zip(nearest) {
+            let (sum, total) = &mut clusters[cluster];
+            for i in 0..N {
+                sum[i] += point[i] as f64 * *weight as f64;
+            }
+            *total += *weight as f64;
+        }
+
+        let mut changed = false;
+        for (centroid, (sum, total)) in centroids.iter_mut().zip(clusters) {
+            // empty clusters keep their color
+            if total > 0.0 {
+                let mean = sum.map(|s| (s / total) as f32);
+                changed |= mean != *centroid;
+                *centroid = mean;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    centroids.into_iter().map(from_perceptual).collect()
+}
+
+/// Returns the index of the nearest centroid of each point.
+fn nearest_centroids<const N: usize>(points: &[[f32; N]], centroids: &[[f32; N]]) -> Vec<usize> {
+    // linear search is really fast for small palettes, and R-trees only
+    // support points with at least 2 dimensions
+    if centroids.len() < 300 || N < 2 {
+        let distance_2 = |a: &[f32; N], b: &[f32; N]| -> f32 {
+            a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+        };
+        return points
+            .par_iter()
+            .map(|p| {
+                let mut nearest = 0;
+                let mut nearest_dist = distance_2(&centroids[0], p);
+                for (i, c) in centroids.iter().enumerate().skip(1) {
+                    let dist = distance_2(c, p);
+                    if dist < nearest_dist {
+                        nearest = i;
+                        nearest_dist = dist;
+                    }
+                }
+                nearest
+            })
+            .collect();
+    }
+
+    let tree = RTree::bulk_load(
+        centroids
+            .iter()
+            .enumerate()
+            .map(|(i, c)| GeomWithData::new(*c, i))
+            .collect(),
+    );
+    points
+        .par_iter()
+        .map(|p| tree.nearest_neighbor(p).unwrap().data)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use image_core::{Image, NDimImage, Shape};
+    use test_util::{
+        data::{read_flower_palette, read_portrait},
+        snap::ImageSnapshot,
+    };
+
+    use super::PaletteAlgorithm;
+    use crate::palette::extract_unique_ndim;
+
+    const ALGORITHMS: [PaletteAlgorithm; 3] = [
+        PaletteAlgorithm::MedianCut,
+        PaletteAlgorithm::Octree,
+        PaletteAlgorithm::Wu,
+    ];
+
+    fn generate(img: &Image<Vec3A>, max_colors: usize, algorithm: PaletteAlgorithm) -> Vec<Vec3A> {
+        let colors = img.data().iter().map(|c| c.to_array());
+        super::generate_palette(colors, max_colors, algorithm, 0)
+            .into_iter()
+            .map(Vec3A::from)
+            .collect()
+    }
+
+    fn apply(img: &Image<Vec3A>, palette: &[Vec3A]) -> Image<Vec3A> {
+        img.map(|c| {
+            *palette
+                .iter()
+                .min_by(|a, b| a.distance_squared(*c).total_cmp(&b.distance_squared(*c)))
+                .unwrap()
+        })
+    }
+
+    fn mean_error(a: &Image<Vec3A>, b: &Image<Vec3A>) -> f32 {
+        let sum: f32 = a
+            .data()
+            .iter()
+            .zip(b.data())
+            .map(|(a, b)| a.distance_squared(*b))
+            .sum();
+        sum / a.data().len() as f32
+    }
+
+    #[test]
+    fn exact_colors() {
+        let original: NDimImage = read_flower_palette().into();
+        let unique = extract_unique_ndim(original.view(), 256).unwrap();
+
+        for algorithm in ALGORITHMS {
+            let palette =
+                super::generate_palette_ndim(original.view(), unique.width(), algorithm, 5)
+                    .unwrap();
+            assert_eq!(palette.shape(), unique.shape());
+            assert_eq!(palette.data(), unique.data());
+        }
+    }
+
+    #[test]
+    fn algorithms() {
+        let original = read_portrait();
+
+        for algorithm in ALGORITHMS {
+            let palette = generate(&original, 16, algorithm);
+            assert!(
+                palette.len() <= 16 && palette.len() >= 12,
+                "{:?}",
+                algorithm
+            );
+
+            let result = apply(&original, &palette);
+            let error = mean_error(&original, &result);
+            assert!(error < 0.015, "{:?}: {}", algorithm, error);
+            result.snapshot(&format!("palette_generate_{:?}", algorithm).to_lowercase());
+        }
+    }
+
+    #[test]
+    fn kmeans() {
+        let original = read_portrait();
+        let colors = || original.data().iter().map(|c| c.to_array());
+
+        for algorithm in ALGORITHMS {
+            let palette = super::generate_palette(colors(), 8, algorithm, 0);
+            let refined = super::generate_palette(colors(), 8, algorithm, 10);
+            assert!(refined.len() <= 8);
+
+            // k-means minimizes the error in Oklab
+            let error = |palette: &[[f32; 3]]| {
+                let oklab: Vec<[f32; 3]> =
+                    palette.iter().map(|c| super::to_perceptual(*c)).collect();
+                let distance = |a: &[f32; 3], b: &[f32; 3]| -> f32 {
+                    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+                };
+                colors()
+                    .map(|c| {
+                        let c = super::to_perceptual(c);
+                        oklab
+                            .iter()
+                            .map(|p| distance(&c, p))
+                            .fold(f32::MAX, f32::min)
+                    })
+                    .sum::<f32>()
+            };
+            assert!(error(&refined) <= error(&palette), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn kmeans_grayscale() {
+        let colors = || (0..100).map(|i| [i as f32 / 99.0]);
+
+        for algorithm in ALGORITHMS {
+            let palette = super::generate_palette(colors(), 4, algorithm, 3);
+            assert!(!palette.is_empty() && palette.len() <= 4, "{:?}", algorithm);
+
+            let gray = NDimImage::new(Shape::new(100, 1, 1), colors().flatten().collect());
+            let palette = super::generate_palette_ndim(gray.view(), 4, algorithm, 3).unwrap();
+            assert_eq!(palette.channels(), 1);
+            assert!(palette.width() <= 4, "{:?}", algorithm);
+        }
+    }
+}
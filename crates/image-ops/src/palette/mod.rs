@@ -1,9 +1,15 @@
 use ahash::AHashSet;
 use image_core::{
     util::{slice_as_chunks, vec_into_flattened},
-    NDimImage, NDimView, Shape,
+    NDimImage, NDimView, Sample, Shape,
 };
 
+mod file;
+mod generate;
+
+pub use file::*;
+pub use generate::*;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExtractionError {
     TooManyColors {
@@ -15,17 +21,17 @@ pub enum ExtractionError {
     },
 }
 
-fn sort_colors<const N: usize>(colors: &mut [[f32; N]], key_fn: impl Fn(&[f32; N]) -> f32) {
-    colors.sort_unstable_by(|a, b| key_fn(a).total_cmp(&key_fn(b)))
+fn sort_colors<T: Sample, const N: usize>(colors: &mut [[T; N]], key_fn: impl Fn([f32; N]) -> f32) {
+    colors.sort_unstable_by(|a, b| key_fn(a.map(T::to_f32)).total_cmp(&key_fn(b.map(T::to_f32))))
 }
 
-pub fn extract_unique_const<const N: usize>(
-    src: impl IntoIterator<Item = [f32; N]>,
+pub fn extract_unique_const<T: Sample, const N: usize>(
+    src: impl IntoIterator<Item = [T; N]>,
     max_colors: usize,
-) -> Result<Vec<[f32; N]>, ExtractionError> {
+) -> Result<Vec<[T; N]>, ExtractionError> {
     assert_ne!(N, 0);
 
-    let set: AHashSet<[u32; N]> = src.into_iter().map(|p| p.map(f32::to_bits)).collect();
+    let set: AHashSet<[T::Bits; N]> = src.into_iter().map(|p| p.map(T::to_bits)).collect();
 
     if set.len() > max_colors {
         return Err(ExtractionError::TooManyColors {
@@ -34,7 +40,7 @@ pub fn extract_unique_const<const N: usize>(
         });
     }
 
-    let mut vec: Vec<[f32; N]> = set.into_iter().map(|p| p.map(f32::from_bits)).collect();
+    let mut vec: Vec<[T; N]> = set.into_iter().map(|p| p.map(T::from_bits)).collect();
 
     fn luminance(r: f32, g: f32, b: f32) -> f32 {
         // Since the color values are likely sRGB, we will approximate 2.2 gamma by squaring the values.
@@ -54,10 +60,10 @@ pub fn extract_unique_const<const N: usize>(
     Ok(vec)
 }
 
-pub fn extract_unique_ndim_const<const N: usize>(
-    src: NDimView,
+pub fn extract_unique_ndim_const<T: Sample, const N: usize>(
+    src: NDimView<T>,
     max_colors: usize,
-) -> Result<Vec<[f32; N]>, ExtractionError> {
+) -> Result<Vec<[T; N]>, ExtractionError> {
     assert_ne!(N, 0);
 
     if src.channels() != N {
@@ -66,18 +72,21 @@ pub fn extract_unique_ndim_const<const N: usize>(
         });
     }
 
-    let (pixels, rest) = slice_as_chunks::<f32, N>(src.data());
+    let (pixels, rest) = slice_as_chunks::<T, N>(src.data());
     assert!(rest.is_empty());
 
     extract_unique_const(pixels.iter().copied(), max_colors)
 }
 
-pub fn extract_unique_ndim(src: NDimView, max_colors: usize) -> Result<NDimImage, ExtractionError> {
-    fn extract<const N: usize>(
-        src: NDimView,
+pub fn extract_unique_ndim<T: Sample>(
+    src: NDimView<T>,
+    max_colors: usize,
+) -> Result<NDimImage<T>, ExtractionError> {
+    fn extract<T: Sample, const N: usize>(
+        src: NDimView<T>,
         max_colors: usize,
-    ) -> Result<NDimImage, ExtractionError> {
-        let colors: Vec<[f32; N]> = extract_unique_ndim_const::<N>(src, max_colors)?;
+    ) -> Result<NDimImage<T>, ExtractionError> {
+        let colors: Vec<[T; N]> = extract_unique_ndim_const::<T, N>(src, max_colors)?;
 
         let shape = Shape::new(colors.len(), 1, N);
         let data = vec_into_flattened(colors);
@@ -85,10 +94,10 @@ pub fn extract_unique_ndim(src: NDimView, max_colors: usize) -> Result<NDimImage
     }
 
     match src.channels() {
-        1 => extract::<1>(src, max_colors),
-        2 => extract::<2>(src, max_colors),
-        3 => extract::<3>(src, max_colors),
-        4 => extract::<4>(src, max_colors),
+        1 => extract::<T, 1>(src, max_colors),
+        2 => extract::<T, 2>(src, max_colors),
+        3 => extract::<T, 3>(src, max_colors),
+        4 => extract::<T, 4>(src, max_colors),
         _ => Err(ExtractionError::UnsupportedChannels {
             channels: src.channels(),
         }),
@@ -107,4 +116,15 @@ mod tests {
         let palette = extract_unique_ndim(original.view(), 256).unwrap();
         assert_eq!(palette.shape(), original.shape());
     }
+
+    #[test]
+    fn integer_samples() {
+        let original: NDimImage = read_flower_palette().into();
+        let original_u8: NDimImage<u8> = original.convert_lossless().unwrap();
+
+        let palette = extract_unique_ndim(original.view(), 256).unwrap();
+        let palette_u8 = extract_unique_ndim(original_u8.view(), 256).unwrap();
+        assert_eq!(palette_u8.shape(), palette.shape());
+        assert_eq!(palette_u8.convert::<f32>().data(), palette.data());
+    }
 }
@@ -154,4 +154,18 @@ mod tests {
         super::eagle_2x(&original).snapshot("px_up_eagle_2x");
         super::eagle_3x(&original).snapshot("px_up_eagle_3x");
     }
+
+    #[test]
+    fn eagle_integer() {
+        use image_core::Sample;
+
+        let original = read_nes_smb();
+        let to_u8 = |p: &glam::Vec3A| p.to_array().map(u8::from_f32);
+        let original_u8 = original.map(to_u8);
+
+        let expected = super::eagle_2x(&original).map(to_u8);
+        assert_eq!(super::eagle_2x(&original_u8).data(), expected.data());
+        let expected = super::eagle_3x(&original).map(to_u8);
+        assert_eq!(super::eagle_3x(&original_u8).data(), expected.data());
+    }
 }
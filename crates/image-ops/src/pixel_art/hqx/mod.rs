@@ -7,4 +7,5 @@ mod yuv;
 pub use hq2x::hq2x;
 pub use hq3x::hq3x;
 pub use hq4x::hq4x;
+pub(crate) use yuv::luma;
 pub use yuv::IntoYuv;
@@ -42,6 +42,12 @@ fn rgb_to_yuv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
     (y, u, v)
 }
 
+/// Returns the luma (the Y of YUV) of the given RGB color.
+#[inline]
+pub(crate) fn luma(rgb: Vec3A) -> f32 {
+    rgb_to_yuv(rgb.x, rgb.y, rgb.z).0
+}
+
 pub trait IntoYuv {
     type Output: Copy + PartialEq;
 
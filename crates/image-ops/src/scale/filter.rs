@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Filter {
     Nearest,
     Box,
@@ -11,7 +13,93 @@ pub enum Filter {
     Hann,
     Lanczos3,
     Lagrange,
-    Gauss,
+    /// A cubic filter with the given B and C parameters (Mitchell-Netravali).
+    ///
+    /// E.g. `b = 1/3, c = 1/3` is [`Filter::CubicMitchell`] and `b = 0, c = 0.5`
+    /// is [`Filter::CubicCatrom`].
+    CubicBC {
+        b: f32,
+        c: f32,
+    },
+    /// A sinc filter windowed by a sinc with the given number of lobes.
+    ///
+    /// `lobes = 3` is [`Filter::Lanczos3`].
+    Lanczos {
+        lobes: u32,
+    },
+    /// A Gaussian with the given standard deviation.
+    ///
+    /// The support is `6 * sigma`. `sigma = 0.5` is a good default.
+    Gauss {
+        sigma: f32,
+    },
+    /// A sinc filter windowed by a Kaiser window with the given number of
+    /// lobes and shape parameter beta.
+    Kaiser {
+        lobes: u32,
+        beta: f32,
+    },
+    /// A sinc filter windowed by a Blackman window with the given number of
+    /// lobes.
+    Blackman {
+        lobes: u32,
+    },
+    /// A jinc filter windowed by a jinc with the given number of lobes.
+    ///
+    /// Jinc is the radially-symmetric 2D analog of sinc and the typical kernel
+    /// for EWA (elliptical weighted average) resampling. The support ends at
+    /// the last zero of the given lobe.
+    ///
+    /// Only [`warp`](crate::warp) evaluates this filter radially. Separable
+    /// resampling like [`scale`](super::scale) applies the 1D kernel along
+    /// each axis instead, so the result is a separable approximation of a
+    /// jinc-windowed jinc, not true EWA resampling.
+    Jinc {
+        lobes: u32,
+    },
+    /// A user-defined kernel.
+    Custom(CustomFilter),
+}
+
+/// A user-defined filter kernel.
+///
+/// The kernel is only ever evaluated in the range `-support..=support`.
+#[derive(Clone)]
+pub struct CustomFilter {
+    kernel: Arc<dyn Fn(f32) -> f32 + Send + Sync>,
+    support: f32,
+}
+
+impl CustomFilter {
+    pub fn new(support: f32, kernel: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+        assert!(support >= 0.0, "Support must be non-negative");
+        Self {
+            kernel: Arc::new(kernel),
+            support,
+        }
+    }
+
+    pub fn support(&self) -> f32 {
+        self.support
+    }
+
+    pub fn kernel(&self, x: f32) -> f32 {
+        (self.kernel)(x)
+    }
+}
+
+impl std::fmt::Debug for CustomFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomFilter")
+            .field("support", &self.support)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.kernel, &other.kernel) && self.support == other.support
+    }
 }
 
 #[inline]
@@ -65,49 +153,211 @@ fn lagrange(x: f32, support: f32) -> f32 {
     value
 }
 
-impl From<Filter> for resize::Type {
-    fn from(filter: Filter) -> Self {
-        match filter {
-            Filter::Nearest => resize::Type::Point,
+#[inline]
+fn gaussian(x: f32, r: f32) -> f32 {
+    ((2.0 * std::f32::consts::PI).sqrt() * r).recip() * (-x.powi(2) / (2.0 * r.powi(2))).exp()
+}
+
+#[inline]
+fn lanczos(taps: f32, x: f32) -> f32 {
+    if x.abs() < taps {
+        let pi = std::f32::consts::PI;
+        sinc(x * pi) * sinc(x / taps * pi)
+    } else {
+        0.0
+    }
+}
+
+/// Evaluates the given window function at `x / radius`. The window functions
+/// are defined on -1..=1 and are 1 at 0.
+#[inline]
+fn windowed_sinc(x: f32, radius: f32, window: impl Fn(f32) -> f32) -> f32 {
+    if x.abs() < radius {
+        sinc(x * std::f32::consts::PI) * window(x / radius)
+    } else {
+        0.0
+    }
+}
+
+/// The zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let q = x * x / 4.0;
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= q / (k * k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// The first-order Bessel function of the first kind.
+fn bessel_j1(x: f64) -> f64 {
+    // Taken from Numerical Recipes in C, 2nd edition, section 6.5
+    let ax = x.abs();
+    if ax < 8.0 {
+        let y = x * x;
+        let p = x
+            * (72362614232.0
+                + y * (-7895059235.0
+                    + y * (242396853.1
+                        + y * (-2972611.439 + y * (15704.48260 + y * (-30.16036606))))));
+        let q = 144725228442.0
+            + y * (2300535178.0 + y * (18583304.74 + y * (99447.43394 + y * (376.9991397 + y))));
+        p / q
+    } else {
+        let z = 8.0 / ax;
+        let y = z * z;
+        let xx = ax - 2.356194491;
+        let p = 1.0
+            + y * (0.183105e-2
+                + y * (-0.3516396496e-4 + y * (0.2457520174e-5 + y * (-0.240337019e-6))));
+        let q = 0.04687499995
+            + y * (-0.2002690873e-3
+                + y * (0.8449199096e-5 + y * (-0.88228987e-6 + y * 0.105787412e-6)));
+        let ans = (std::f64::consts::FRAC_2_PI / ax).sqrt() * (xx.cos() * p - z * xx.sin() * q);
+        if x < 0.0 {
+            -ans
+        } else {
+            ans
+        }
+    }
+}
+
+/// `2 J1(pi x) / (pi x)`, normalized to be 1 at 0.
+fn jinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = f64::from(x) * std::f64::consts::PI;
+        (2.0 * bessel_j1(x) / x) as f32
+    }
+}
+
+/// Returns the `n`-th positive zero of [`jinc`] (1-based).
+fn jinc_zero(n: u32) -> f32 {
+    // the zeros of J1 divided by pi
+    const ZEROS: [f64; 5] = [
+        1.2196698912665045,
+        2.2331305943815286,
+        3.238315484166236,
+        4.24106286379607,
+        5.2427643768701817,
+    ];
+    match ZEROS.get(n as usize - 1) {
+        Some(&zero) => zero as f32,
+        None => {
+            // McMahon's asymptotic expansion is accurate enough from here on
+            let beta = (n as f64 + 0.25) * std::f64::consts::PI;
+            ((beta - 3.0 / (8.0 * beta)) / std::f64::consts::PI) as f32
+        }
+    }
+}
+
+impl Filter {
+    /// The radius of the filter kernel in source pixels (before scaling).
+    ///
+    /// The kernel is zero for all `x` with `|x| > support`.
+    pub fn support(&self) -> f32 {
+        match *self {
+            Filter::Nearest => 0.0,
+            Filter::Box | Filter::Linear | Filter::Hermite | Filter::Hamming | Filter::Hann => 1.0,
+            Filter::CubicCatrom
+            | Filter::CubicMitchell
+            | Filter::CubicBSpline
+            | Filter::Lagrange
+            | Filter::CubicBC { .. } => 2.0,
+            Filter::Lanczos3 => 3.0,
+            Filter::Lanczos { lobes }
+            | Filter::Kaiser { lobes, .. }
+            | Filter::Blackman { lobes } => lobes.max(1) as f32,
+            Filter::Gauss { sigma } => 6.0 * sigma,
+            Filter::Jinc { lobes } => jinc_zero(lobes.max(1)),
+            Filter::Custom(ref custom) => custom.support(),
+        }
+    }
+
+    /// Evaluates the filter kernel at the given distance from the center.
+    pub fn kernel(&self, x: f32) -> f32 {
+        match *self {
+            Filter::Nearest => 1.0,
             Filter::Box => {
-                let filter =
-                    resize::Filter::new(Box::new(|x| if x.abs() <= 0.5 { 1.0 } else { 0.0 }), 1.0);
-                resize::Type::Custom(filter)
-            }
-            Filter::Linear => resize::Type::Triangle,
-            Filter::Hermite => {
-                let filter = resize::Filter::new(Box::new(|x| cubic_bc(0.0, 0.0, x)), 1.0);
-                resize::Type::Custom(filter)
+                if x.abs() <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
             }
-            Filter::CubicCatrom => resize::Type::Catrom,
-            Filter::CubicMitchell => resize::Type::Mitchell,
-            Filter::CubicBSpline => resize::Type::BSpline,
+            Filter::Linear => f32::max(1.0 - x.abs(), 0.0),
+            Filter::Hermite => cubic_bc(0.0, 0.0, x),
+            Filter::CubicCatrom => cubic_bc(0.0, 0.5, x),
+            Filter::CubicMitchell => cubic_bc(1.0 / 3.0, 1.0 / 3.0, x),
+            Filter::CubicBSpline => cubic_bc(1.0, 0.0, x),
             Filter::Hamming => {
-                let filter = resize::Filter::new(
-                    Box::new(|x| {
-                        let x = x.abs() * std::f32::consts::PI;
-                        sinc(x) * (0.54 + 0.46 * x.cos())
-                    }),
-                    1.0,
-                );
-                resize::Type::Custom(filter)
+                let x = x.abs() * std::f32::consts::PI;
+                sinc(x) * (0.54 + 0.46 * x.cos())
             }
             Filter::Hann => {
-                let filter = resize::Filter::new(
-                    Box::new(|x| {
-                        let x = x.abs() * std::f32::consts::PI;
-                        sinc(x) * (0.5 + 0.5 * x.cos())
-                    }),
-                    1.0,
-                );
-                resize::Type::Custom(filter)
+                let x = x.abs() * std::f32::consts::PI;
+                sinc(x) * (0.5 + 0.5 * x.cos())
             }
-            Filter::Lanczos3 => resize::Type::Lanczos3,
-            Filter::Lagrange => {
-                let filter = resize::Filter::new(Box::new(|x| lagrange(x, 2.0)), 2.0);
-                resize::Type::Custom(filter)
+            Filter::Lanczos3 => lanczos(3.0, x),
+            Filter::Lagrange => lagrange(x, 2.0),
+            Filter::CubicBC { b, c } => cubic_bc(b, c, x),
+            Filter::Lanczos { lobes } => lanczos(lobes.max(1) as f32, x),
+            Filter::Gauss { sigma } => gaussian(x, sigma),
+            Filter::Kaiser { lobes, beta } => {
+                let beta = f64::from(beta);
+                windowed_sinc(x, lobes.max(1) as f32, |t| {
+                    let t = f64::from(t);
+                    (bessel_i0(beta * (1.0 - t * t).max(0.0).sqrt()) / bessel_i0(beta)) as f32
+                })
             }
-            Filter::Gauss => resize::Type::Gaussian,
+            Filter::Blackman { lobes } => windowed_sinc(x, lobes.max(1) as f32, |t| {
+                let t = t * std::f32::consts::PI;
+                0.42 + 0.5 * t.cos() + 0.08 * (2.0 * t).cos()
+            }),
+            Filter::Jinc { lobes } => {
+                let support = jinc_zero(lobes.max(1));
+                if x.abs() < support {
+                    jinc(x) * jinc(x * jinc_zero(1) / support)
+                } else {
+                    0.0
+                }
+            }
+            Filter::Custom(ref custom) => custom.kernel(x),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+
+    #[test]
+    fn windowed_kernels() {
+        let filters = [
+            Filter::Lanczos { lobes: 2 },
+            Filter::Kaiser {
+                lobes: 4,
+                beta: 8.0,
+            },
+            Filter::Blackman { lobes: 3 },
+            Filter::Jinc { lobes: 1 },
+            Filter::Jinc { lobes: 4 },
+            Filter::Jinc { lobes: 7 },
+        ];
+        for filter in filters {
+            let support = filter.support();
+            assert!((filter.kernel(0.0) - 1.0).abs() < 1e-6, "{:?}", filter);
+            assert!(filter.kernel(support).abs() < 1e-4, "{:?}", filter);
+            assert_eq!(filter.kernel(support + 0.1), 0.0, "{:?}", filter);
+        }
+
+        // the first zero of jinc is the support of a 1-lobe jinc
+        assert!(super::jinc(super::jinc_zero(1)).abs() < 1e-6);
+        assert!(super::jinc(super::jinc_zero(6)).abs() < 1e-4);
+    }
+}
@@ -1,8 +1,11 @@
 mod filter;
 mod pixel_format;
+mod resample;
 #[allow(clippy::module_inception)]
 mod scale;
 
 pub use self::filter::*;
 pub use self::pixel_format::*;
+pub use self::resample::ScaleError;
+use self::resample::{resample, WeightTable};
 pub use self::scale::*;
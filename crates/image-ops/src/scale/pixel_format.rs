@@ -34,37 +34,6 @@ impl<T> Default for FloatPixelFormat<T> {
     }
 }
 
-impl<T> resize::PixelFormat for FloatPixelFormat<T>
-where
-    Self: PixelFormat,
-{
-    type InputPixel = <Self as PixelFormat>::InputPixel;
-
-    type OutputPixel = <Self as PixelFormat>::OutputPixel;
-
-    type Accumulator = <Self as PixelFormat>::Accumulator;
-
-    #[inline(always)]
-    fn new() -> Self::Accumulator {
-        Self::new_acc()
-    }
-
-    #[inline(always)]
-    fn add(&self, acc: &mut Self::Accumulator, inp: Self::InputPixel, coeff: f32) {
-        self.add_pixel_scaled(acc, inp, coeff)
-    }
-
-    #[inline(always)]
-    fn add_acc(acc: &mut Self::Accumulator, inp: Self::Accumulator, coeff: f32) {
-        Self::add_acc_scaled(acc, inp, coeff)
-    }
-
-    #[inline(always)]
-    fn into_pixel(&self, acc: Self::Accumulator) -> Self::OutputPixel {
-        self.acc_to_pixel(acc)
-    }
-}
-
 impl PixelFormat for FloatPixelFormat<f32> {
     type InputPixel = f32;
 
@@ -0,0 +1,255 @@
+use image_core::{Image, ImageView, Size};
+use rayon::prelude::*;
+
+use super::{Filter, PixelFormat};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleError {
+    /// The source image has a width or height of 0, but the target size doesn't.
+    EmptySource,
+    /// Not enough memory for the intermediate or resulting image.
+    OutOfMemory,
+}
+
+impl From<std::collections::TryReserveError> for ScaleError {
+    fn from(_: std::collections::TryReserveError) -> Self {
+        ScaleError::OutOfMemory
+    }
+}
+
+/// The precomputed weights for resampling one axis.
+///
+/// Output pixel `i` is the weighted sum of the source pixels
+/// `start[i]..start[i] + len` using the weights of line `i`.
+pub(crate) struct WeightTable {
+    starts: Vec<usize>,
+    /// `offsets[i]..offsets[i + 1]` is the range of line `i` in `weights`.
+    offsets: Vec<usize>,
+    weights: Vec<f32>,
+}
+
+impl WeightTable {
+    /// Computes the weights to resample `src_len` pixels into `dst_len` pixels.
+    ///
+    /// When downsampling, the kernel is stretched by the scale factor, so that
+    /// every source pixel contributes to the result.
+    ///
+    /// The source is assumed to be padded by `pad` pixels on both sides. With
+    /// a padding of at least [`WeightTable::radius`], no kernel window is
+    /// clipped. Otherwise, clipped windows are renormalized.
+    pub fn new(
+        src_len: usize,
+        dst_len: usize,
+        pad: usize,
+        support: f32,
+        kernel: impl Fn(f32) -> f32,
+    ) -> Result<Self, ScaleError> {
+        assert!(src_len > 0);
+
+        let ratio = src_len as f64 / dst_len as f64;
+        let filter_scale = ratio.max(1.0);
+        let filter_radius = (f64::from(support) * filter_scale).ceil();
+        let last = (src_len + 2 * pad) as isize - 1;
+
+        let mut starts = Vec::new();
+        starts.try_reserve_exact(dst_len)?;
+        let mut offsets = Vec::new();
+        offsets.try_reserve_exact(dst_len + 1)?;
+        offsets.push(0);
+        let mut weights = Vec::new();
+        weights.try_reserve(dst_len * (filter_radius as usize * 2 + 1).min(src_len))?;
+
+        for i in 0..dst_len {
+            // the center of the output pixel in source coordinates
+            let center = (i as f64 + 0.5) * ratio - 0.5 + pad as f64;
+
+            let start = ((center - filter_radius).ceil() as isize).clamp(0, last) as usize;
+            let end =
+                (((center + filter_radius).floor() as isize).clamp(0, last) as usize).max(start);
+
+            // the kernel is only evaluated within its support and is zero outside
+            let line_start = weights.len();
+            weights.extend((start..=end).map(|j| {
+                let x = ((j as f64 - center) / filter_scale) as f32;
+                if x.abs() > support {
+                    0.0
+                } else {
+                    kernel(x)
+                }
+            }));
+
+            let line = &mut weights[line_start..];
+            let sum: f64 = line.iter().map(|&w| f64::from(w)).sum();
+            if sum == 0.0 {
+                // the kernel is too narrow to hit any pixel, so use the nearest one
+                let nearest = (center.round() as isize).clamp(start as isize, end as isize);
+                for (j, w) in (start..=end).zip(line) {
+                    *w = (j as isize == nearest) as u8 as f32;
+                }
+            } else {
+                for w in line {
+                    *w = (f64::from(*w) / sum) as f32;
+                }
+            }
+
+            starts.push(start);
+            offsets.push(weights.len());
+        }
+
+        Ok(Self {
+            starts,
+            offsets,
+            weights,
+        })
+    }
+
+    pub fn from_filter(
+        src_len: usize,
+        dst_len: usize,
+        pad: usize,
+        filter: &Filter,
+    ) -> Result<Self, ScaleError> {
+        Self::new(src_len, dst_len, pad, filter.support(), |x| {
+            filter.kernel(x)
+        })
+    }
+
+    /// Returns how many pixels the kernel reaches beyond the center of an
+    /// output pixel, in source pixels.
+    pub fn radius(src_len: usize, dst_len: usize, support: f32) -> usize {
+        let filter_scale = (src_len as f64 / dst_len as f64).max(1.0);
+        (f64::from(support) * filter_scale).ceil() as usize
+    }
+
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns the index of the first source pixel and the weights of the
+    /// given output pixel.
+    #[inline]
+    pub fn get(&self, i: usize) -> (usize, &[f32]) {
+        (
+            self.starts[i],
+            &self.weights[self.offsets[i]..self.offsets[i + 1]],
+        )
+    }
+}
+
+/// Resamples the given image with the given weights for each axis.
+///
+/// The image is first resampled horizontally into an intermediate image of
+/// accumulators, which is then resampled vertically. Both passes process rows
+/// in parallel.
+pub(crate) fn resample<F: PixelFormat>(
+    format: &F,
+    img: ImageView<F::InputPixel>,
+    weights_x: &WeightTable,
+    weights_y: &WeightTable,
+) -> Result<Image<F::OutputPixel>, ScaleError> {
+    let size = Size::new(weights_x.len(), weights_y.len());
+    let width = size.width;
+
+    // horizontal pass
+    let mut tmp: Vec<F::Accumulator> = Vec::new();
+    tmp.try_reserve_exact(width * img.height())?;
+    tmp.resize(width * img.height(), F::new_acc());
+    tmp.par_chunks_exact_mut(width)
+        .enumerate()
+        .for_each(|(y, tmp_row)| {
+            let src_row = img.row(y);
+            for (x, dst) in tmp_row.iter_mut().enumerate() {
+                let (start, weights) = weights_x.get(x);
+                let src = &src_row[start..start + weights.len()];
+                let mut acc = F::new_acc();
+                for (&w, &p) in weights.iter().zip(src) {
+                    format.add_pixel_scaled(&mut acc, p, w);
+                }
+                *dst = acc;
+            }
+        });
+
+    // vertical pass
+    let mut data: Vec<F::OutputPixel> = Vec::new();
+    data.try_reserve_exact(size.len())?;
+    data.resize(size.len(), F::OutputPixel::default());
+    data.par_chunks_exact_mut(width).enumerate().for_each_init(
+        Vec::new,
+        |acc_row, (y, dst_row)| {
+            let (start, weights) = weights_y.get(y);
+            let tmp = &tmp[start * width..(start + weights.len()) * width];
+
+            // accumulate whole rows at a time to access memory sequentially
+            acc_row.clear();
+            acc_row.resize(width, F::new_acc());
+            for (&w, tmp_row) in weights.iter().zip(tmp.chunks_exact(width)) {
+                for (acc, &t) in acc_row.iter_mut().zip(tmp_row) {
+                    F::add_acc_scaled(acc, t, w);
+                }
+            }
+
+            for (dst, &acc) in dst_row.iter_mut().zip(acc_row.iter()) {
+                *dst = format.acc_to_pixel(acc);
+            }
+        },
+    );
+
+    Ok(Image::new(size, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WeightTable;
+    use crate::scale::{CustomFilter, Filter};
+
+    #[test]
+    fn weights_sum_to_one() {
+        let filters = [
+            Filter::Nearest,
+            Filter::Box,
+            Filter::Linear,
+            Filter::Hermite,
+            Filter::CubicCatrom,
+            Filter::CubicMitchell,
+            Filter::CubicBSpline,
+            Filter::Hamming,
+            Filter::Hann,
+            Filter::Lanczos3,
+            Filter::Lagrange,
+            Filter::CubicBC { b: 0.2, c: 0.4 },
+            Filter::Lanczos { lobes: 4 },
+            Filter::Gauss { sigma: 0.5 },
+            Filter::Kaiser {
+                lobes: 3,
+                beta: 4.0,
+            },
+            Filter::Blackman { lobes: 3 },
+            Filter::Jinc { lobes: 3 },
+            // nonzero at the edge of its support
+            Filter::Custom(CustomFilter::new(1.3, |x| 1.0 - x.abs() / 2.0)),
+        ];
+        let sizes = [(10, 10), (10, 27), (27, 10), (100, 7), (7, 3), (3, 8)];
+
+        for filter in &filters {
+            for (src_len, dst_len) in sizes {
+                for pad in [0, WeightTable::radius(src_len, dst_len, filter.support())] {
+                    let table = WeightTable::from_filter(src_len, dst_len, pad, filter).unwrap();
+                    for i in 0..table.len() {
+                        let (_, weights) = table.get(i);
+                        let sum: f32 = weights.iter().sum();
+                        assert!(
+                            (sum - 1.0).abs() < 1e-5,
+                            "{:?} {}->{} pad {}: line {} sums to {}",
+                            filter,
+                            src_len,
+                            dst_len,
+                            pad,
+                            i,
+                            sum
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
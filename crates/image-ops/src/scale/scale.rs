@@ -1,46 +1,95 @@
-use image_core::{Image, ImageView, Size};
-
-use super::{Filter, FloatPixelFormat, PixelFormat};
-
-pub fn scale<P>(img: ImageView<P>, size: Size, filter: Filter) -> Result<Image<P>, resize::Error>
+use image_core::{Image, ImageView, PremultiplyAlpha, Size};
+
+use super::{resample, Filter, FloatPixelFormat, PixelFormat, ScaleError, WeightTable};
+use crate::util::{pad, BorderMode};
+
+/// Scales the given image to the given size using the given filter.
+///
+/// All filters except [`Filter::Nearest`] are implemented as a separable
+/// convolution with precomputed weights.
+///
+/// The border mode determines the pixels the filter sees beyond the edges of
+/// the image. [`BorderMode::Transparent`] renormalizes the weights of the
+/// clipped kernel instead, and [`Filter::Nearest`] never samples outside the
+/// image.
+pub fn scale<P>(
+    img: ImageView<P>,
+    size: Size,
+    filter: Filter,
+    border: BorderMode<P>,
+) -> Result<Image<P>, ScaleError>
 where
-    P: Clone + Default,
+    P: Copy + Default,
     FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
 {
     if size.is_empty() {
         return Ok(Image::new(size, Vec::new()));
     }
+    if img.size().is_empty() {
+        return Err(ScaleError::EmptySource);
+    }
 
-    let filter_type = match filter {
-        Filter::Nearest => {
-            // the nearest implementation isn't correct, so we use our own
-            return Ok(nearest_neighbor(img, size));
-        }
-        _ => filter.into(),
+    if filter == Filter::Nearest {
+        return Ok(scale_nearest(img, size));
+    }
+
+    let padding = |src_len: usize, dst_len: usize| match border {
+        BorderMode::Transparent => 0,
+        _ => WeightTable::radius(src_len, dst_len, filter.support()),
     };
+    let pad_x = padding(img.width(), size.width);
+    let pad_y = padding(img.height(), size.height);
 
-    let mut dest = Image::from_const(size, P::default());
+    let weights_x = WeightTable::from_filter(img.width(), size.width, pad_x, &filter)?;
+    let weights_y = WeightTable::from_filter(img.height(), size.height, pad_y, &filter)?;
 
-    resize::Resizer::new(
-        img.width(),
-        img.height(),
-        size.width,
-        size.height,
-        FloatPixelFormat::default(),
-        filter_type,
-    )?
-    .resize(img.data(), dest.data_mut())?;
+    let padded = pad(img, pad_x, pad_y, &border);
+    let img = padded.as_ref().map_or(img, Image::view);
 
+    resample(&FloatPixelFormat::default(), img, &weights_x, &weights_y)
+}
+
+/// Scales the given image while weighting the color channels by alpha.
+///
+/// The image is premultiplied before resampling and un-premultiplied after.
+/// Unlike [`scale`], this prevents the (often arbitrary) colors of transparent
+/// pixels from bleeding into the edges of visible pixels.
+pub fn scale_alpha<P>(
+    img: ImageView<P>,
+    size: Size,
+    filter: Filter,
+    border: BorderMode<P>,
+) -> Result<Image<P>, ScaleError>
+where
+    P: PremultiplyAlpha + Default,
+    FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
+{
+    if filter == Filter::Nearest {
+        // pixels are only copied, so alpha doesn't matter
+        return Ok(scale_nearest(img, size));
+    }
+
+    let pre = img.map(|p| p.premultiply_alpha());
+    let border = border.map(|c| c.premultiply_alpha());
+    let mut dest = scale(pre.view(), size, filter, border)?;
+    dest.data_mut()
+        .iter_mut()
+        .for_each(|p| *p = p.unpremultiply_alpha());
     Ok(dest)
 }
 
-fn nearest_neighbor<P: Clone>(src: ImageView<P>, size: Size) -> Image<P> {
+/// Scales the given image to the given size using nearest neighbor interpolation.
+///
+/// Unlike [`scale`], this works for any pixel type since pixels are only copied.
+/// This makes it suitable for integer pixels, e.g. `Image<[u8; 4]>`.
+pub fn scale_nearest<P: Clone>(src: ImageView<P>, size: Size) -> Image<P> {
     if src.size() == size {
         return src.into_owned();
     }
 
     let src_size = src.size();
-    let src = src.data();
+    let src_stride = src.stride();
+    let src = src.raw_data();
 
     {
         // optimization for power-of-2 scaling factors, e.g. 2x, 4x
@@ -51,7 +100,7 @@ fn nearest_neighbor<P: Clone>(src: ImageView<P>, size: Size) -> Image<P> {
             let mut data = Vec::with_capacity(size.len());
             for y in 0..size.height {
                 let src_y = y >> shift;
-                let src_i = src_y * src_size.width;
+                let src_i = src_y * src_stride;
 
                 data.extend((0..size.width).map(move |x| {
                     let src_x = x >> shift;
@@ -90,7 +139,7 @@ fn nearest_neighbor<P: Clone>(src: ImageView<P>, size: Size) -> Image<P> {
     let mut data = Vec::with_capacity(size.len());
     for y in 0..(size.height as u64) {
         let src_y = ((y * k_y + k_y_half) >> SHIFT) as usize;
-        let src_i = src_y * src_size.width;
+        let src_i = src_y * src_stride;
 
         data.extend((0..(size.width as u64)).map(move |x| {
             let src_x = ((x * k_x + k_x_half) >> SHIFT) as usize;
@@ -104,12 +153,47 @@ fn nearest_neighbor<P: Clone>(src: ImageView<P>, size: Size) -> Image<P> {
 #[cfg(test)]
 mod tests {
     use glam::Vec3A;
-    use image_core::Size;
-    use test_util::{data::read_portrait, snap::ImageSnapshot};
+    use image_core::{Image, Size};
+    use test_util::{
+        data::{read_abstract_transparent, read_portrait},
+        fixtures::{assert_close, pattern},
+        snap::ImageSnapshot,
+    };
+
+    use crate::util::BorderMode;
 
     fn small_portrait() -> image_core::Image<Vec3A> {
         let img = read_portrait();
-        super::scale(img.view(), img.size().scale(0.5), super::Filter::Linear).unwrap()
+        super::scale(
+            img.view(),
+            img.size().scale(0.5),
+            super::Filter::Linear,
+            BorderMode::Transparent,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn scale_region() {
+        let original = read_portrait();
+        let (x, y, w, h) = (37, 50, 101, 80);
+        let new_size = Size::new(150, 223);
+
+        for filter in [super::Filter::Nearest, super::Filter::CubicMitchell] {
+            let from_view = super::scale(
+                original.view_region(x, y, w, h),
+                new_size,
+                filter.clone(),
+                BorderMode::Transparent,
+            );
+            let from_copy = super::scale(
+                original.crop(x, y, w, h).view(),
+                new_size,
+                filter,
+                BorderMode::Transparent,
+            );
+            assert_eq!(from_view.unwrap().data(), from_copy.unwrap().data());
+        }
     }
 
     #[test]
@@ -118,27 +202,110 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_nearest_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_nearest_200");
     }
 
+    #[test]
+    fn scale_nearest_integer() {
+        use image_core::Sample;
+
+        let original = small_portrait();
+        let to_u8 = |p: &Vec3A| p.to_array().map(u8::from_f32);
+
+        for new_size in [original.size().scale(4.), Size::new(200, 200)] {
+            let nn = super::scale_nearest(original.map(to_u8).view(), new_size);
+            let expected = super::scale(
+                original.view(),
+                new_size,
+                super::Filter::Nearest,
+                BorderMode::Transparent,
+            );
+            assert_eq!(nn.data(), expected.unwrap().map(to_u8).data());
+        }
+    }
+
+    #[test]
+    fn scale_alpha() {
+        let original = read_abstract_transparent();
+        let new_size = original.size().scale(0.25);
+
+        let filter = super::Filter::CubicCatrom;
+        super::scale_alpha(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap()
+        .snapshot("resize_alpha_catrom");
+
+        // fully transparent images must not produce NaNs
+        let transparent = image_core::Image::from_const(original.size(), glam::Vec4::ZERO);
+        let result = super::scale_alpha(
+            transparent.view(),
+            new_size,
+            filter,
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        assert!(result.data().iter().all(|p| *p == glam::Vec4::ZERO));
+    }
+
+    #[test]
+    fn scale_empty() {
+        let original = read_portrait();
+        let empty = original.view_region(0, 0, 0, 10);
+
+        let result = super::scale(
+            empty,
+            Size::new(0, 5),
+            super::Filter::Linear,
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        assert!(result.size().is_empty());
+        assert_eq!(
+            super::scale(
+                empty,
+                Size::new(5, 5),
+                super::Filter::Linear,
+                BorderMode::Transparent
+            )
+            .unwrap_err(),
+            super::ScaleError::EmptySource
+        );
+    }
+
     #[test]
     fn scale_box() {
         let filter = super::Filter::Box;
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_box_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_box_200");
     }
 
@@ -148,12 +315,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_linear_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_linear_200");
     }
 
@@ -163,12 +336,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_hermite_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_hermite_200");
     }
 
@@ -178,13 +357,13 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_cubic_catrom_4x");
 
         // https://github.com/chaiNNer-org/chaiNNer-rs/pull/20#issuecomment-1839525313
         // let original = read_portrait();
         // let new_size = Size::new(200, 200);
-        // let nn = super::scale(original.view(), new_size, filter).unwrap();
+        // let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         // nn.snapshot("resize_cubic_catrom_200");
     }
 
@@ -194,12 +373,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_cubic_bspline_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_cubic_bspline_200");
     }
 
@@ -209,12 +394,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_cubic_mitchell_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_cubic_mitchell_200");
     }
 
@@ -224,12 +415,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_hamming_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_hamming_200");
     }
 
@@ -239,12 +436,18 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_hann_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_hann_200");
     }
 
@@ -254,28 +457,34 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_lanczos3_4x");
 
         // https://github.com/chaiNNer-org/chaiNNer-rs/pull/20#issuecomment-1839525313
         // let original = read_portrait();
         // let new_size = Size::new(200, 200);
-        // let nn = super::scale(original.view(), new_size, filter).unwrap();
+        // let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         // nn.snapshot("resize_lanczos3_200");
     }
 
     #[test]
     fn scale_gauss() {
-        let filter = super::Filter::Gauss;
+        let filter = super::Filter::Gauss { sigma: 0.5 };
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_gauss_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_gauss_200");
     }
 
@@ -285,12 +494,220 @@ mod tests {
 
         let original = small_portrait();
         let new_size = original.size().scale(4.);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
         nn.snapshot("resize_lagrange_4x");
 
         let original = read_portrait();
         let new_size = Size::new(200, 200);
-        let nn = super::scale(original.view(), new_size, filter).unwrap();
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
         nn.snapshot("resize_lagrange_200");
     }
+
+    #[test]
+    fn scale_parametric() {
+        let original = small_portrait();
+        let new_size = original.size().scale(2.5);
+        let scale = |filter| {
+            super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap()
+        };
+
+        let presets = [
+            (
+                super::Filter::CubicMitchell,
+                super::Filter::CubicBC {
+                    b: 1.0 / 3.0,
+                    c: 1.0 / 3.0,
+                },
+            ),
+            (
+                super::Filter::CubicCatrom,
+                super::Filter::CubicBC { b: 0.0, c: 0.5 },
+            ),
+            (super::Filter::Lanczos3, super::Filter::Lanczos { lobes: 3 }),
+        ];
+        for (preset, parametric) in presets {
+            assert_eq!(scale(preset).data(), scale(parametric).data());
+        }
+
+        let custom = crate::scale::CustomFilter::new(1.0, |x| f32::max(1.0 - x.abs(), 0.0));
+        assert_eq!(
+            scale(super::Filter::Custom(custom)).data(),
+            scale(super::Filter::Linear).data()
+        );
+
+        // kernels that miss every pixel fall back to nearest neighbor
+        let narrow = super::Filter::Gauss { sigma: 0.01 };
+        assert!(scale(narrow).data().iter().all(|p| p.is_finite()));
+    }
+
+    #[test]
+    fn scale_kaiser() {
+        let filter = super::Filter::Kaiser {
+            lobes: 3,
+            beta: 6.0,
+        };
+
+        let original = small_portrait();
+        let new_size = original.size().scale(4.);
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        nn.snapshot("resize_kaiser_4x");
+
+        let original = read_portrait();
+        let new_size = Size::new(200, 200);
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
+        nn.snapshot("resize_kaiser_200");
+    }
+
+    #[test]
+    fn scale_blackman() {
+        let filter = super::Filter::Blackman { lobes: 3 };
+
+        let original = small_portrait();
+        let new_size = original.size().scale(4.);
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        nn.snapshot("resize_blackman_4x");
+
+        let original = read_portrait();
+        let new_size = Size::new(200, 200);
+        let nn = super::scale(original.view(), new_size, filter, BorderMode::Transparent).unwrap();
+        nn.snapshot("resize_blackman_200");
+    }
+
+    #[test]
+    fn scale_jinc() {
+        let filter = super::Filter::Jinc { lobes: 3 };
+
+        let original = small_portrait();
+        let new_size = original.size().scale(4.);
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        nn.snapshot("resize_jinc_4x");
+
+        let original = read_portrait();
+        let new_size = Size::new(200, 200);
+        let nn = super::scale(
+            original.view(),
+            new_size,
+            filter.clone(),
+            BorderMode::Transparent,
+        )
+        .unwrap();
+        nn.snapshot("resize_jinc_200");
+
+        // scaling is separable, so the 2D kernel is the product of the 1D kernels
+        let original = pattern::<f32>(Size::new(13, 9));
+        for new_size in [Size::new(29, 20), Size::new(5, 4)] {
+            let actual =
+                super::scale(original.view(), new_size, filter.clone(), BorderMode::Clamp).unwrap();
+            let expected = scale_separable_naive(&original, new_size, &filter);
+            assert_close(actual, expected, 1e-5);
+        }
+    }
+
+    /// Scales the given image by evaluating the product of the kernel along
+    /// both axes for every pair of source and output pixels. Pixels outside the
+    /// image are clamped to the edge.
+    fn scale_separable_naive(img: &Image<f32>, size: Size, filter: &super::Filter) -> Image<f32> {
+        let support = filter.support() as f64;
+        // the kernel and its support in source pixels along one axis
+        let axis = |src_len: usize, dst_len: usize, i: usize| {
+            let ratio = src_len as f64 / dst_len as f64;
+            let filter_scale = ratio.max(1.0);
+            let center = (i as f64 + 0.5) * ratio - 0.5;
+            let radius = (support * filter_scale).ceil() as isize;
+            let first = center.floor() as isize - radius;
+            (first..=first + 2 * radius + 1)
+                .map(|j| {
+                    let x = (j as f64 - center) / filter_scale;
+                    let w = if x.abs() > support {
+                        0.0
+                    } else {
+                        filter.kernel(x as f32) as f64
+                    };
+                    (j.clamp(0, src_len as isize - 1) as usize, w)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Image::from_fn(size, |x, y| {
+            let mut sum = 0.0;
+            let mut weight = 0.0;
+            for (sx, wx) in axis(img.width(), size.width, x) {
+                for &(sy, wy) in &axis(img.height(), size.height, y) {
+                    sum += wx * wy * img.row(sy)[sx] as f64;
+                    weight += wx * wy;
+                }
+            }
+            (sum / weight) as f32
+        })
+    }
+
+    #[test]
+    fn scale_border() {
+        let original = small_portrait();
+        let crop = original.view_region(40, 60, 50, 40);
+        let new_size = Size::new(200, 160);
+        let filter = super::Filter::Lanczos3;
+
+        for (border, name) in [
+            (BorderMode::Clamp, "clamp"),
+            (BorderMode::Reflect, "reflect"),
+            (BorderMode::Wrap, "wrap"),
+            (BorderMode::Constant(Vec3A::new(1.0, 0.0, 1.0)), "constant"),
+        ] {
+            super::scale(crop, new_size, filter.clone(), border)
+                .unwrap()
+                .snapshot(&format!("resize_border_{}", name));
+        }
+
+        // tiles of a wrapped image are continuous, so scaling a tiled image
+        // is the same as tiling the scaled image
+        let tile = original.crop(0, 0, 40, 30);
+        let tiled = image_core::Image::new(
+            Size::new(80, 30),
+            (0..30)
+                .flat_map(|y| tile.row(y).iter().chain(tile.row(y)).copied())
+                .collect(),
+        );
+        let scaled_tile = super::scale(
+            tile.view(),
+            Size::new(100, 75),
+            filter.clone(),
+            BorderMode::Wrap,
+        )
+        .unwrap();
+        let scaled_tiled =
+            super::scale(tiled.view(), Size::new(200, 75), filter, BorderMode::Wrap).unwrap();
+        for y in 0..75 {
+            for x in 0..200 {
+                let diff = (scaled_tiled.row(y)[x] - scaled_tile.row(y)[x % 100])
+                    .abs()
+                    .max_element();
+                assert!(diff < 1e-4, "({}, {}): {}", x, y, diff);
+            }
+        }
+    }
 }
@@ -0,0 +1,281 @@
+use glam::{Vec3A, Vec4};
+use image_core::{Image, ImageView, PremultiplyAlpha};
+
+use crate::{
+    blur::{gaussian_blur, BlurPixel},
+    pixel_art::luma,
+    util::BorderMode,
+};
+
+/// Which parts of the color are sharpened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharpenMode {
+    /// Sharpen the red, green, and blue channels independently.
+    Rgb,
+    /// Only sharpen the luminance and keep the chroma of the image. This
+    /// avoids color fringes around edges.
+    Luminance,
+}
+
+/// A color pixel that can be sharpened. The alpha channel (if any) is never
+/// changed.
+pub trait SharpenPixel: Copy + Send + Sync {
+    /// The representation of the pixel that is blurred.
+    type Blur: BlurPixel;
+
+    fn to_blur(self) -> Self::Blur;
+    /// Returns the color of a blurred pixel, or `None` if the blurred pixel
+    /// is fully transparent.
+    fn blurred_color(blurred: Self::Blur) -> Option<Vec3A>;
+    fn color(self) -> Vec3A;
+    fn with_color(self, color: Vec3A) -> Self;
+}
+
+impl SharpenPixel for Vec3A {
+    type Blur = Vec3A;
+
+    #[inline]
+    fn to_blur(self) -> Self::Blur {
+        self
+    }
+    #[inline]
+    fn blurred_color(blurred: Self::Blur) -> Option<Vec3A> {
+        Some(blurred)
+    }
+    #[inline]
+    fn color(self) -> Vec3A {
+        self
+    }
+    #[inline]
+    fn with_color(self, color: Vec3A) -> Self {
+        color
+    }
+}
+impl SharpenPixel for Vec4 {
+    // blur with premultiplied alpha, so transparent pixels don't affect the
+    // colors of their neighbors
+    type Blur = Vec4;
+
+    #[inline]
+    fn to_blur(self) -> Self::Blur {
+        self.premultiply_alpha()
+    }
+    #[inline]
+    fn blurred_color(blurred: Self::Blur) -> Option<Vec3A> {
+        if blurred.w > 0.0 {
+            Some(Vec3A::from(blurred.truncate() / blurred.w))
+        } else {
+            None
+        }
+    }
+    #[inline]
+    fn color(self) -> Vec3A {
+        Vec3A::from(self.truncate())
+    }
+    #[inline]
+    fn with_color(self, color: Vec3A) -> Self {
+        color.extend(self.w)
+    }
+}
+
+/// Returns the difference between the colors of the image and the colors of
+/// the blurred image.
+fn details<P: SharpenPixel>(
+    img: ImageView<P>,
+    radius: f32,
+    mode: SharpenMode,
+    border: BorderMode<P>,
+) -> Image<Vec3A> {
+    let blurred = gaussian_blur(
+        img.map(|p| p.to_blur()).view(),
+        radius,
+        radius,
+        border.map(P::to_blur),
+    );
+
+    let data = img
+        .rows()
+        .zip(blurred.rows())
+        .flat_map(|(row, blurred)| row.iter().zip(blurred))
+        .map(|(p, &b)| {
+            let detail = match P::blurred_color(b) {
+                Some(b) => p.color() - b,
+                None => Vec3A::ZERO,
+            };
+            match mode {
+                SharpenMode::Rgb => detail,
+                // adding the same value to all channels doesn't change the chroma
+                SharpenMode::Luminance => Vec3A::splat(luma(detail)),
+            }
+        })
+        .collect();
+    Image::new(img.size(), data)
+}
+
+/// Sharpens the given image by adding the difference between the image and a
+/// blurred version of it.
+///
+/// `radius` is the standard deviation of the Gaussian blur, and `amount` is
+/// the factor for the difference. Differences smaller than `threshold` are
+/// ignored, which avoids amplifying noise in flat areas.
+///
+/// Sharpening can create colors outside the range `0..=1`. The result is not
+/// clipped.
+pub fn unsharp_mask<P: SharpenPixel>(
+    img: ImageView<P>,
+    radius: f32,
+    amount: f32,
+    threshold: f32,
+    mode: SharpenMode,
+    border: BorderMode<P>,
+) -> Image<P> {
+    let details = details(img, radius, mode, border);
+
+    let threshold = Vec3A::splat(threshold);
+    let data = img
+        .rows()
+        .zip(details.rows())
+        .flat_map(|(row, details)| row.iter().zip(details))
+        .map(|(&p, &d)| {
+            let d = Vec3A::select(d.abs().cmpge(threshold), d, Vec3A::ZERO);
+            p.with_color(p.color() + d * amount)
+        })
+        .collect();
+    Image::new(img.size(), data)
+}
+
+/// Returns the high frequencies of the given image, i.e. the difference
+/// between the image and a blurred version of it, offset by 0.5.
+///
+/// `radius` is the standard deviation of the Gaussian blur. With
+/// [`SharpenMode::Luminance`], the result is grayscale.
+pub fn high_pass<P: SharpenPixel>(
+    img: ImageView<P>,
+    radius: f32,
+    mode: SharpenMode,
+    border: BorderMode<P>,
+) -> Image<P> {
+    let details = details(img, radius, mode, border);
+
+    let data = img
+        .rows()
+        .zip(details.rows())
+        .flat_map(|(row, details)| row.iter().zip(details))
+        .map(|(&p, &d)| p.with_color(d + 0.5))
+        .collect();
+    Image::new(img.size(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3A, Vec4};
+    use image_core::Image;
+    use test_util::{
+        data::{read_flower_transparent, read_portrait},
+        snap::ImageSnapshot,
+    };
+
+    use super::SharpenMode;
+    use crate::util::BorderMode;
+
+    fn max_diff(a: &Image<Vec3A>, b: &Image<Vec3A>) -> f32 {
+        a.data()
+            .iter()
+            .zip(b.data())
+            .map(|(a, b)| (*a - *b).abs().max_element())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn unsharp_mask() {
+        let original = read_portrait();
+        super::unsharp_mask(
+            original.view(),
+            2.0,
+            1.5,
+            0.0,
+            SharpenMode::Rgb,
+            BorderMode::Reflect,
+        )
+        .snapshot("unsharp_mask");
+        super::unsharp_mask(
+            original.view(),
+            2.0,
+            1.5,
+            0.02,
+            SharpenMode::Luminance,
+            BorderMode::Reflect,
+        )
+        .snapshot("unsharp_mask_luminance");
+
+        // no amount or a huge threshold don't change the image
+        let mask = |amount: f32, threshold: f32| {
+            super::unsharp_mask(
+                original.view(),
+                2.0,
+                amount,
+                threshold,
+                SharpenMode::Rgb,
+                BorderMode::Clamp,
+            )
+        };
+        assert_eq!(max_diff(&mask(0.0, 0.0), &original), 0.0);
+        assert_eq!(max_diff(&mask(1.0, 2.0), &original), 0.0);
+    }
+
+    #[test]
+    fn luminance_keeps_chroma() {
+        let original = read_portrait().crop(100, 100, 100, 100);
+        let result = super::unsharp_mask(
+            original.view(),
+            1.5,
+            3.0,
+            0.0,
+            SharpenMode::Luminance,
+            BorderMode::Reflect,
+        );
+
+        let chroma = |c: &Vec3A| {
+            let u = -0.169 * c.x - 0.331 * c.y + 0.5 * c.z;
+            let v = 0.5 * c.x - 0.419 * c.y - 0.081 * c.z;
+            (u, v)
+        };
+        for (a, b) in original.data().iter().zip(result.data()) {
+            let (a, b) = (chroma(a), chroma(b));
+            assert!((a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn alpha() {
+        let original = read_flower_transparent();
+        let result = super::unsharp_mask(
+            original.view(),
+            3.0,
+            2.0,
+            0.0,
+            SharpenMode::Rgb,
+            BorderMode::Transparent,
+        );
+        assert!(original
+            .data()
+            .iter()
+            .zip(result.data())
+            .all(|(a, b): (&Vec4, &Vec4)| a.w == b.w));
+        result.snapshot("unsharp_mask_alpha");
+    }
+
+    #[test]
+    fn high_pass() {
+        let original = read_portrait();
+        super::high_pass(original.view(), 3.0, SharpenMode::Rgb, BorderMode::Reflect)
+            .snapshot("high_pass");
+        super::high_pass(
+            original.view(),
+            3.0,
+            SharpenMode::Luminance,
+            BorderMode::Reflect,
+        )
+        .snapshot("high_pass_luminance");
+    }
+}
@@ -1,4 +1,4 @@
-use image_core::{NDimImage, NDimView};
+use image_core::{NDimView, NDimViewMut};
 
 use crate::util::BiLinear;
 
@@ -7,13 +7,13 @@ pub struct AntiAliasing {
     pub extra_smoothness: f32,
 }
 
-pub fn binary_threshold(img: &mut NDimImage, threshold: f32, anti_aliasing: Option<AntiAliasing>) {
+pub fn binary_threshold(mut img: NDimViewMut, threshold: f32, anti_aliasing: Option<AntiAliasing>) {
     if let Some(AntiAliasing {
         extra_smoothness: extra_smooth,
     }) = anti_aliasing
     {
         // if anti-aliasing is enabled, we need to do some extra work
-        let original = img.clone();
+        let original = img.into_owned();
 
         for p in img.data_mut() {
             *p = if *p > threshold { 1.0 } else { 0.0 };
@@ -21,7 +21,7 @@ pub fn binary_threshold(img: &mut NDimImage, threshold: f32, anti_aliasing: Opti
 
         let c = img.channels();
         for i in 0..c {
-            binary_threshold_aa(original.view(), img, threshold, i, c, extra_smooth);
+            binary_threshold_aa(original.view(), &mut img, threshold, i, c, extra_smooth);
         }
     } else {
         for p in img.data_mut() {
@@ -32,7 +32,7 @@ pub fn binary_threshold(img: &mut NDimImage, threshold: f32, anti_aliasing: Opti
 
 fn binary_threshold_aa(
     img: NDimView,
-    dest: &mut NDimImage,
+    dest: &mut NDimViewMut,
     threshold: f32,
     offset: usize,
     stride: usize,
@@ -174,11 +174,11 @@ mod tests {
     #[test]
     fn binary_threshold() {
         let mut original: NDimImage = read_flower().into();
-        super::binary_threshold(&mut original, 0.5, None);
+        super::binary_threshold(original.view_mut(), 0.5, None);
         original.snapshot("threshold_flower");
 
         let mut original: NDimImage = read_at_sdf().into();
-        super::binary_threshold(&mut original, 0.5, None);
+        super::binary_threshold(original.view_mut(), 0.5, None);
         original.snapshot("threshold_at_sdf");
     }
 
@@ -186,7 +186,7 @@ mod tests {
     fn binary_threshold_aa() {
         let mut original: NDimImage = read_flower().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.0,
@@ -196,7 +196,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.0,
@@ -206,7 +206,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.2,
@@ -216,7 +216,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.4,
@@ -226,7 +226,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.6,
@@ -236,7 +236,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 0.8,
@@ -246,7 +246,7 @@ mod tests {
 
         let mut original: NDimImage = read_at_sdf().into();
         super::binary_threshold(
-            &mut original,
+            original.view_mut(),
             0.5,
             Some(super::AntiAliasing {
                 extra_smoothness: 1.0,
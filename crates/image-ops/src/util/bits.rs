@@ -14,7 +14,7 @@ const USIZE_BITS: usize = USIZE_BYTES * 8;
 impl FixedBits {
     pub fn new(bits: usize) -> Self {
         Self {
-            data: vec![0; div_ceil(bits, USIZE_BYTES)].into_boxed_slice(),
+            data: vec![0; div_ceil(bits, USIZE_BITS)].into_boxed_slice(),
             bits,
         }
     }
@@ -83,6 +83,13 @@ impl FixedBits {
         }
     }
 
+    pub fn not(&mut self) {
+        for a in self.data.iter_mut() {
+            *a = !*a;
+        }
+        self.fix_tail();
+    }
+
     pub fn expand_one(&mut self) {
         for part in self.data.iter_mut() {
             *part |= (*part >> 1) | (*part << 1)
@@ -0,0 +1,147 @@
+use image_core::{Image, ImageView, Size};
+
+/// How pixels outside the bounds of an image are handled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorderMode<P> {
+    /// Repeat the edge pixels (`aaa|abcd|ddd`).
+    Clamp,
+    /// Mirror the image at its edges (`cba|abcd|dcb`).
+    Reflect,
+    /// Tile the image (`bcd|abcd|abc`). Use this for tileable textures.
+    Wrap,
+    /// Use the given color for all pixels outside the image.
+    Constant(P),
+    /// There are no pixels outside the image.
+    ///
+    /// Operations that combine multiple pixels ignore the missing pixels and
+    /// only use the pixels inside the image.
+    Transparent,
+}
+
+impl<P> BorderMode<P> {
+    /// Maps the given index to an index in `0..len`.
+    ///
+    /// Returns `None` if the index is outside `0..len` and the border doesn't
+    /// repeat the image, i.e. for [`BorderMode::Constant`] and
+    /// [`BorderMode::Transparent`].
+    #[inline]
+    pub fn index(&self, i: isize, len: usize) -> Option<usize> {
+        let n = len as isize;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            BorderMode::Clamp => Some(i.clamp(0, n - 1) as usize),
+            BorderMode::Reflect => {
+                let i = i.rem_euclid(2 * n);
+                Some(if i < n { i } else { 2 * n - 1 - i } as usize)
+            }
+            BorderMode::Wrap => Some(i.rem_euclid(n) as usize),
+            BorderMode::Constant(_) | BorderMode::Transparent => None,
+        }
+    }
+
+    /// Returns the pixel at the given position, or `None` if there is no pixel
+    /// at this position (only possible for [`BorderMode::Transparent`]).
+    ///
+    /// The image must not be empty.
+    #[inline]
+    pub fn get(&self, img: ImageView<P>, x: isize, y: isize) -> Option<P>
+    where
+        P: Copy,
+    {
+        match (self.index(x, img.width()), self.index(y, img.height())) {
+            (Some(x), Some(y)) => Some(img.row(y)[x]),
+            _ => match self {
+                BorderMode::Constant(c) => Some(*c),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn map<Q>(self, f: impl FnOnce(P) -> Q) -> BorderMode<Q> {
+        match self {
+            BorderMode::Clamp => BorderMode::Clamp,
+            BorderMode::Reflect => BorderMode::Reflect,
+            BorderMode::Wrap => BorderMode::Wrap,
+            BorderMode::Constant(c) => BorderMode::Constant(f(c)),
+            BorderMode::Transparent => BorderMode::Transparent,
+        }
+    }
+}
+
+/// Returns the image extended by `pad_x` pixels left and right and by `pad_y`
+/// pixels at the top and bottom using the given border mode.
+///
+/// Returns `None` for [`BorderMode::Transparent`], since there are no pixels to
+/// pad the image with.
+pub(crate) fn pad<P: Copy>(
+    img: ImageView<P>,
+    pad_x: usize,
+    pad_y: usize,
+    border: &BorderMode<P>,
+) -> Option<Image<P>> {
+    if matches!(border, BorderMode::Transparent) || img.size().is_empty() {
+        return None;
+    }
+
+    let size = Size::new(img.width() + 2 * pad_x, img.height() + 2 * pad_y);
+    let mut data = Vec::with_capacity(size.len());
+    for y in 0..size.height as isize {
+        let y = y - pad_y as isize;
+        data.extend((0..size.width as isize).map(|x| {
+            let x = x - pad_x as isize;
+            border
+                .get(img, x, y)
+                .expect("only transparent borders don't have pixels")
+        }));
+    }
+    Some(Image::new(size, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use image_core::{Image, Size};
+
+    use super::BorderMode;
+
+    #[test]
+    fn index() {
+        let indexes = |border: BorderMode<u8>| {
+            (-5..9)
+                .map(|i| border.index(i, 4).map_or(-1, |i| i as isize))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            indexes(BorderMode::Clamp),
+            [0, 0, 0, 0, 0, 0, 1, 2, 3, 3, 3, 3, 3, 3]
+        );
+        assert_eq!(
+            indexes(BorderMode::Reflect),
+            [3, 3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]
+        );
+        assert_eq!(
+            indexes(BorderMode::Wrap),
+            [3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0]
+        );
+        assert_eq!(
+            indexes(BorderMode::Transparent),
+            [-1, -1, -1, -1, -1, 0, 1, 2, 3, -1, -1, -1, -1, -1]
+        );
+    }
+
+    #[test]
+    fn pad() {
+        let img = Image::new(Size::new(2, 1), vec![1, 2]);
+
+        let padded = super::pad(img.view(), 1, 1, &BorderMode::Constant(0)).unwrap();
+        assert_eq!(padded.size(), Size::new(4, 3));
+        assert_eq!(padded.data(), [0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0]);
+
+        let padded = super::pad(img.view(), 2, 0, &BorderMode::Wrap).unwrap();
+        assert_eq!(padded.data(), [1, 2, 1, 2, 1, 2]);
+
+        assert!(super::pad(img.view(), 1, 1, &BorderMode::Transparent).is_none());
+    }
+}
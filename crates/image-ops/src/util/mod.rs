@@ -1,34 +1,39 @@
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) mod avx2;
 mod bilinear;
 mod bits;
+mod border;
 mod grid;
 mod image;
 
 use std::ops::Range;
 
-pub use bilinear::*;
-pub use bits::FixedBits;
-pub use grid::Grid;
-pub use image::*;
+pub(crate) use bilinear::*;
+pub(crate) use bits::FixedBits;
+pub(crate) use border::pad;
+pub use border::BorderMode;
+pub(crate) use grid::Grid;
+pub(crate) use image::*;
 
 #[inline(always)]
-pub const fn div_ceil(a: usize, b: usize) -> usize {
+pub(crate) const fn div_ceil(a: usize, b: usize) -> usize {
     a / b + ((a % b != 0) as usize)
 }
 
-pub const fn move_range_i(range: &Range<usize>, offset: isize) -> Range<usize> {
+pub(crate) const fn move_range_i(range: &Range<usize>, offset: isize) -> Range<usize> {
     Range {
         start: (range.start as isize + offset) as usize,
         end: (range.end as isize + offset) as usize,
     }
 }
-pub const fn move_range(range: &Range<usize>, offset: usize) -> Range<usize> {
+pub(crate) const fn move_range(range: &Range<usize>, offset: usize) -> Range<usize> {
     Range {
         start: range.start + offset,
         end: range.end + offset,
     }
 }
 
-pub fn process_pairs<'a, T: 'a + ?Sized>(
+pub(crate) fn process_pairs<'a, T: 'a + ?Sized>(
     iter: impl IntoIterator<Item = &'a mut T>,
     mut f: impl FnMut(&mut T, &mut T),
 ) {
@@ -0,0 +1,428 @@
+use glam::{Affine2, Mat3, Vec2, Vec3};
+use image_core::{Image, ImageView, Size};
+use rayon::prelude::*;
+
+use crate::{
+    scale::{Filter, FloatPixelFormat, PixelFormat},
+    util::BorderMode,
+};
+
+/// The largest factor by which the filter kernel is stretched when
+/// downsampling. This limits the work per pixel for extreme perspective
+/// transforms.
+const MAX_FILTER_SCALE: f32 = 32.0;
+
+/// A projective transformation that maps source coordinates to destination
+/// coordinates.
+///
+/// Coordinates are continuous, so the center of pixel `(0, 0)` is at
+/// `(0.5, 0.5)` and an image of size `w x h` covers `(0, 0)` to `(w, h)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    matrix: Mat3,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        matrix: Mat3::IDENTITY,
+    };
+
+    pub fn affine(affine: Affine2) -> Self {
+        Self {
+            matrix: affine.into(),
+        }
+    }
+    /// Creates a perspective transform from the given homogeneous matrix.
+    pub fn perspective(matrix: Mat3) -> Self {
+        Self { matrix }
+    }
+    /// Creates an affine transform from a row-major 2x3 matrix, e.g. the
+    /// matrix used by OpenCV's `warpAffine`.
+    pub fn from_affine_rows(rows: [[f32; 3]; 2]) -> Self {
+        Self::from_rows([rows[0], rows[1], [0.0, 0.0, 1.0]])
+    }
+    /// Creates a perspective transform from a row-major 3x3 matrix, e.g. the
+    /// matrix used by OpenCV's `warpPerspective`.
+    pub fn from_rows(rows: [[f32; 3]; 3]) -> Self {
+        Self::perspective(Mat3::from_cols_array_2d(&rows).transpose())
+    }
+
+    pub fn translate(offset: Vec2) -> Self {
+        Self::affine(Affine2::from_translation(offset))
+    }
+    /// Creates a transform that maps the given rectangle of the source image
+    /// onto the whole destination image of the given size.
+    ///
+    /// The rectangle may have fractional coordinates.
+    pub fn from_rect(min: Vec2, max: Vec2, size: Size) -> Self {
+        let scale = Vec2::new(size.width as f32, size.height as f32) / (max - min);
+        Self::affine(Affine2::from_scale(scale) * Affine2::from_translation(-min))
+    }
+
+    pub fn matrix(&self) -> Mat3 {
+        self.matrix
+    }
+
+    /// Returns a transform that first applies `self` and then `other`.
+    pub fn then(self, other: Self) -> Self {
+        Self::perspective(other.matrix * self.matrix)
+    }
+
+    /// Returns the inverse transform, or `None` if the transform is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.matrix.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Self::perspective(self.matrix.inverse()))
+    }
+
+    /// Maps the given point.
+    ///
+    /// Points on the horizon of a perspective transform are mapped to
+    /// infinity.
+    #[inline]
+    pub fn apply(&self, p: Vec2) -> Vec2 {
+        let v = self.matrix * Vec3::new(p.x, p.y, 1.0);
+        Vec2::new(v.x, v.y) / v.z
+    }
+
+    /// Maps the given point, or returns `None` if the point is on or behind
+    /// the horizon of a perspective transform.
+    #[inline]
+    fn apply_visible(&self, p: Vec2) -> Option<Vec2> {
+        let v = self.matrix * Vec3::new(p.x, p.y, 1.0);
+        if v.z > 0.0 {
+            Some(Vec2::new(v.x, v.y) / v.z)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarpError {
+    /// The transform cannot be inverted.
+    Singular,
+    /// The source image has a width or height of 0, but the target size doesn't.
+    EmptySource,
+}
+
+/// Resamples the given image with the given transform.
+///
+/// Pixel `(x, y)` of the result is the source image sampled at
+/// `transform.inverse().apply((x + 0.5, y + 0.5))`. The filter kernel is
+/// stretched along the source axes when the transform locally shrinks the
+/// image, so downscaling doesn't alias. [`Filter::Jinc`] uses a radial
+/// (EWA) kernel, all other filters are applied separably.
+///
+/// With [`BorderMode::Transparent`], source pixels outside the image are
+/// ignored and pixels that don't see the source image at all are
+/// `P::default()`.
+pub fn warp<P>(
+    img: ImageView<P>,
+    size: Size,
+    transform: &Transform,
+    filter: &Filter,
+    border: BorderMode<P>,
+) -> Result<Image<P>, WarpError>
+where
+    P: Copy + Default + Send + Sync,
+    FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
+{
+    let inverse = transform.inverse().ok_or(WarpError::Singular)?;
+    if size.is_empty() {
+        return Ok(Image::new(size, Vec::new()));
+    }
+    if img.size().is_empty() {
+        return Err(WarpError::EmptySource);
+    }
+
+    let sampler = Sampler {
+        img,
+        filter,
+        border,
+        format: FloatPixelFormat::<P>::default(),
+    };
+
+    let mut data = vec![P::default(); size.len()];
+    data.par_chunks_exact_mut(size.width)
+        .enumerate()
+        .for_each_init(Weights::default, |weights, (y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let center = match inverse.apply_visible(p) {
+                    Some(center) if center.is_finite() => center,
+                    _ => {
+                        *out = sampler.constant_or_default();
+                        continue;
+                    }
+                };
+
+                // how far the source moves when moving by one pixel in the
+                // destination
+                let dx = inverse.apply(p + Vec2::X) - center;
+                let dy = inverse.apply(p + Vec2::Y) - center;
+                let scale = Vec2::new(
+                    Vec2::new(dx.x, dy.x).length(),
+                    Vec2::new(dx.y, dy.y).length(),
+                );
+                let scale = if scale.is_finite() {
+                    scale.clamp(Vec2::ONE, Vec2::splat(MAX_FILTER_SCALE))
+                } else {
+                    Vec2::ONE
+                };
+
+                *out = sampler.sample(center - 0.5, scale, weights);
+            }
+        });
+
+    Ok(Image::new(size, data))
+}
+
+#[derive(Default)]
+struct Weights {
+    x: Vec<f32>,
+    y: Vec<f32>,
+}
+
+struct Sampler<'a, P> {
+    img: ImageView<'a, P>,
+    filter: &'a Filter,
+    border: BorderMode<P>,
+    format: FloatPixelFormat<P>,
+}
+
+impl<P> Sampler<'_, P>
+where
+    P: Copy + Default,
+    FloatPixelFormat<P>: PixelFormat<InputPixel = P, OutputPixel = P>,
+{
+    fn constant_or_default(&self) -> P {
+        match self.border {
+            BorderMode::Constant(c) => c,
+            _ => P::default(),
+        }
+    }
+
+    #[inline]
+    fn get(&self, x: isize, y: isize) -> Option<P> {
+        self.border.get(self.img, x, y)
+    }
+
+    /// Samples the image at the given position in pixel index coordinates,
+    /// i.e. `(0, 0)` is the center of the top left pixel.
+    fn sample(&self, center: Vec2, scale: Vec2, weights: &mut Weights) -> P {
+        let nearest = || {
+            self.get(center.x.round() as isize, center.y.round() as isize)
+                .unwrap_or_default()
+        };
+
+        if *self.filter == Filter::Nearest {
+            return nearest();
+        }
+
+        let support = self.filter.support();
+        let radius = support * scale;
+        let x0 = (center.x - radius.x).ceil() as isize;
+        let x1 = (center.x + radius.x).floor() as isize;
+        let y0 = (center.y - radius.y).ceil() as isize;
+        let y1 = (center.y + radius.y).floor() as isize;
+        if x1 < x0 || y1 < y0 {
+            return nearest();
+        }
+
+        let mut acc = FloatPixelFormat::<P>::new_acc();
+        let mut sum = 0.0;
+
+        if matches!(self.filter, Filter::Jinc { .. }) {
+            // EWA: the kernel is a function of the (scaled) distance
+            for y in y0..=y1 {
+                let dy = (y as f32 - center.y) / scale.y;
+                for x in x0..=x1 {
+                    let dx = (x as f32 - center.x) / scale.x;
+                    let r = (dx * dx + dy * dy).sqrt();
+                    if r >= support {
+                        continue;
+                    }
+                    if let Some(p) = self.get(x, y) {
+                        let w = self.filter.kernel(r);
+                        self.format.add_pixel_scaled(&mut acc, p, w);
+                        sum += w;
+                    }
+                }
+            }
+        } else {
+            let kernel = |i: isize, c: f32, s: f32| self.filter.kernel((i as f32 - c) / s);
+            weights.x.clear();
+            weights
+                .x
+                .extend((x0..=x1).map(|x| kernel(x, center.x, scale.x)));
+            weights.y.clear();
+            weights
+                .y
+                .extend((y0..=y1).map(|y| kernel(y, center.y, scale.y)));
+
+            for (y, &wy) in (y0..=y1).zip(&weights.y) {
+                if wy == 0.0 {
+                    continue;
+                }
+                for (x, &wx) in (x0..=x1).zip(&weights.x) {
+                    if let Some(p) = self.get(x, y) {
+                        let w = wx * wy;
+                        self.format.add_pixel_scaled(&mut acc, p, w);
+                        sum += w;
+                    }
+                }
+            }
+        }
+
+        if sum == 0.0 {
+            return nearest();
+        }
+        self.format.acc_to_pixel_scaled(acc, 1.0 / sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Affine2, Mat3, Vec2, Vec4};
+    use image_core::{Image, Size};
+    use test_util::{
+        data::{read_abstract_transparent, read_portrait},
+        snap::ImageSnapshot,
+    };
+
+    use super::{Transform, WarpError};
+    use crate::{
+        scale::{scale, Filter},
+        util::BorderMode,
+    };
+
+    #[test]
+    fn identity() {
+        let original = read_portrait();
+        for filter in [Filter::Nearest, Filter::Linear, Filter::CubicCatrom] {
+            let result = super::warp(
+                original.view(),
+                original.size(),
+                &Transform::IDENTITY,
+                &filter,
+                BorderMode::Clamp,
+            )
+            .unwrap();
+            let max_diff = result
+                .data()
+                .iter()
+                .zip(original.data())
+                .map(|(a, b)| (*a - *b).abs().max_element())
+                .fold(0.0, f32::max);
+            assert!(max_diff < 1e-5, "{:?}: {}", filter, max_diff);
+        }
+    }
+
+    #[test]
+    fn matches_scale() {
+        let original = read_portrait();
+        let size = original.size().scale(0.5);
+        let min = Vec2::ZERO;
+        let max = Vec2::new(original.width() as f32, original.height() as f32);
+
+        let filter = Filter::CubicCatrom;
+        let warped = super::warp(
+            original.view(),
+            size,
+            &Transform::from_rect(min, max, size),
+            &filter,
+            BorderMode::Clamp,
+        )
+        .unwrap();
+        let scaled = scale(original.view(), size, filter, BorderMode::Clamp).unwrap();
+
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let diff = (warped.row(y)[x] - scaled.row(y)[x]).abs().max_element();
+                assert!(diff < 1e-4, "({}, {}): {}", x, y, diff);
+            }
+        }
+    }
+
+    #[test]
+    fn rotate() {
+        let original = read_portrait();
+        let size = original.size();
+        let center = Vec2::new(size.width as f32, size.height as f32) / 2.0;
+        let transform = Transform::affine(
+            Affine2::from_translation(center)
+                * Affine2::from_angle(30_f32.to_radians())
+                * Affine2::from_translation(-center),
+        );
+
+        let border = BorderMode::Constant(glam::Vec3A::ZERO);
+        super::warp(original.view(), size, &transform, &Filter::Linear, border)
+            .unwrap()
+            .snapshot("warp_rotate_linear");
+        super::warp(
+            original.view(),
+            size.scale(0.5),
+            &transform.then(Transform::from_rect(
+                Vec2::ZERO,
+                center * 2.0,
+                size.scale(0.5),
+            )),
+            &Filter::Jinc { lobes: 3 },
+            BorderMode::Reflect,
+        )
+        .unwrap()
+        .snapshot("warp_rotate_jinc_half");
+    }
+
+    #[test]
+    fn perspective() {
+        let original = read_abstract_transparent();
+        let size = Size::new(400, 300);
+        let transform =
+            Transform::from_rows([[0.4, 0.05, 20.0], [0.02, 0.4, 10.0], [0.0004, 0.0002, 1.0]]);
+
+        super::warp(
+            original.view(),
+            size,
+            &transform,
+            &Filter::CubicMitchell,
+            BorderMode::Wrap,
+        )
+        .unwrap()
+        .snapshot("warp_perspective_wrap");
+    }
+
+    #[test]
+    fn errors() {
+        let img = Image::from_const(Size::new(4, 4), Vec4::ONE);
+        let singular = Transform::perspective(Mat3::ZERO);
+        assert_eq!(
+            super::warp(
+                img.view(),
+                img.size(),
+                &singular,
+                &Filter::Linear,
+                BorderMode::Clamp
+            )
+            .unwrap_err(),
+            WarpError::Singular
+        );
+
+        // sub-pixel shifts keep constant images constant
+        let shifted = super::warp(
+            img.view(),
+            img.size(),
+            &Transform::translate(Vec2::new(0.5, -0.25)),
+            &Filter::Lanczos3,
+            BorderMode::Clamp,
+        )
+        .unwrap();
+        assert!(shifted
+            .data()
+            .iter()
+            .all(|p| (*p - Vec4::ONE).abs().max_element() < 1e-5));
+    }
+}
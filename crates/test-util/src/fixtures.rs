@@ -0,0 +1,45 @@
+use image_core::{FromFlat, Image, NDimImage, Size};
+
+/// A small image with an irregular pattern of values in `0..=1`.
+///
+/// This is useful to compare optimized implementations against naive ones.
+/// Each channel has a different pattern.
+pub fn pattern<P: FromFlat<Sample = f32>>(size: Size) -> Image<P> {
+    let channels = P::COMPONENTS;
+    let data = (0..size.height)
+        .flat_map(|y| (0..size.width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| (0..channels).map(move |c| (x * 7 + y * 13 + x * y + c * 5) % 11))
+        .map(|v| v as f32 / 10.0)
+        .collect();
+    match P::from_flat_vec(data, channels) {
+        Ok(pixels) => Image::new(size, pixels),
+        Err(_) => unreachable!("the number of channels always matches"),
+    }
+}
+
+/// Parses an image from rows of characters.
+///
+/// `.` is 0, `#` is 1, and the digits `0` to `9` are 0.0 to 0.9.
+pub fn parse_image(rows: &[&str]) -> Image<f32> {
+    let size = Size::new(rows[0].len(), rows.len());
+    let data = rows
+        .iter()
+        .flat_map(|r| r.chars())
+        .map(|c| match c {
+            '.' => 0.0,
+            '#' => 1.0,
+            _ => c.to_digit(10).expect("a digit, '.', or '#'") as f32 / 10.0,
+        })
+        .collect();
+    Image::new(size, data)
+}
+
+/// Asserts that both images have the same shape and that all of their samples
+/// differ by less than `epsilon`.
+pub fn assert_close(actual: impl Into<NDimImage>, expected: impl Into<NDimImage>, epsilon: f32) {
+    let (actual, expected) = (actual.into(), expected.into());
+    assert_eq!(actual.shape(), expected.shape());
+    for (a, b) in actual.data().iter().zip(expected.data()) {
+        assert!((a - b).abs() < epsilon, "{} != {}", a, b);
+    }
+}
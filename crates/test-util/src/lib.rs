@@ -1,2 +1,3 @@
 pub mod data;
+pub mod fixtures;
 pub mod snap;